@@ -1,4 +1,8 @@
-use std::ops::{ControlFlow, Range};
+use std::{
+    collections::HashMap,
+    ops::{ControlFlow, Range},
+    path::Path,
+};
 
 #[allow(unused_imports)] // Keeping this Anyhow import as Context would clash with egui
 use anyhow::Context as _;
@@ -10,22 +14,31 @@ use egui::{
 use itertools::Itertools;
 use log::{error, info};
 use platform_type_selector::PlatformTypeSelector;
-use rustsat::{
-    solvers::{Interrupt, Solve, SolveStats, SolverResult},
-    types::{Assignment, TernaryVal},
-};
+use rustsat::solvers::SolverResult;
 use timberborn_platform_cruncher::{
-    encoder,
-    encoder::{Encoding, PlatformLayout, PlatformLimits},
-    math::{Dimensions, Grid, Point},
+    encoder::{
+        Encoding, PlatformLayout, PlatformLimits,
+        diagnostics::{Diagnostic, Severity},
+        layout_diff::{LayoutDiff, PlatformChange, diff_layouts},
+        layout_file::LayoutFile,
+        merging::merge_platforms,
+        redundancy::{PruneStrategy, remove_redundant_platforms},
+        support_forest::SupportForest,
+        support_map::{MAX_SUPPORT_DISTANCE, SupportMap},
+    },
+    math::{Dimensions, Grid, Point, Rotation, Transform},
     platform::PlatformDef,
     platform_def,
+    solver::{
+        SolverConfig, SolverKind, alternatives::AlternativesOptions, external::ExternalSolver,
+        minimize::limit_objective, progress::Progress,
+    },
     world::{World, WorldGrid},
 };
 
 use crate::{
     app::frame_history::FrameHistory,
-    solver_backend::{SolverBackend, SolverResponse, SolverSession},
+    solver_backend::{AlternativesSession, SolverBackend, SolverResponse, SolverSession},
 };
 
 mod frame_history;
@@ -36,15 +49,34 @@ struct TerrainTile {
     terrain: bool,
 }
 
-pub struct App<S>
-where
-    S: Interrupt,
-{
+pub struct App {
     terrain_grid: Grid<TerrainTile>,
     resize_modal: ResizeModal,
     backend: SolverBackend,
-    active_session: Option<SolverSession<S>>,
+    active_session: Option<SolverSession>,
+    /// `None` runs [`Self::external_command`] instead of a built-in backend
+    solver_kind: Option<SolverKind>,
+    external_command: String,
+    /// File to save the displayed layout to, or load one from
+    layout_path: String,
     displayed_layout: Option<PlatformLayout>,
+    /// The layout displayed before the last solve or load, to compare against
+    diff_base: Option<PlatformLayout>,
+    show_diff: bool,
+    /// Color terrain by its support distance in the displayed layout
+    show_support_map: bool,
+    /// How the tile under the pointer is supported, see [`Self::draw_terrain_grid_ui`]
+    hover_info: Option<String>,
+    /// Number of optimal layouts to look for once the optimum is found
+    alternatives_count: usize,
+    /// Solve for layouts that survive losing any one platform
+    fault_tolerant: bool,
+    /// Tiles added on each side by "Pad"
+    pad_amount: usize,
+    alternatives_session: Option<AlternativesSession>,
+    /// Optimal layouts to flip through, and the displayed one
+    alternatives: Vec<PlatformLayout>,
+    alternative_index: usize,
     layout_stats: PlatformLayoutStats,
     frame_history: FrameHistory,
     platform_type_selector: PlatformTypeSelector,
@@ -61,10 +93,7 @@ const DEFAULT_PLATFORMS: [(PlatformDef, isize); 8] = [
     (platform_def!(5, 5), 4),
 ];
 
-impl<S> App<S>
-where
-    S: Interrupt,
-{
+impl App {
     pub fn new(cc: &eframe::CreationContext<'_>, mut backend: SolverBackend) -> Self {
         let terrain_grid = Grid::new(Dimensions::new(24, 24));
         backend.set_egui_ctx(cc.egui_ctx.clone());
@@ -74,7 +103,20 @@ where
             resize_modal: Default::default(),
             backend,
             active_session: None,
+            solver_kind: SolverKind::default_enabled(),
+            external_command: String::new(),
+            layout_path: "layout.toml".to_owned(),
             displayed_layout: None,
+            diff_base: None,
+            show_diff: false,
+            show_support_map: false,
+            hover_info: None,
+            alternatives_count: 1,
+            fault_tolerant: false,
+            pad_amount: 1,
+            alternatives_session: None,
+            alternatives: vec![],
+            alternative_index: 0,
             frame_history: FrameHistory::default(),
             layout_stats: PlatformLayoutStats::new(5..100, 5.0),
             platform_type_selector: PlatformTypeSelector::with_defaults(DEFAULT_PLATFORMS),
@@ -82,7 +124,15 @@ where
     }
 
     fn draw_terrain_grid_ui(&mut self, ui: &mut Ui) -> Response {
-        ui.scope_builder(UiBuilder::new().sense(Sense::drag()), |ui| {
+        let diagnostics = self.diagnostics();
+        let diff = self.diff().filter(|_| self.show_diff);
+        let support_map = self.support_map().filter(|_| self.show_support_map);
+        let forest = self
+            .displayed_layout
+            .as_ref()
+            .map(|layout| SupportForest::new(layout, &self.make_world()));
+        let mut hover_info = None;
+        let response = ui.scope_builder(UiBuilder::new().sense(Sense::drag()), |ui| {
             egui::Frame::canvas(ui.style()).show(ui, |ui| {
                 let corner_point = ui.next_widget_position();
 
@@ -105,14 +155,24 @@ where
                 ui.set_width(grid_vec.x);
                 ui.set_height(grid_vec.y);
 
+                let removed = self.displayed_layout.as_ref().map(PlatformLayout::removed_tiles);
                 for (point, tile) in self.terrain_grid.enumerate() {
+                    let color = match &support_map {
+                        _ if !tile.terrain => Color32::WHITE,
+                        // Terrain to dig out
+                        _ if removed.is_some_and(|removed| removed.contains(&point)) => {
+                            REMOVED_TILE_COLOR
+                        }
+                        Some(map) => support_color(map.distances.get(point).copied().flatten()),
+                        None => Color32::BROWN,
+                    };
                     ui.painter().rect_filled(
                         Rect::from_two_pos(
                             corner_point + get_offset(point, Vec2::ZERO),
                             corner_point + get_offset(point, Vec2::ONE),
                         ),
                         0,
-                        if tile.terrain { Color32::BROWN } else { Color32::WHITE },
+                        color,
                     );
                 }
 
@@ -140,22 +200,113 @@ where
                         );
                     }
                 }
+
+                // Outline the changes since the diff base, and where removed or
+                // moved platforms used to be
+                for change in diff.iter().flat_map(|diff| diff.changes.iter()) {
+                    let (plat, color) = match *change {
+                        PlatformChange::Added(plat) => (plat, Color32::GREEN),
+                        PlatformChange::Removed(plat) => (plat, Color32::RED),
+                        PlatformChange::Moved { from, to } => {
+                            let from_color = Color32::RED * Color32::from_white_alpha(128);
+                            if let Some((a, b)) = from.area_corners() {
+                                let rect = Rect::from_two_pos(
+                                    corner_point + get_offset(a, Vec2::ZERO),
+                                    corner_point + get_offset(b, Vec2::ONE),
+                                );
+                                ui.painter().rect_stroke(
+                                    rect,
+                                    0,
+                                    Stroke::new(1f32, from_color),
+                                    StrokeKind::Inside,
+                                );
+                            }
+                            (to, Color32::LIGHT_BLUE)
+                        }
+                        PlatformChange::Resized { to, .. } => (to, Color32::YELLOW),
+                    };
+                    let Some((a, b)) = plat.area_corners() else {
+                        continue;
+                    };
+                    let rect = Rect::from_two_pos(
+                        corner_point + get_offset(a, Vec2::ZERO),
+                        corner_point + get_offset(b, Vec2::ONE),
+                    );
+                    ui.painter().rect_stroke(rect, 0, Stroke::new(2f32, color), StrokeKind::Inside);
+                }
+
+                // Trace the support of the hovered tile to its platform, and shade
+                // everything else that platform holds up
+                let hovered = ui.ctx().pointer_hover_pos().and_then(|pos| {
+                    let rel = (pos - corner_point) / (tile_size + spacing);
+                    let p = Point::new(rel.x.floor() as isize, rel.y.floor() as isize);
+                    self.terrain_grid.dims().contains(p).then_some(p)
+                });
+                if let Some((p, forest)) = hovered.zip(forest.as_ref())
+                    && let Some(path) = forest.path(p)
+                {
+                    for &tile in forest.held_up_by()[&path.platform].iter() {
+                        ui.painter().rect_filled(
+                            Rect::from_two_pos(
+                                corner_point + get_offset(tile, Vec2::ZERO),
+                                corner_point + get_offset(tile, Vec2::ONE),
+                            ),
+                            0,
+                            Color32::from_white_alpha(60),
+                        );
+                    }
+                    let center = |tile| corner_point + get_offset(tile, vec2(0.5, 0.5));
+                    for (&a, &b) in path.tiles.iter().tuple_windows() {
+                        ui.painter()
+                            .line_segment([center(a), center(b)], Stroke::new(3f32, Color32::WHITE));
+                    }
+                    ui.painter().circle_filled(center(p), tile_size / 4.0, Color32::WHITE);
+
+                    let plat = path.platform;
+                    hover_info = Some(format!(
+                        "Tile ({};{}) is held up by the {}x{} platform at ({};{}), {} steps away; \
+                         it holds up {} tiles",
+                        p.x,
+                        p.y,
+                        plat.dims().width,
+                        plat.dims().height,
+                        plat.point().x,
+                        plat.point().y,
+                        path.len(),
+                        forest.held_up_by()[&plat].len()
+                    ));
+                }
+
+                // Outline everything the diagnostics point at
+                for diagnostic in diagnostics.iter() {
+                    let stroke = Stroke::new(2f32, severity_color(diagnostic.severity));
+                    let areas =
+                        diagnostic.points.iter().map(|&p| (p, p)).chain(
+                            diagnostic.platforms.iter().filter_map(|plat| plat.area_corners()),
+                        );
+                    for (a, b) in areas {
+                        let rect = Rect::from_two_pos(
+                            corner_point + get_offset(a, Vec2::ZERO),
+                            corner_point + get_offset(b, Vec2::ONE),
+                        );
+                        ui.painter().rect_stroke(rect, 0, stroke, StrokeKind::Inside);
+                    }
+                }
             })
         })
-        .response
+        .response;
+        self.hover_info = hover_info;
+        response
     }
 
-    fn try_get_current_session_results(&mut self) -> Option<SolverSessionResult<S>>
-    where
-        S: Solve + SolveStats,
-    {
+    fn try_get_current_session_results(&mut self) -> Option<SolverSessionResult> {
         let resp = SolverSession::try_recv(&mut self.active_session)?;
         match resp.result {
             Ok(SolverResult::Sat) => match resp.solver.full_solution() {
                 Ok(asgn) => {
                     let mut layout = PlatformLayout::from_assignment(&asgn, resp.encoding.vars());
                     layout.run_trivial_optimization(&self.make_world());
-                    Some(SolverSessionResult::Sat { layout, response: resp, assignment: asgn })
+                    Some(SolverSessionResult::Sat { layout, response: Box::new(resp) })
                 }
                 Err(err) => {
                     error!("Failed to get assignment, but the solver reported SAT: {err:?}");
@@ -173,42 +324,186 @@ where
             }
         }
     }
-    fn start_solver(&mut self, limits: PlatformLimits)
-    where
-        S: Solve + Default + Send + 'static,
-    {
-        let world = self.make_world();
+    fn encode(&self) -> Encoding {
         let encoding = Encoding::encode(
             &self.platform_type_selector.active_platform_defs().map(|(def, _)| def).collect_vec(),
-            world.grid(),
+            self.make_world().grid(),
         );
+        if self.fault_tolerant { encoding.with_fault_tolerance() } else { encoding }
+    }
 
-        self.active_session.take().map(|mut session| session.interrupt());
+    fn solver_config(&self) -> Option<SolverConfig> {
+        match self.solver_kind {
+            Some(kind) => Some(SolverConfig::Builtin(kind)),
+            None => match ExternalSolver::parse(&self.external_command) {
+                Some(command) => Some(SolverConfig::External(command)),
+                None => {
+                    error!("No external solver command set");
+                    None
+                }
+            },
+        }
+    }
+
+    fn start_solver(&mut self, limits: PlatformLimits) {
+        let encoding = self.encode();
+        let Some(solver_config) = self.solver_config() else {
+            return;
+        };
+
+        if let Some(mut session) = self.active_session.take() {
+            session.interrupt();
+        }
         self.active_session = self
             .backend
-            .start(encoding, limits)
+            .start(encoding, limits, &solver_config)
             .map_err(|err| {
                 error!("Failed to start solver: {err}");
             })
             .ok();
     }
 
+    /// Looks for more layouts as good as the displayed one, which is optimal.
+    fn start_alternatives(&mut self) {
+        let Some(first) = self.displayed_layout.clone() else {
+            return;
+        };
+        let Some(solver_config) = self.solver_config() else {
+            return;
+        };
+        let weights: HashMap<_, _> = self.platform_type_selector.active_platform_defs().collect();
+        let optimum = first.total_weight(&weights);
+        let mut limits = PlatformLimits::new_with_weights(Default::default(), weights, None);
+        limit_objective(&mut limits, optimum);
+
+        self.alternatives_session = Some(self.backend.start_alternatives(
+            self.encode(),
+            limits,
+            &solver_config,
+            first,
+            AlternativesOptions { count: self.alternatives_count, min_distance: 1 },
+        ));
+    }
+
+    fn show_alternative(&mut self, index: usize) {
+        self.alternative_index = index;
+        self.displayed_layout = self.alternatives.get(index).cloned();
+    }
+
+    fn save_layout(&self) -> anyhow::Result<()> {
+        let Some(layout) = &self.displayed_layout else {
+            anyhow::bail!("No layout to save");
+        };
+        LayoutFile::new(layout.clone(), &self.make_world()).save(Path::new(&self.layout_path))?;
+        info!("Saved the layout to {}", self.layout_path);
+        Ok(())
+    }
+
+    /// Loads a layout for the current terrain, which has to have the same
+    /// dimensions. Validation problems show up as diagnostics.
+    fn load_layout(&mut self) -> anyhow::Result<()> {
+        let file = LayoutFile::read(Path::new(&self.layout_path))?;
+        file.check(&self.make_world())?;
+        info!("Loaded a layout with {} platforms", file.layout.platform_count());
+        self.alternatives.clear();
+        self.diff_base = self.displayed_layout.replace(file.layout);
+        Ok(())
+    }
+
+    /// Changes from the diff base to the displayed layout.
+    fn diff(&self) -> Option<LayoutDiff> {
+        Some(diff_layouts(self.diff_base.as_ref()?, self.displayed_layout.as_ref()?))
+    }
+
+    /// Removes redundant platforms from the displayed layout, which can be
+    /// compared to the layout before with "Show changes".
+    fn prune_layout(&mut self, strategy: PruneStrategy) -> anyhow::Result<()> {
+        let Some(mut layout) = self.displayed_layout.clone() else {
+            anyhow::bail!("No layout to prune");
+        };
+        let removed = remove_redundant_platforms(&mut layout, &self.make_world(), strategy)?;
+        info!("Removed {} redundant platforms", removed.len());
+        self.alternatives.clear();
+        self.diff_base = self.displayed_layout.replace(layout);
+        Ok(())
+    }
+
+    /// Merges small platforms of the displayed layout into larger ones, where
+    /// that lowers the total weight of the active platform types.
+    fn merge_layout(&mut self) {
+        let Some(mut layout) = self.displayed_layout.clone() else {
+            return;
+        };
+        let (defs, weights): (Vec<_>, HashMap<_, _>) = self
+            .platform_type_selector
+            .active_platform_defs()
+            .map(|(def, weight)| (def, (def, weight)))
+            .unzip();
+        let merges = merge_platforms(&mut layout, &self.make_world(), &defs, Some(&weights));
+        info!("Made {} merges", merges.len());
+        self.alternatives.clear();
+        self.diff_base = self.displayed_layout.replace(layout);
+    }
+
+    /// Transforms the terrain along with the displayed, previous and
+    /// alternative layouts. Does nothing if no terrain would be left.
+    fn transform(&mut self, transform: Transform) {
+        let dims = self.terrain_grid.dims();
+        if transform.dims(dims).empty() {
+            return;
+        }
+        self.terrain_grid = self.terrain_grid.transformed(transform, TerrainTile::default());
+        for layout in self
+            .displayed_layout
+            .iter_mut()
+            .chain(self.diff_base.iter_mut())
+            .chain(self.alternatives.iter_mut())
+        {
+            *layout = layout.transformed(transform, dims);
+        }
+        info!("Applied {transform}");
+    }
+
+    /// Crops to the smallest area holding all terrain, if there's any.
+    fn crop_to_terrain(&self) -> Option<Transform> {
+        let terrain = self
+            .terrain_grid
+            .enumerate()
+            .filter(|(_, tile)| tile.terrain)
+            .map(|(p, _)| p)
+            .collect_vec();
+        let (min_x, max_x) = terrain.iter().map(|p| p.x).minmax().into_option()?;
+        let (min_y, max_y) = terrain.iter().map(|p| p.y).minmax().into_option()?;
+        Some(Transform::Crop {
+            origin: Point::new(min_x, min_y),
+            dims: Dimensions::new((max_x - min_x + 1) as usize, (max_y - min_y + 1) as usize),
+        })
+    }
+
+    fn support_map(&self) -> Option<SupportMap> {
+        Some(SupportMap::new(self.displayed_layout.as_ref()?, &self.make_world()))
+    }
+
+    /// Validation findings for the displayed layout.
+    fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.displayed_layout
+            .as_ref()
+            .map(|layout| layout.validate(&self.make_world()).diagnostics())
+            .unwrap_or_default()
+    }
+
     fn make_world(&self) -> World {
         let world_grid = WorldGrid(self.terrain_grid.iter_map(|tile| tile.terrain));
         World::new(world_grid)
     }
 }
 
-#[derive(Debug)]
-enum SolverSessionResult<S> {
-    Sat { layout: PlatformLayout, response: SolverResponse<S>, assignment: Assignment },
+enum SolverSessionResult {
+    Sat { layout: PlatformLayout, response: Box<SolverResponse> },
     Unsat,
 }
 
-impl<S> eframe::App for App<S>
-where
-    S: Interrupt + Solve + SolveStats + Default + Send + 'static,
-{
+impl eframe::App for App {
     fn update(&mut self, ctx: &Context, frame: &mut Frame) {
         self.frame_history.on_new_frame(ctx.input(|i| i.time), frame.info().cpu_usage);
 
@@ -216,12 +511,13 @@ where
             None => {}
             Some(SolverSessionResult::Unsat) => {
                 info!("Unsat");
+                // The displayed layout is optimal
+                if self.alternatives_count > 1 {
+                    self.start_alternatives();
+                }
             }
-            Some(SolverSessionResult::Sat {
-                layout,
-                response: SolverResponse { mut limits, .. },
-                ..
-            }) => {
+            Some(SolverSessionResult::Sat { layout, response }) => {
+                let mut limits = response.limits;
                 // info!("Sat\n{layout:#?}");
                 info!("Sat");
                 // if let Some(platform_count_limit) = layout.platform_count().checked_sub(1) {
@@ -248,7 +544,48 @@ where
             }
         };
 
+        match AlternativesSession::try_recv(&mut self.alternatives_session) {
+            None => {}
+            Some(Ok(alternatives)) => {
+                info!("Found {} optimal layouts", alternatives.layouts.len());
+                let world = self.make_world();
+                self.alternatives = alternatives.layouts;
+                for layout in self.alternatives.iter_mut() {
+                    layout.run_trivial_optimization(&world);
+                }
+                self.show_alternative(0);
+            }
+            Some(Err(err)) => {
+                error!("Failed to find alternative layouts: {err:?}");
+            }
+        }
+
         egui::SidePanel::left("left panel").show(ctx, |ui| {
+            ui.add_enabled_ui(self.active_session.is_none(), |ui| {
+                egui::ComboBox::from_label("Solver")
+                    .selected_text(self.solver_kind.map_or("external", SolverKind::name))
+                    .show_ui(ui, |ui| {
+                        for kind in SolverKind::iter_enabled() {
+                            ui.selectable_value(&mut self.solver_kind, Some(kind), kind.name());
+                        }
+                        ui.selectable_value(&mut self.solver_kind, None, "external");
+                    });
+                if self.solver_kind.is_none() {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.external_command)
+                            .hint_text("Solver command, e.g. kissat -q"),
+                    );
+                }
+            });
+            ui.add_enabled_ui(self.active_session.is_none(), |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Optimal layouts");
+                    ui.add(DragValue::new(&mut self.alternatives_count).range(1..=100));
+                });
+                ui.checkbox(&mut self.fault_tolerant, "Fault tolerant")
+                    .on_hover_text("Hold up all terrain with at least two platforms");
+            });
+            ui.separator();
             self.platform_type_selector.ui(ui);
         });
 
@@ -258,10 +595,70 @@ where
             if ui.button("Resize grid").clicked() {
                 self.resize_modal.open(self.terrain_grid.dims());
             }
+            ui.add_enabled_ui(self.active_session.is_none(), |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.layout_path).hint_text("Layout file"),
+                    );
+                    if ui.button("Save layout").clicked()
+                        && let Err(err) = self.save_layout()
+                    {
+                        error!("Failed to save the layout: {err:?}");
+                    }
+                    if ui.button("Load layout").clicked()
+                        && let Err(err) = self.load_layout()
+                    {
+                        error!("Failed to load the layout: {err:?}");
+                    }
+                });
+                ui.horizontal(|ui| {
+                    for (label, strategy) in [
+                        ("Prune", PruneStrategy::Greedy),
+                        ("Prune (optimal)", PruneStrategy::Optimal),
+                    ] {
+                        if ui.button(label).clicked()
+                            && let Err(err) = self.prune_layout(strategy)
+                        {
+                            error!("Failed to prune the layout: {err:?}");
+                        }
+                    }
+                    if ui.button("Merge").clicked() {
+                        self.merge_layout();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    let mut transform = None;
+                    for (label, clicked) in [
+                        ("Rotate right", Transform::Rotate(Rotation::Cw90)),
+                        ("Rotate left", Transform::Rotate(Rotation::Cw270)),
+                        ("Mirror ↔", Transform::MirrorLeftRight),
+                        ("Mirror ↕", Transform::MirrorTopBottom),
+                        ("←", Transform::Translate(Point::new(-1, 0))),
+                        ("→", Transform::Translate(Point::new(1, 0))),
+                        ("↑", Transform::Translate(Point::new(0, -1))),
+                        ("↓", Transform::Translate(Point::new(0, 1))),
+                    ] {
+                        if ui.button(label).clicked() {
+                            transform = Some(clicked);
+                        }
+                    }
+                    if ui.button("Crop to terrain").clicked() {
+                        transform = self.crop_to_terrain();
+                    }
+                    if ui.button("Pad").clicked() {
+                        let n = self.pad_amount;
+                        transform = Some(Transform::Pad { left: n, top: n, right: n, bottom: n });
+                    }
+                    ui.add(DragValue::new(&mut self.pad_amount).range(1..=50));
+                    if let Some(transform) = transform {
+                        self.transform(transform);
+                    }
+                });
+            });
             if let ControlFlow::Break(Some(new_dims)) = self.resize_modal.ui(ui)
                 && !new_dims.empty()
             {
-                self.terrain_grid = Grid::new(new_dims); // TODO copy old
+                self.transform(Transform::Crop { origin: Point::new(0, 0), dims: new_dims });
             }
             debug_assert!(!self.terrain_grid.dims().empty());
 
@@ -292,7 +689,8 @@ where
             }
 
             ui.horizontal(|ui| {
-                let is_running = self.active_session.is_some();
+                let is_running =
+                    self.active_session.is_some() || self.alternatives_session.is_some();
                 let button_text = if !is_running {
                     RichText::new("Solve").color(Color32::GREEN)
                 } else {
@@ -309,9 +707,16 @@ where
                             None,
                         );
                         self.layout_stats.clear();
+                        self.alternatives.clear();
+                        self.diff_base = self.displayed_layout.clone();
                         self.start_solver(limits);
                     } else {
-                        self.active_session.take().map(|mut session| session.interrupt());
+                        if let Some(mut session) = self.active_session.take() {
+                            session.interrupt();
+                        }
+                        if let Some(mut session) = self.alternatives_session.take() {
+                            session.interrupt();
+                        }
                     }
                 }
 
@@ -332,14 +737,150 @@ where
                     });
                 }
             });
+
+            if self.alternatives.len() > 1 {
+                ui.horizontal(|ui| {
+                    let n = self.alternatives.len();
+                    if ui.button("◀").clicked() {
+                        self.show_alternative((self.alternative_index + n - 1) % n);
+                    }
+                    ui.label(format!("Layout {} of {n}", self.alternative_index + 1));
+                    if ui.button("▶").clicked() {
+                        self.show_alternative((self.alternative_index + 1) % n);
+                    }
+                });
+            }
+
+            if let Some(progress) = self.active_session.as_mut().and_then(|s| s.progress()) {
+                solver_stats_ui(ui, progress);
+            }
+
+            if let Some(diff) = self.diff() {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.show_diff, "Show changes");
+                    ui.label(diff.to_string());
+                });
+            }
+
+            if let Some(info) = &self.hover_info {
+                ui.label(info);
+            }
+
+            if let Some(map) = self.support_map() {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.show_support_map, "Support heatmap");
+                    let max = map.max.map_or("-".to_owned(), |max| max.to_string());
+                    ui.label(format!("Max distance {max}, {} critical tiles", map.critical.len()));
+                });
+            }
+
+            if let Some(layout) = &self.displayed_layout
+                && !layout.removed_tiles().is_empty()
+            {
+                ui.colored_label(
+                    REMOVED_TILE_COLOR,
+                    format!("{} tiles to dig out", layout.removed_tiles().len()),
+                );
+            }
+
+            if let Some(layout) = &self.displayed_layout {
+                let result = layout.validate_fault_tolerance(&self.make_world());
+                if result.is_fault_tolerant() {
+                    ui.label("Survives losing any one platform");
+                } else {
+                    ui.label(format!(
+                        "{} platforms are the only support of some terrain",
+                        result.single_points_of_failure.len()
+                    ));
+                }
+            }
+
+            let diagnostics = self.diagnostics();
+            if !diagnostics.is_empty() {
+                diagnostics_ui(ui, &diagnostics);
+            }
         });
     }
 
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
-        self.active_session.take().map(|mut session| session.interrupt());
+        if let Some(mut session) = self.active_session.take() {
+            session.interrupt();
+        }
+        if let Some(mut session) = self.alternatives_session.take() {
+            session.interrupt();
+        }
     }
 }
 
+/// Live stats of the running solver call, to tell a stuck solve from a working
+/// one.
+fn solver_stats_ui(ui: &mut Ui, progress: &Progress) {
+    egui::CollapsingHeader::new("Solver stats").default_open(true).show(ui, |ui| {
+        egui::Grid::new("solver stats").num_columns(2).show(ui, |ui| {
+            ui.label("Elapsed");
+            ui.label(format!("{:.1?}", progress.elapsed));
+            ui.end_row();
+
+            let search = progress.search_stats;
+            ui.label("Conflicts");
+            ui.label(
+                search
+                    .map(|s| s.conflicts)
+                    .or(progress.conflicts)
+                    .map_or("n/a".to_owned(), |c| c.to_string()),
+            );
+            ui.end_row();
+
+            ui.label("Decisions");
+            ui.label(search.map_or("n/a".to_owned(), |s| s.decisions.to_string()));
+            ui.end_row();
+
+            ui.label("Propagations");
+            ui.label(search.map_or("n/a".to_owned(), |s| s.propagations.to_string()));
+            ui.end_row();
+
+            ui.label("Clauses");
+            ui.label(progress.solver_stats.n_clauses.to_string());
+            ui.end_row();
+        });
+    });
+}
+
+/// Terrain the displayed layout digs out rather than supports.
+const REMOVED_TILE_COLOR: Color32 = Color32::from_rgb(222, 196, 150);
+
+/// Green to yellow while there's slack, orange at the limit, red past it.
+fn support_color(distance: Option<usize>) -> Color32 {
+    match distance {
+        Some(d) if d < MAX_SUPPORT_DISTANCE => {
+            let t = d as f32 / MAX_SUPPORT_DISTANCE as f32;
+            Color32::from_rgb((t * 255.0) as u8, 200, 60)
+        }
+        Some(MAX_SUPPORT_DISTANCE) => Color32::ORANGE,
+        Some(_) => Color32::RED,
+        None => Color32::DARK_RED,
+    }
+}
+
+fn severity_color(severity: Severity) -> Color32 {
+    match severity {
+        Severity::Error => Color32::RED,
+        Severity::Warning => Color32::ORANGE,
+    }
+}
+
+/// Validation findings of the displayed layout, which are also outlined on the
+/// grid.
+fn diagnostics_ui(ui: &mut Ui, diagnostics: &[Diagnostic]) {
+    egui::CollapsingHeader::new(format!("Diagnostics ({})", diagnostics.len()))
+        .default_open(true)
+        .show(ui, |ui| {
+            for diagnostic in diagnostics {
+                ui.colored_label(severity_color(diagnostic.severity), diagnostic.to_string());
+            }
+        });
+}
+
 fn try_parse_platform_def(input: &str) -> Option<PlatformDef> {
     let (a, b) = input.trim().split_once('x')?;
     let (a, b) = (a.trim().parse().ok()?, b.trim().parse().ok()?);
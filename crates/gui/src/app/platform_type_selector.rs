@@ -3,7 +3,7 @@ use std::{cmp::Ordering, collections::BTreeMap};
 use eframe::epaint::Color32;
 use egui::{DragValue, RichText, TextEdit, Ui, Widget};
 use log::info;
-use timberborn_platform_cruncher::{math::Dimensions, platform::PlatformDef};
+use timberborn_platform_cruncher::platform::PlatformDef;
 
 use crate::app;
 
@@ -96,17 +96,9 @@ impl PlatformTypeSelector {
             // Items are removed by returning false from the closure
             self.platform_defs.retain(|def, data| {
                 let dims = def.0.dims();
-                // Disable removal/deactivation for 1x1 platforms,
-                // as a lot of logic assumes that they're always available
-                let disable_controls = dims != Dimensions::new(1, 1);
                 ui.horizontal(|ui| {
-                    let should_keep = ui
-                        .add_enabled_ui(disable_controls, |ui| {
-                            let should_keep = !ui.button("X").clicked();
-                            ui.checkbox(&mut data.active, egui::Atom::default());
-                            should_keep
-                        })
-                        .inner;
+                    let should_keep = !ui.button("X").clicked();
+                    ui.checkbox(&mut data.active, egui::Atom::default());
                     let label_text = RichText::new(format!("{}x{}", dims.width(), dims.height()))
                         .color(
                             // Highlight if a duplicate entry warning is being shown
@@ -1,6 +1,3 @@
-use rustsat::solvers::{Interrupt, InterruptSolver, Solve};
-use rustsat_glucose::simp::Glucose as GlucoseSimp;
-
 use crate::solver_backend::SolverBackend;
 
 mod app;
@@ -23,7 +20,7 @@ fn main() -> anyhow::Result<()> {
     eframe::run_native(
         "PlatformCruncher",
         native_options,
-        Box::new(|cc| Ok(Box::new(app::App::<GlucoseSimp>::new(cc, backend)))),
+        Box::new(|cc| Ok(Box::new(app::App::new(cc, backend)))),
     )
     .expect("Error while running frontend");
 
@@ -1,28 +1,37 @@
+use std::{sync::mpsc, time::Duration};
+
 use log::warn;
-use rustsat::solvers::{Interrupt, InterruptSolver, Solve, SolverResult};
-use timberborn_platform_cruncher::encoder::{Encoding, PlatformLimits};
+use rustsat::solvers::SolverResult;
+use timberborn_platform_cruncher::{
+    encoder::{Encoding, PlatformLayout, PlatformLimits},
+    solver::{
+        DynSolver, SolverConfig,
+        alternatives::{Alternatives, AlternativesOptions, enumerate_optimal},
+        budget::{BudgetTracker, CallOutcome, SolveBudget, StopHandle},
+        progress::{Progress, ProgressSender},
+    },
+};
 use tokio::sync::{oneshot, oneshot::error::TryRecvError};
 
+/// How often the stats panel gets new solver progress
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
 pub struct SolverBackend {
     rt: tokio::runtime::Runtime,
     egui_ctx: Option<egui::Context>,
 }
 
-pub struct SolverSession<S>
-where
-    S: Interrupt,
-{
+pub struct SolverSession {
     encoding: Encoding,
     limits: PlatformLimits,
-    rx: oneshot::Receiver<(anyhow::Result<SolverResult>, S)>,
-    interrupter: S::Interrupter,
+    rx: oneshot::Receiver<(anyhow::Result<SolverResult>, Box<dyn DynSolver>)>,
+    stop: StopHandle,
+    progress_rx: mpsc::Receiver<Progress>,
+    latest_progress: Option<Progress>,
 }
 
-impl<S> SolverSession<S>
-where
-    S: Interrupt,
-{
-    pub fn try_recv(maybe_self: &mut Option<Self>) -> Option<SolverResponse<S>> {
+impl SolverSession {
+    pub fn try_recv(maybe_self: &mut Option<Self>) -> Option<SolverResponse> {
         // Both empty and closed is okay
         // Closed also implies the value has already been received
         let session = maybe_self.as_mut()?;
@@ -45,14 +54,49 @@ where
     }
 
     pub fn interrupt(&mut self) {
-        self.interrupter.interrupt();
+        self.stop.stop();
+    }
+
+    /// The most recent progress published by the running solver.
+    pub fn progress(&mut self) -> Option<&Progress> {
+        if let Some(progress) = self.progress_rx.try_iter().last() {
+            self.latest_progress = Some(progress);
+        }
+        self.latest_progress.as_ref()
+    }
+}
+
+/// A search for further optimal layouts, see [`enumerate_optimal`].
+pub struct AlternativesSession {
+    rx: oneshot::Receiver<anyhow::Result<Alternatives>>,
+    stop: StopHandle,
+}
+
+impl AlternativesSession {
+    pub fn try_recv(maybe_self: &mut Option<Self>) -> Option<anyhow::Result<Alternatives>> {
+        let session = maybe_self.as_mut()?;
+        match session.rx.try_recv() {
+            Ok(result) => {
+                *maybe_self = None;
+                Some(result)
+            }
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Closed) => {
+                warn!(target: "solver backend", "Alternatives channel was closed");
+                *maybe_self = None;
+                None
+            }
+        }
+    }
+
+    pub fn interrupt(&mut self) {
+        self.stop.stop();
     }
 }
 
-#[derive(Debug)]
-pub struct SolverResponse<S> {
+pub struct SolverResponse {
     pub result: anyhow::Result<SolverResult>,
-    pub solver: S,
+    pub solver: Box<dyn DynSolver>,
     pub encoding: Encoding,
     pub limits: PlatformLimits,
 }
@@ -66,28 +110,41 @@ impl SolverBackend {
         self.egui_ctx = Some(ctx);
     }
 
-    pub fn start<S>(
+    pub fn start(
         &mut self,
         encoding: Encoding,
         limits: PlatformLimits,
-    ) -> anyhow::Result<SolverSession<S>>
-    where
-        S: Solve + Interrupt + Default + Send + 'static,
-    {
+        solver_config: &SolverConfig,
+    ) -> anyhow::Result<SolverSession> {
         let instance = encoding.with_limits(&limits);
         let (cnf, _var_manager) = instance.into_cnf();
-        let mut solver = S::default();
+        let mut solver = solver_config.create(encoding.vars())?;
         solver.add_cnf(cnf)?;
         let (tx, rx) = oneshot::channel();
-        let interrupter = solver.interrupter();
+        let stop = StopHandle::default();
+        let (progress, progress_rx) = ProgressSender::channel(PROGRESS_INTERVAL);
 
-        let session = SolverSession::<S> { encoding, limits, rx, interrupter };
+        let session = SolverSession {
+            encoding,
+            limits,
+            rx,
+            stop: stop.clone(),
+            progress_rx,
+            latest_progress: None,
+        };
 
         _ = self.rt.spawn_blocking({
             let ctx = self.egui_ctx.clone();
             move || {
+                let mut tracker =
+                    BudgetTracker::new(SolveBudget::default()).with_progress(progress);
+                let result = tracker.solve(solver.as_mut(), &stop).map(|outcome| match outcome {
+                    CallOutcome::Sat => SolverResult::Sat,
+                    CallOutcome::Unsat => SolverResult::Unsat,
+                    CallOutcome::Stopped(_) => SolverResult::Interrupted,
+                });
                 // If sending fails, the backend has dropped the receiver
-                _ = tx.send((solver.solve(), solver));
+                _ = tx.send((result, solver));
                 if let Some(ctx) = ctx {
                     ctx.request_repaint();
                 }
@@ -95,4 +152,43 @@ impl SolverBackend {
         });
         Ok(session)
     }
+
+    /// Looks for layouts as good as `first`, whose objective `limits` has to
+    /// be bounded at.
+    pub fn start_alternatives(
+        &mut self,
+        encoding: Encoding,
+        limits: PlatformLimits,
+        solver_config: &SolverConfig,
+        first: PlatformLayout,
+        options: AlternativesOptions,
+    ) -> AlternativesSession {
+        let (tx, rx) = oneshot::channel();
+        let stop = StopHandle::default();
+        let session = AlternativesSession { rx, stop: stop.clone() };
+
+        _ = self.rt.spawn_blocking({
+            let ctx = self.egui_ctx.clone();
+            let solver_config = solver_config.clone();
+            move || {
+                let mut tracker = BudgetTracker::new(SolveBudget::default());
+                let result = enumerate_optimal(
+                    &encoding,
+                    &limits,
+                    &solver_config,
+                    first,
+                    options,
+                    &mut tracker,
+                    &stop,
+                    |_| {},
+                );
+                // If sending fails, the backend has dropped the receiver
+                _ = tx.send(result);
+                if let Some(ctx) = ctx {
+                    ctx.request_repaint();
+                }
+            }
+        });
+        session
+    }
 }
@@ -1,32 +1,55 @@
 #![allow(dead_code)]
 
 use std::{
+    cmp::Ordering,
     collections::HashMap,
-    fs,
+    fs, io,
     io::Write,
     num::ParseIntError,
     path::{Path, PathBuf},
     str::FromStr,
+    time::Duration,
 };
 
 use anyhow::{Context, bail};
 use clap::{CommandFactory, Parser, Subcommand};
 use log::{error, info, trace, warn};
 use owo_colors::OwoColorize;
-use rustsat::solvers::{InterruptSolver, Solve, SolveStats, SolverResult};
-use rustsat_glucose::simp::Glucose as GlucoseSimp;
 use thiserror::Error;
 use timberborn_platform_cruncher::{
-    Project, encoder,
-    encoder::{Encoding, PlatformLayout, PlatformLimits, ValidationResult},
-    math::{Dimensions, Grid},
-    platform::{PLATFORMS_DEFAULT, PlatformDef},
-    platform_def,
+    Project, brute_force, encoder,
+    encoder::{
+        Encoding, PlatformLayout, PlatformLimits, ValidationResult,
+        diagnostics::Severity,
+        export::{self, SolverOutput},
+        layout_diff::{LayoutDiff, PlatformChange, diff_layouts},
+        layout_file::LayoutFile,
+        merging::merge_platforms,
+        redundancy::{PruneStrategy, remove_redundant_platforms},
+        support_forest::SupportForest,
+        support_map::{MAX_SUPPORT_DISTANCE, SupportMap},
+    },
+    math::{Dimensions, Grid, Point, Rotation, Transform},
+    platform::{PLATFORMS_DEFAULT, Platform, PlatformDef},
+    solver::{
+        SolverConfig, SolverKind,
+        alternatives::{Alternatives, AlternativesOptions},
+        budget::{SolveBudget, StopHandle, StopReason},
+        infeasibility::explain_infeasibility,
+        manifest::{RunManifest, RunMode},
+        minimize::{Improvement, MinimizeOptions, MinimizeOutcome},
+        partial_support::SupportImprovement,
+        progress::ProgressSender,
+        removal::RemovalImprovement,
+    },
     world::World,
 };
-use tokio_util::{future::FutureExt, sync::CancellationToken};
+use tokio_util::{
+    future::FutureExt,
+    sync::{CancellationToken, DropGuard},
+};
 
-use crate::solver_runner::run_solver;
+use crate::solver_runner::{run_maximize_support, run_minimize, run_minimize_with_removal};
 
 mod solver_runner;
 
@@ -67,6 +90,179 @@ enum ReplCommand {
         /// or larger.
         #[arg(short = 'l', value_delimiter = ',')]
         limits: Vec<PlatformLimitArg>,
+        /// SAT solver backend to use (glucose, cadical, minisat, kissat)
+        ///
+        /// An external solver executable can be used with
+        /// `external:<command>`, e.g. `--solver "external:kissat -q"`. The
+        /// instance is passed to it as a DIMACS file path.
+        ///
+        /// Several solvers can be raced with `portfolio:<solver>[@seed],...`,
+        /// e.g. `--solver portfolio:glucose,glucose@1,glucose@2`. Seeded
+        /// members get a shuffled copy of the instance.
+        ///
+        /// Large maps can be split into `2^depth` cubes solved in parallel with
+        /// `cubes:<solver>[:<depth>]`, e.g. `--solver cubes:glucose:6`.
+        ///
+        /// Defaults to the first backend enabled at build time.
+        #[arg(long)]
+        solver: Option<SolverConfig>,
+        #[command(flatten)]
+        budget: BudgetArgs,
+        /// Proof-log the run and, once the optimum is proven, store an
+        /// optimality certificate in this directory: the final (UNSAT)
+        /// instance, its DRAT proof and the claimed optimum. Requires a
+        /// proof-capable solver, e.g. `cadical`.
+        #[arg(long, value_name = "DIR")]
+        proof: Option<PathBuf>,
+        /// Once the optimum is found, look for up to this many distinct
+        /// optimal layouts, which can then be paged through with `alt`
+        #[arg(long, value_name = "K")]
+        alternatives: Option<usize>,
+        /// Minimum Hamming distance between alternative layouts, counted in
+        /// per-tile platform variables (moving a 1x1 platform counts 2)
+        #[arg(long, value_name = "D", default_value_t = 1, requires = "alternatives")]
+        min_distance: usize,
+        /// Partial support: rather than requiring all terrain to be supported,
+        /// support as much of it as possible within the limits
+        ///
+        /// Example: `solve --partial -l1:3` ~ the most terrain 3 platforms can
+        /// hold up.
+        #[arg(long, conflicts_with_all = ["proof", "alternatives"])]
+        partial: bool,
+        /// Allow terrain to be dug out instead of supported, at this cost per
+        /// tile. The total of platforms (1 each) and removal costs is
+        /// minimized.
+        ///
+        /// Example: `solve --removal-cost 2` ~ a tile is dug out rather than
+        /// supported if that saves more than 2 platforms (either may be chosen
+        /// if it saves exactly 2).
+        #[arg(long, value_name = "C", conflicts_with_all = ["proof", "alternatives", "partial"])]
+        removal_cost: Option<usize>,
+        /// Require every terrain tile to be held up by at least two platforms,
+        /// so that the layout survives losing any one of them
+        #[arg(long, conflicts_with_all = ["partial", "removal_cost"])]
+        fault_tolerant: bool,
+        /// Seed for the solver, which shuffles the instance before solving.
+        /// Runs with the same seed find the same solutions.
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Write a run manifest (encoding hash, solver, seed, limits and mode,
+        /// e.g. `--partial`) to this file, which `replay` can repeat the run
+        /// from
+        #[arg(long, value_name = "FILE")]
+        manifest: Option<PathBuf>,
+    },
+    /// Repeat the run described by a manifest from `solve --manifest` on the
+    /// currently loaded project
+    Replay { manifest: PathBuf },
+    /// Save the most recently shown layout to a file (JSON if it ends in
+    /// `.json`, TOML otherwise)
+    SaveSolution { path: PathBuf },
+    /// Load a layout saved with `save-solution`, and validate it against the
+    /// currently loaded project
+    LoadSolution { path: PathBuf },
+    /// Compare a layout saved with `save-solution` against another saved one,
+    /// or against the most recently shown layout
+    Diff {
+        /// The old layout
+        old: PathBuf,
+        /// The new layout, defaults to the most recently shown one
+        new: Option<PathBuf>,
+    },
+    /// Remove platforms from the most recently shown layout whose terrain is
+    /// also supported by their neighbours
+    Prune {
+        /// Remove as many as possible, instead of trying them one by one
+        #[arg(long)]
+        optimal: bool,
+    },
+    /// Replace groups of small platforms in the most recently shown layout with
+    /// larger ones covering the same tiles
+    Merge {
+        /// Weights for platform types, to merge only where it lowers the total
+        /// weight rather than the platform count
+        ///
+        /// Specified the same way as limits.
+        /// Example: `-w1:5,3:2`
+        #[arg(short = 'w', value_delimiter = ',')]
+        weights: Vec<PlatformLimitArg>,
+    },
+    /// Show the support distance of every terrain tile in the most recently
+    /// shown layout, and how many tiles are at the limit
+    #[command(visible_aliases = ["heat"])]
+    Heatmap,
+    /// Show how a terrain tile is supported in the most recently shown layout:
+    /// the tiles leading to its platform, and what else that platform holds up
+    Explain { x: isize, y: isize },
+    /// Rotate, mirror, move, crop or pad the terrain of the currently loaded
+    /// project, along with the shown and alternative layouts. The project file
+    /// isn't changed, `reload` undoes this.
+    Transform {
+        #[command(subcommand)]
+        cmd: TransformCommand,
+    },
+    /// Check whether the most recently shown layout survives losing any one
+    /// platform, and list the platforms it doesn't survive losing
+    #[command(visible_aliases = ["ft"])]
+    FaultTolerance,
+    /// List the validation diagnostics of the most recently shown layout, with
+    /// suggested fixes
+    #[command(visible_aliases = ["diag"])]
+    Diagnostics {
+        /// Print them as JSON instead
+        #[arg(long)]
+        json: bool,
+    },
+    /// Page through the alternative optimal layouts of the last `solve
+    /// --alternatives` run
+    #[command(visible_aliases = ["alt"])]
+    Alternative {
+        #[command(subcommand)]
+        cmd: Option<AlternativeCommand>,
+    },
+    /// Find a minimum layout for the currently loaded project using the exact
+    /// (non-SAT) reference solver.
+    ///
+    /// This is an exhaustive search, so it only accepts tiny worlds (up to 64
+    /// tiles, e.g. 8x8).
+    #[command(visible_aliases = ["bf"])]
+    BruteForce,
+    /// Inspect the SAT encoding of the currently loaded project
+    #[command(visible_aliases = ["enc"])]
+    Encoding {
+        #[command(subcommand)]
+        cmd: EncodingCommand,
+    },
+    /// Export the SAT encoding of the currently loaded project with optional
+    /// limits, for use with external solvers
+    ///
+    /// Writes `<NAME>.cnf` (DIMACS), `<NAME>.opb` and `<NAME>.vars` (variable
+    /// map), and `<NAME>.wcnf` if any weights are given.
+    Export {
+        /// Output directory
+        dir: PathBuf,
+        /// Base name of the written files
+        #[arg(long, default_value = "instance")]
+        name: String,
+        /// Limits for platform types, same as for `solve`
+        #[arg(short = 'l', value_delimiter = ',')]
+        limits: Vec<PlatformLimitArg>,
+        /// Weights for platform types, used as the objective (WCNF/OPB)
+        ///
+        /// Specified the same way as limits.
+        /// Example: `-w1:5,3:2`
+        #[arg(short = 'w', value_delimiter = ',')]
+        weights: Vec<PlatformLimitArg>,
+    },
+    /// Import a solver's output (`s`/`v` lines) for the most recently exported
+    /// encoding, and show it as a layout
+    ImportSolution {
+        path: PathBuf,
+        /// The `.vars` file written by `export`, to import for the loaded
+        /// project instead, e.g. after a restart. Fails if the project's
+        /// encoding doesn't match the exported one.
+        #[arg(long, value_name = "FILE")]
+        vars: Option<PathBuf>,
     },
     #[command(visible_aliases = ["q"])]
     Exit,
@@ -74,6 +270,86 @@ enum ReplCommand {
     ShowHelp,
 }
 
+#[derive(Debug, Subcommand)]
+enum AlternativeCommand {
+    /// Show the next layout
+    #[command(visible_aliases = ["n"])]
+    Next,
+    /// Show the previous layout
+    #[command(visible_aliases = ["p"])]
+    Prev,
+    /// Show the layout with this number, starting at 1
+    Show { number: usize },
+}
+
+#[derive(Debug, Subcommand)]
+enum TransformCommand {
+    /// Rotate clockwise by 90, 180 or 270 degrees
+    Rotate { degrees: u16 },
+    /// Swap the left and right sides
+    Mirror {
+        /// Swap the top and bottom instead
+        #[arg(long)]
+        top_bottom: bool,
+    },
+    /// Move everything by an offset, dropping what ends up out of bounds
+    Translate {
+        #[arg(allow_negative_numbers = true)]
+        x: isize,
+        #[arg(allow_negative_numbers = true)]
+        y: isize,
+    },
+    /// Keep only the area with its top-left corner at (x;y)
+    Crop {
+        #[arg(allow_negative_numbers = true)]
+        x: isize,
+        #[arg(allow_negative_numbers = true)]
+        y: isize,
+        width: usize,
+        height: usize,
+    },
+    /// Add empty tiles on each side
+    Pad { left: usize, top: usize, right: usize, bottom: usize },
+}
+
+impl TryFrom<TransformCommand> for Transform {
+    type Error = anyhow::Error;
+
+    fn try_from(cmd: TransformCommand) -> anyhow::Result<Self> {
+        Ok(match cmd {
+            TransformCommand::Rotate { degrees } => {
+                let Some(rotation) = Rotation::from_degrees(degrees) else {
+                    bail!("Expected 90, 180 or 270 degrees, got {degrees}");
+                };
+                Transform::Rotate(rotation)
+            }
+            TransformCommand::Mirror { top_bottom: false } => Transform::MirrorLeftRight,
+            TransformCommand::Mirror { top_bottom: true } => Transform::MirrorTopBottom,
+            TransformCommand::Translate { x, y } => Transform::Translate(Point::new(x, y)),
+            TransformCommand::Crop { x, y, width, height } => {
+                Transform::Crop { origin: Point::new(x, y), dims: Dimensions::new(width, height) }
+            }
+            TransformCommand::Pad { left, top, right, bottom } => {
+                Transform::Pad { left, top, right, bottom }
+            }
+        })
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum EncodingCommand {
+    /// Show the number of variables and clauses, with clauses counted by their
+    /// origin
+    Stats {
+        /// Limits for platform types, same as for `solve`
+        #[arg(short = 'l', value_delimiter = ',')]
+        limits: Vec<PlatformLimitArg>,
+        /// Also write all clauses in a readable form to this file
+        #[arg(long)]
+        dump: Option<PathBuf>,
+    },
+}
+
 #[derive(Error, Debug)]
 enum PlatformLimitError {
     #[error("duplicate limit for `{0}`")]
@@ -86,6 +362,13 @@ fn try_into_platform_limits(
     limit_args: Vec<PlatformLimitArg>,
     dims_platform_map: &HashMap<Dimensions, PlatformDef>,
 ) -> Result<PlatformLimits, PlatformLimitError> {
+    Ok(PlatformLimits::new_unweighted(try_into_platform_map(limit_args, dims_platform_map)?))
+}
+
+fn try_into_platform_map(
+    limit_args: Vec<PlatformLimitArg>,
+    dims_platform_map: &HashMap<Dimensions, PlatformDef>,
+) -> Result<HashMap<PlatformDef, usize>, PlatformLimitError> {
     let mut map = HashMap::new();
     for PlatformLimitArg(dims, count) in limit_args.into_iter() {
         let def = *dims_platform_map.get(&dims).ok_or(PlatformLimitError::Unknown(dims))?;
@@ -97,7 +380,7 @@ fn try_into_platform_limits(
         }
     }
 
-    Ok(PlatformLimits::new_unweighted(map))
+    Ok(map)
 }
 
 /// Note: describes limits for _dimensions_, not the platform defs themselves.
@@ -153,6 +436,45 @@ enum ReplParseError {
     Io(#[from] std::io::Error),
 }
 
+/// Stopping criteria for `solve`, see [`SolveBudget`]
+#[derive(Debug, Clone, clap::Args)]
+struct BudgetArgs {
+    /// Stop after this many seconds, keeping the best solution so far
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    time_limit: Option<Duration>,
+    /// Conflict limit for each solver call
+    #[arg(long, value_name = "N")]
+    conflicts: Option<u32>,
+    /// Propagation limit for each solver call
+    #[arg(long, value_name = "N")]
+    propagations: Option<u32>,
+    /// Conflict limit for all solver calls together. Conflict limits are
+    /// approximate: counted in slices, and per member with portfolios.
+    /// Cube-and-conquer doesn't support them.
+    #[arg(long, value_name = "N")]
+    total_conflicts: Option<u64>,
+    /// Stop if no better solution was found within this many seconds
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    no_improvement: Option<Duration>,
+}
+
+impl From<BudgetArgs> for SolveBudget {
+    fn from(args: BudgetArgs) -> Self {
+        SolveBudget {
+            time_limit: args.time_limit,
+            conflicts_per_call: args.conflicts,
+            propagations_per_call: args.propagations,
+            total_conflicts: args.total_conflicts,
+            no_improvement: args.no_improvement,
+        }
+    }
+}
+
+fn parse_seconds(input: &str) -> Result<Duration, String> {
+    let seconds: f64 = input.trim().parse().map_err(|err| format!("{err}"))?;
+    Duration::try_from_secs_f64(seconds).map_err(|err| format!("{err}"))
+}
+
 fn parse_repl() -> Result<ReplCli, ReplParseError> {
     write!(std::io::stdout(), "$ ")?;
     std::io::stdout().flush()?;
@@ -172,6 +494,12 @@ async fn repl_loop() -> anyhow::Result<()> {
     struct State {
         loaded_project: Option<LoadedProject>,
         dims_platform_map: HashMap<Dimensions, PlatformDef>,
+        /// The last export, whose solutions can be imported without `--vars`
+        exported: Option<(Encoding, World)>,
+        /// Optimal layouts of the last solve, and the one currently shown
+        alternatives: Option<(World, Vec<PlatformLayout>, usize)>,
+        /// The most recently shown layout, for `diagnostics`
+        shown: Option<(World, PlatformLayout)>,
     }
 
     struct LoadedProject {
@@ -186,7 +514,13 @@ async fn repl_loop() -> anyhow::Result<()> {
             .collect()
     };
 
-    let mut state = State { loaded_project: None, dims_platform_map };
+    let mut state = State {
+        loaded_project: None,
+        dims_platform_map,
+        exported: None,
+        alternatives: None,
+        shown: None,
+    };
 
     loop {
         if let Some(LoadedProject { project: _project, path }) = &state.loaded_project {
@@ -217,6 +551,30 @@ async fn repl_loop() -> anyhow::Result<()> {
         }
     }
 
+    /// Shows the result of [`solve_in_mode`], and makes any alternatives
+    /// available to `alt`.
+    fn show_solve_result(
+        state: &mut State,
+        world: World,
+        best: Option<PlatformLayout>,
+        alternatives: Option<Alternatives>,
+    ) {
+        state.shown = best.map(|best| (world.clone(), best));
+        state.alternatives = None;
+        if let Some(alternatives) = alternatives {
+            let found = alternatives.layouts.len();
+            match alternatives.stop_reason {
+                None => println!("Found {found} optimal layouts"),
+                Some(StopReason::Unsat) => {
+                    println!("Found {found} optimal layouts, there are no others")
+                }
+                Some(reason) => println!("Found {found} optimal layouts ({reason})"),
+            }
+            println!("Use `alt next`/`alt prev` to page through them");
+            state.alternatives = Some((world, alternatives.layouts, 0));
+        }
+    }
+
     async fn run_cmd(cmd: ReplCommand, state: &mut State) -> anyhow::Result<()> {
         match cmd {
             ReplCommand::Load { path } => {
@@ -245,17 +603,535 @@ async fn repl_loop() -> anyhow::Result<()> {
 
                 Ok(())
             }
-            ReplCommand::Solve { limits: limit_args } => {
+            ReplCommand::Solve {
+                limits: limit_args,
+                solver,
+                budget,
+                proof,
+                alternatives,
+                min_distance,
+                partial,
+                removal_cost,
+                fault_tolerant,
+                seed,
+                manifest,
+            } => {
+                let limits = try_into_platform_limits(limit_args, &state.dims_platform_map)?;
+                let Some(mut solver_config) =
+                    solver.or_else(|| SolverKind::default_enabled().map(Into::into))
+                else {
+                    bail!("No solver backends enabled in this build");
+                };
+                if let Some(seed) = seed {
+                    solver_config = solver_config.with_seed(seed);
+                }
+                let Some(LoadedProject { project, .. }) = &state.loaded_project else {
+                    bail!("No project loaded");
+                };
+
+                if let Some(dir) = &proof {
+                    fs::create_dir_all(dir).context("Failed to create the proof directory")?;
+                }
+
+                let mut encoding = Encoding::encode(&PLATFORMS_DEFAULT, project.world.grid());
+                if fault_tolerant {
+                    encoding = encoding.with_fault_tolerance();
+                }
+                let mode = if partial {
+                    RunMode::Partial
+                } else if let Some(cost) = removal_cost {
+                    RunMode::Removal { cost: cost as isize }
+                } else if let Some(count) = alternatives {
+                    RunMode::Alternatives { count, min_distance }
+                } else {
+                    RunMode::Minimize
+                };
+                if let Some(path) = &manifest {
+                    RunManifest::new(&encoding, &limits, &solver_config)?
+                        .with_mode(mode)
+                        .write(path)?;
+                    println!("Run manifest written to {}", path.display());
+                }
+
+                let world = project.world.clone();
+                let (best, alternatives) = solve_in_mode(
+                    project,
+                    &encoding,
+                    limits,
+                    &solver_config,
+                    mode,
+                    budget.into(),
+                    proof,
+                )
+                .await?;
+                show_solve_result(state, world, best, alternatives);
+
+                Ok(())
+            }
+            ReplCommand::Replay { manifest } => {
+                let Some(LoadedProject { project, .. }) = &state.loaded_project else {
+                    bail!("No project loaded");
+                };
+                let manifest = RunManifest::read(&manifest)?;
+                let mut encoding = Encoding::encode(&PLATFORMS_DEFAULT, project.world.grid());
+                if manifest.fault_tolerant {
+                    encoding = encoding.with_fault_tolerance();
+                }
+                manifest.check_encoding(&encoding)?;
+                if manifest.version != timberborn_platform_cruncher::solver::manifest::VERSION {
+                    warn!("The manifest was written by version {}", manifest.version);
+                }
+                let limits = manifest.platform_limits(&PLATFORMS_DEFAULT)?;
+                let solver_config = manifest.solver_config()?;
+                println!("Replaying a {} run", manifest.mode);
+
+                let world = project.world.clone();
+                let (best, alternatives) = solve_in_mode(
+                    project,
+                    &encoding,
+                    limits,
+                    &solver_config,
+                    manifest.mode,
+                    Default::default(),
+                    None,
+                )
+                .await?;
+                show_solve_result(state, world, best, alternatives);
+
+                Ok(())
+            }
+            ReplCommand::Alternative { cmd } => {
+                let Some((world, layouts, current)) = &mut state.alternatives else {
+                    bail!("No alternative layouts, use `solve --alternatives <K>` first");
+                };
+
+                *current = match cmd {
+                    None => *current,
+                    Some(AlternativeCommand::Next) => (*current + 1) % layouts.len(),
+                    Some(AlternativeCommand::Prev) => {
+                        (*current + layouts.len() - 1) % layouts.len()
+                    }
+                    Some(AlternativeCommand::Show { number }) => {
+                        if !(1..=layouts.len()).contains(&number) {
+                            bail!("Expected a layout number from 1 to {}", layouts.len());
+                        }
+                        number - 1
+                    }
+                };
+
+                let layout = &layouts[*current];
+                println!(
+                    "Layout {} of {} ({} platforms total)",
+                    *current + 1,
+                    layouts.len(),
+                    layout.platform_count()
+                );
+                for (def, count) in layout.platform_stats().iter() {
+                    println!("{}: {}", def.dimensions_str(), count);
+                }
+                print_world(world, Some(layout), &layout.validate(world));
+                state.shown = Some((world.clone(), layout.clone()));
+
+                Ok(())
+            }
+            ReplCommand::SaveSolution { path } => {
+                let Some((world, layout)) = &state.shown else {
+                    bail!("No layout shown yet");
+                };
+
+                LayoutFile::new(layout.clone(), world).save(&path)?;
+                println!("Saved to {}", path.display());
+
+                Ok(())
+            }
+            ReplCommand::LoadSolution { path } => {
+                let Some(LoadedProject { project, .. }) = &state.loaded_project else {
+                    bail!("No project loaded");
+                };
+
+                let file = LayoutFile::read(&path)?;
+                let validation = file.check(&project.world)?;
+                println!("Loaded layout ({} platforms total)", file.layout.platform_count());
+                if validation.is_valid() {
+                    info!("Solution validation OK");
+                } else {
+                    warn!("Solution validation FAILED");
+                    for printout in validation.iter_error_printouts() {
+                        warn!(
+                            "Validation failed: {}:\n{}",
+                            printout.header,
+                            printout.items.join("\n")
+                        );
+                    }
+                }
+                print_world(&project.world, Some(&file.layout), &validation);
+                state.shown = Some((project.world.clone(), file.layout));
+
+                Ok(())
+            }
+            ReplCommand::Diff { old, new } => {
+                let old = LayoutFile::read(&old)?;
+                let (world, new) = match new {
+                    Some(path) => {
+                        let Some(LoadedProject { project, .. }) = &state.loaded_project else {
+                            bail!("No project loaded");
+                        };
+                        (project.world.clone(), LayoutFile::read(&path)?.layout)
+                    }
+                    None => {
+                        let Some((world, layout)) = &state.shown else {
+                            bail!("No layout shown yet");
+                        };
+                        (world.clone(), layout.clone())
+                    }
+                };
+                if old.dims != world.grid().dims() {
+                    bail!("The layouts were made for worlds of different dimensions");
+                }
+
+                let diff = diff_layouts(&old.layout, &new);
+                println!("{diff}");
+                for change in diff.changes.iter() {
+                    println!("{}", format_change(change));
+                }
+                print_world_with_diff(&world, Some(&new), &new.validate(&world), Some(&diff));
+
+                Ok(())
+            }
+            ReplCommand::Prune { optimal } => {
+                let Some((world, layout)) = &mut state.shown else {
+                    bail!("No layout shown yet");
+                };
+
+                let strategy = if optimal { PruneStrategy::Optimal } else { PruneStrategy::Greedy };
+                let removed = remove_redundant_platforms(layout, world, strategy)?;
+                if removed.is_empty() {
+                    println!("No redundant platforms");
+                    return Ok(());
+                }
+                println!(
+                    "Removed {} redundant platforms ({} platforms total)",
+                    removed.len(),
+                    layout.platform_count()
+                );
+                for plat in removed.iter() {
+                    println!("{}", format_change(&PlatformChange::Removed(*plat)));
+                }
+                print_world(world, Some(&*layout), &layout.validate(world));
+
+                Ok(())
+            }
+            ReplCommand::Merge { weights: weight_args } => {
+                let weights: HashMap<_, _> =
+                    try_into_platform_map(weight_args, &state.dims_platform_map)?
+                        .into_iter()
+                        .map(|(def, weight)| (def, weight as isize))
+                        .collect();
+                let Some((world, layout)) = &mut state.shown else {
+                    bail!("No layout shown yet");
+                };
+
+                let weights = (!weights.is_empty()).then_some(&weights);
+                let merges = merge_platforms(layout, world, &PLATFORMS_DEFAULT, weights);
+                if merges.is_empty() {
+                    println!("Nothing to merge");
+                    return Ok(());
+                }
+                println!(
+                    "Made {} merges ({} platforms total)",
+                    merges.len(),
+                    layout.platform_count()
+                );
+                for merge in merges.iter() {
+                    let replaced = merge
+                        .replaced
+                        .iter()
+                        .map(|plat| format!("({};{})", plat.point().x, plat.point().y))
+                        .collect::<Vec<_>>();
+                    println!(
+                        "{} platforms at {} to {}",
+                        "merged".green(),
+                        replaced.join(", "),
+                        format_platform(&merge.platform)
+                    );
+                }
+                print_world(world, Some(&*layout), &layout.validate(world));
+
+                Ok(())
+            }
+            ReplCommand::Heatmap => {
+                let Some((world, layout)) = &state.shown else {
+                    bail!("No layout shown yet");
+                };
+
+                let map = SupportMap::new(layout, world);
+                print_support_map(world, layout, &map);
+                match (map.max, map.slack()) {
+                    (None, _) => println!("No terrain is supported"),
+                    (Some(max), Some(slack)) => {
+                        println!("Max distance: {max} (slack {slack})")
+                    }
+                    (Some(max), None) => println!("Max distance: {max} (over the limit)"),
+                }
+                for (distance, count) in map.histogram.iter().enumerate() {
+                    println!("{distance}: {count}");
+                }
+                if map.disconnected > 0 {
+                    println!("Disconnected: {}", map.disconnected);
+                }
+                let critical =
+                    map.critical.iter().map(|p| format!("({};{})", p.x, p.y)).collect::<Vec<_>>();
+                println!("{} critical tiles: {}", critical.len(), critical.join(", "));
+
+                Ok(())
+            }
+            ReplCommand::Explain { x, y } => {
+                let Some((world, layout)) = &state.shown else {
+                    bail!("No layout shown yet");
+                };
+                let p = Point::new(x, y);
+                if world.grid().get(p) != Some(&true) {
+                    bail!("There's no terrain at ({x};{y})");
+                }
+
+                let forest = SupportForest::new(layout, world);
+                let Some(path) = forest.path(p) else {
+                    println!("Tile ({x};{y}) isn't supported");
+                    return Ok(());
+                };
+                let tiles =
+                    path.tiles.iter().map(|p| format!("({};{})", p.x, p.y)).collect::<Vec<_>>();
+                let (plat_dims, plat_point) = (path.platform.dims(), path.platform.point());
+                println!(
+                    "Tile ({x};{y}) is held up by the {}x{} platform at ({};{}), {} steps away",
+                    plat_dims.width,
+                    plat_dims.height,
+                    plat_point.x,
+                    plat_point.y,
+                    path.len()
+                );
+                println!("Path: {}", tiles.join(" -> "));
+                let held_up = &forest.held_up_by()[&path.platform];
+                println!("That platform holds up {} tiles", held_up.len());
+
+                Ok(())
+            }
+            ReplCommand::Transform { cmd } => {
+                let Some(LoadedProject { project, .. }) = &mut state.loaded_project else {
+                    bail!("No project loaded");
+                };
+                let transform = Transform::try_from(cmd)?;
+                let dims = transform.dims(project.world.grid().dims());
+                if dims.empty() {
+                    bail!("The terrain would be empty ({}x{})", dims.width, dims.height);
+                }
+
+                project.world = project.world.transformed(transform);
+                let transform_layout = |world: &World, layout: &PlatformLayout| {
+                    let transformed = layout.transformed(transform, world.grid().dims());
+                    let dropped = layout.platform_count() - transformed.platform_count();
+                    (transformed, dropped)
+                };
+                if let Some((world, layout)) = &mut state.shown {
+                    let (transformed, dropped) = transform_layout(world, layout);
+                    if dropped > 0 {
+                        println!("{dropped} platforms of the shown layout ended up out of bounds");
+                    }
+                    *layout = transformed;
+                    *world = world.transformed(transform);
+                }
+                if let Some((world, layouts, _)) = &mut state.alternatives {
+                    for layout in layouts.iter_mut() {
+                        *layout = transform_layout(world, layout).0;
+                    }
+                    *world = world.transformed(transform);
+                }
+
+                println!("Applied {transform}, the terrain is now {}x{}", dims.width, dims.height);
+                match &state.shown {
+                    Some((world, layout)) => {
+                        print_world(world, Some(layout), &layout.validate(world))
+                    }
+                    None => print_world(&project.world, None, &Default::default()),
+                }
+
+                Ok(())
+            }
+            ReplCommand::FaultTolerance => {
+                let Some((world, layout)) = &state.shown else {
+                    bail!("No layout shown yet");
+                };
+
+                let result = layout.validate_fault_tolerance(world);
+                if result.is_fault_tolerant() {
+                    println!("The layout survives losing any one platform");
+                    return Ok(());
+                }
+                let mut failures = result.single_points_of_failure.iter().collect::<Vec<_>>();
+                failures.sort_by_key(|(plat, _)| (plat.point().y, plat.point().x));
+                println!("{} platforms are the only support of some terrain:", failures.len());
+                for (plat, lost) in failures {
+                    println!("  {}: {} tiles", format_platform(plat), lost.len());
+                }
+
+                Ok(())
+            }
+            ReplCommand::Diagnostics { json } => {
+                let Some((world, layout)) = &state.shown else {
+                    bail!("No layout shown yet");
+                };
+
+                let diagnostics = layout.validate(world).diagnostics();
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&diagnostics)?);
+                } else if diagnostics.is_empty() {
+                    println!("No findings");
+                } else {
+                    for diagnostic in diagnostics.iter() {
+                        let severity = match diagnostic.severity {
+                            Severity::Error => "error".red().to_string(),
+                            Severity::Warning => "warning".yellow().to_string(),
+                        };
+                        println!("{severity}: {diagnostic}");
+                    }
+                }
+
+                Ok(())
+            }
+            ReplCommand::BruteForce => {
+                let Some(LoadedProject { project, .. }) = &state.loaded_project else {
+                    bail!("No project loaded");
+                };
+
+                let Some(layout) = brute_force::solve_minimum(&project.world, &PLATFORMS_DEFAULT)?
+                else {
+                    println!("No solution exists for the current project");
+                    return Ok(());
+                };
+
+                println!("Minimum layout found ({} platforms total)", layout.platform_count());
+                for (def, count) in layout.platform_stats().iter() {
+                    println!("{}: {}", def.dimensions_str(), count);
+                }
+                let validation = layout.validate(&project.world);
+                print_world(&project.world, Some(&layout), &validation);
+                state.shown = Some((project.world.clone(), layout));
+
+                Ok(())
+            }
+            ReplCommand::Encoding { cmd: EncodingCommand::Stats { limits: limit_args, dump } } => {
                 let limits = try_into_platform_limits(limit_args, &state.dims_platform_map)?;
                 let Some(LoadedProject { project, .. }) = &state.loaded_project else {
                     bail!("No project loaded");
                 };
 
                 let encoding = Encoding::encode(&PLATFORMS_DEFAULT, project.world.grid());
+                let stats = encoding.stats(Some(&limits));
+
+                println!("Variables: {}", stats.n_vars);
+                println!("Clauses: {}", stats.n_clauses());
+                for (origin, count) in stats.clauses_by_origin.iter() {
+                    println!("  {:<20}{count:>10}", format!("{origin}:"));
+                }
+                println!("Cardinality constraints: {}", stats.n_cards);
+                println!("PB constraints: {}", stats.n_pbs);
+
+                if let Some(path) = dump {
+                    let mut file = fs::File::create(&path).context("Failed to create dump file")?;
+                    encoding
+                        .write_clause_dump(&mut file, Some(&limits))
+                        .context("Failed to write clause dump")?;
+                    println!("Clauses written to {}", path.display());
+                }
+
+                Ok(())
+            }
+            ReplCommand::Export { dir, name, limits: limit_args, weights: weight_args } => {
+                let card_limits = try_into_platform_map(limit_args, &state.dims_platform_map)?;
+                let weights = try_into_platform_map(weight_args, &state.dims_platform_map)?
+                    .into_iter()
+                    .map(|(def, weight)| (def, weight as isize))
+                    .collect();
+                let limits = PlatformLimits::new_with_weights(card_limits, weights, None);
+                let Some(LoadedProject { project, .. }) = &state.loaded_project else {
+                    bail!("No project loaded");
+                };
+
+                let encoding = Encoding::encode(&PLATFORMS_DEFAULT, project.world.grid());
+
+                fs::create_dir_all(&dir).context("Failed to create output directory")?;
+                let write = |ext: &str,
+                             write_fn: &dyn Fn(&mut io::BufWriter<fs::File>) -> io::Result<()>|
+                 -> anyhow::Result<()> {
+                    let path = dir.join(format!("{name}.{ext}"));
+                    info!("Writing {}", path.display());
+                    let file = fs::File::create(&path)
+                        .with_context(|| format!("Failed to create {}", path.display()))?;
+                    let mut writer = io::BufWriter::new(file);
+                    write_fn(&mut writer)?;
+                    writer.flush()?;
+                    Ok(())
+                };
+
+                write("cnf", &|w| encoding.write_dimacs(w, &limits))?;
+                write("opb", &|w| encoding.write_opb(w, &limits))?;
+                write("vars", &|w| encoding.write_var_map(w))?;
+                if !limits.weights.is_empty() {
+                    write("wcnf", &|w| encoding.write_wcnf(w, &limits).map(|_| ()))?;
+                }
+
+                state.exported = Some((encoding, project.world.clone()));
+                println!("Exported");
+
+                Ok(())
+            }
+            ReplCommand::ImportSolution { path, vars } => {
+                let reencoded;
+                let (encoding, world) = match (&vars, &state.exported) {
+                    (Some(vars), _) => {
+                        let Some(LoadedProject { project, .. }) = &state.loaded_project else {
+                            bail!("No project loaded");
+                        };
+                        reencoded = reencode_for_import(vars, &project.world)?;
+                        (&reencoded, &project.world)
+                    }
+                    (None, Some((encoding, world))) => (encoding, world),
+                    (None, None) => bail!(
+                        "Nothing was exported in this session, pass the exported `.vars` file \
+                         with `--vars`"
+                    ),
+                };
 
-                solver_loop(project, &encoding, limits).await.context("Error while solving")?;
+                let file = fs::File::open(&path).context("Failed to open solution file")?;
+                let assignment = match export::parse_solver_output(io::BufReader::new(file))? {
+                    SolverOutput::Sat(assignment) => assignment,
+                    SolverOutput::Unsat => {
+                        println!("The solver reported the instance as unsatisfiable");
+                        return Ok(());
+                    }
+                    SolverOutput::Unknown => {
+                        println!("The solver did not report a result");
+                        return Ok(());
+                    }
+                };
 
-                println!("Done");
+                let layout = PlatformLayout::from_assignment(&assignment, encoding.vars());
+                println!("Imported solution ({} platforms total)", layout.platform_count());
+                let validation = layout.validate(world);
+                if validation.is_valid() {
+                    info!("Solution validation OK");
+                } else {
+                    warn!("Solution validation FAILED");
+                    for printout in validation.iter_error_printouts() {
+                        warn!(
+                            "Validation failed: {}:\n{}",
+                            printout.header,
+                            printout.items.join("\n")
+                        );
+                    }
+                }
+                print_world(world, Some(&layout), &validation);
+                state.shown = Some((world.clone(), layout));
 
                 Ok(())
             }
@@ -280,89 +1156,355 @@ fn load_project(path: &Path) -> anyhow::Result<Project> {
 async fn solver_loop(
     project: &Project,
     encoding: &Encoding,
-    mut limits: PlatformLimits,
-) -> anyhow::Result<()> {
-    // Rather than a reverse for loop, this repeatedly looks for a solution with a
-    // cardinality 1 lesser than each previous one. This means that, if there's a
-    // high initial estimate, the SAT solver is likely to find a much more efficient
-    // solution, and the solver doesn't step down by one each time unnecessarily.
+    limits: PlatformLimits,
+    solver_config: &SolverConfig,
+    mut options: MinimizeOptions,
+) -> anyhow::Result<MinimizeOutcome> {
+    info!("Using solver backend: {solver_config}");
+
+    let (progress, status_line) = status_line();
+    options.progress = Some(progress);
+    let proof_dir = options.proof_dir.clone();
+
+    let world = project.world.clone();
+    let (minimize_future, stop) = run_minimize(
+        encoding.clone(),
+        limits.clone(),
+        solver_config.clone(),
+        options,
+        move |improvement| print_improvement(&world, improvement),
+    );
+    let _guard = stop_on_ctrl_c(stop);
+
+    let outcome = minimize_future.future().await;
+    _ = status_line.await;
+    let outcome = outcome?;
+    match &outcome.best {
+        Some(best) => println!(
+            "Stopped after {:.1?}: {} (best solution: {} platforms)",
+            outcome.elapsed,
+            outcome.stop_reason,
+            best.platform_count()
+        ),
+        None if outcome.stop_reason == StopReason::Unsat => {
+            println!("No solution found for the current constraints");
+            print_infeasibility(encoding, limits, solver_config).await;
+        }
+        None => println!("Stopped after {:.1?}: {}", outcome.elapsed, outcome.stop_reason),
+    }
+    if let (Some(certificate), Some(dir)) = (&outcome.certificate, &proof_dir) {
+        println!("Optimality certificate written to {}", dir.display());
+        println!("Check it with: {}", certificate.check_command(dir));
+    } else if proof_dir.is_some() {
+        warn!("No optimality certificate was written, since optimality wasn't proven");
+    }
+    Ok(outcome)
+}
 
-    loop {
-        // info!("Solving for n <= {}...", run_config.max_platforms());
-        let instance = encoding.with_limits(&limits);
-        let (cnf, _var_manager) = instance.into_cnf();
-
-        let (solver_future, interrupter) = run_solver(GlucoseSimp::default(), cnf)?;
-
-        let ctrl_c_cancellation = CancellationToken::new();
-        tokio::spawn({
-            let cancel = ctrl_c_cancellation.clone();
-            async move {
-                trace!(target: "solver_interrupter", "Interrupt listener ready");
-                // TODO: better ctrl-c/interrupt handling
-                // Example: on unix, this listener will continue to capture SIGINT even after
-                // going out of scope, which might eat further SIGINTs if the program gets stuck
-                // elsewhere etc.
-                // Also, solver.start() takes a moment to run, and the user can press Ctrl-C
-                // during that time
-                // A global Ctrl-C listener that can cancel this whole task might be better
-
-                match tokio::signal::ctrl_c().with_cancellation_token_owned(cancel).await {
-                    None => {
-                        trace!(target: "solver_interrupter", "Interrupt listener canceled");
-                    }
-                    Some(Ok(())) => {
-                        println!("Aborting...");
-                        interrupter.interrupt();
-                    }
-                    Some(Err(err)) => {
-                        error!("Interrupt listener error: {err}");
-                    }
+/// Runs `solve` in `mode`, returning the best layout and, for
+/// [`RunMode::Alternatives`], the alternative layouts.
+async fn solve_in_mode(
+    project: &Project,
+    encoding: &Encoding,
+    limits: PlatformLimits,
+    solver_config: &SolverConfig,
+    mode: RunMode,
+    budget: SolveBudget,
+    proof: Option<PathBuf>,
+) -> anyhow::Result<(Option<PlatformLayout>, Option<Alternatives>)> {
+    let (best, alternatives) = match mode {
+        RunMode::Partial => {
+            let best = partial_support_loop(project, encoding, limits, solver_config, budget).await;
+            (best.context("Error while solving")?, None)
+        }
+        RunMode::Removal { cost } => {
+            let best = removal_loop(project, encoding, limits, cost, solver_config, budget).await;
+            (best.context("Error while solving")?, None)
+        }
+        RunMode::Minimize | RunMode::Alternatives { .. } => {
+            let alternatives = match mode {
+                RunMode::Alternatives { count, min_distance } => {
+                    Some(AlternativesOptions { count, min_distance })
                 }
-            }
-        });
-        let _guard = ctrl_c_cancellation.drop_guard();
+                _ => None,
+            };
+            let options =
+                MinimizeOptions { budget, proof_dir: proof, alternatives, ..Default::default() };
+            let outcome = solver_loop(project, encoding, limits, solver_config, options)
+                .await
+                .context("Error while solving")?;
+            (outcome.best, outcome.alternatives)
+        }
+    };
+    println!("Done");
+    Ok((best, alternatives))
+}
 
-        let (result, solver) = solver_future.future().await?;
-        let layout = match result {
-            SolverResult::Sat => {
-                PlatformLayout::from_assignment(&solver.full_solution()?, encoding.vars())
-            }
-            SolverResult::Unsat => {
-                println!("No solution found for the current constraints");
-                return Ok(());
-            }
-            SolverResult::Interrupted => {
-                println!("Solver interrupted");
-                return Ok(());
-            }
-        };
+/// Like [`solver_loop`], but maximizes the supported terrain rather than
+/// requiring all of it to be supported.
+async fn partial_support_loop(
+    project: &Project,
+    encoding: &Encoding,
+    limits: PlatformLimits,
+    solver_config: &SolverConfig,
+    budget: SolveBudget,
+) -> anyhow::Result<Option<PlatformLayout>> {
+    info!("Using solver backend: {solver_config}");
+
+    let (progress, status_line) = status_line();
+    let world = project.world.clone();
+    let (support_future, stop) = run_maximize_support(
+        encoding.clone(),
+        limits,
+        solver_config.clone(),
+        budget,
+        Some(progress),
+        move |improvement| print_support_improvement(&world, improvement),
+    );
+    let _guard = stop_on_ctrl_c(stop);
+
+    let outcome = support_future.future().await;
+    _ = status_line.await;
+    let outcome = outcome?;
+    match &outcome.best {
+        Some(_) => println!(
+            "Stopped after {:.1?}: {} (best solution supports {} of {} terrain tiles)",
+            outcome.elapsed, outcome.stop_reason, outcome.supported, outcome.total
+        ),
+        None if outcome.stop_reason == StopReason::Unsat => {
+            println!("No solution found for the current constraints");
+        }
+        None => println!("Stopped after {:.1?}: {}", outcome.elapsed, outcome.stop_reason),
+    }
+    Ok(outcome.best)
+}
 
-        if layout.platform_count() == 0 {
-            println!("Found a solution with no platforms - aborting");
-            return Ok(());
+/// Like [`solver_loop`], but terrain may also be dug out at `removal_cost` per
+/// tile rather than supported.
+async fn removal_loop(
+    project: &Project,
+    encoding: &Encoding,
+    limits: PlatformLimits,
+    removal_cost: isize,
+    solver_config: &SolverConfig,
+    budget: SolveBudget,
+) -> anyhow::Result<Option<PlatformLayout>> {
+    info!("Using solver backend: {solver_config}");
+
+    let (progress, status_line) = status_line();
+    let world = project.world.clone();
+    let (removal_future, stop) = run_minimize_with_removal(
+        encoding.clone(),
+        limits,
+        removal_cost,
+        solver_config.clone(),
+        budget,
+        Some(progress),
+        move |improvement| print_removal_improvement(&world, improvement),
+    );
+    let _guard = stop_on_ctrl_c(stop);
+
+    let outcome = removal_future.future().await;
+    _ = status_line.await;
+    let outcome = outcome?;
+    match (&outcome.best, outcome.cost) {
+        (Some(best), Some(cost)) => {
+            println!(
+                "Stopped after {:.1?}: {} (best solution: {} platforms, {} tiles removed, cost {})",
+                outcome.elapsed,
+                outcome.stop_reason,
+                best.platform_count(),
+                best.removed_tiles().len(),
+                cost
+            );
+            let mut removed = best.removed_tiles().iter().collect::<Vec<_>>();
+            removed.sort_by_key(|p| (p.y, p.x));
+            if !removed.is_empty() {
+                let removed: Vec<_> =
+                    removed.iter().map(|p| format!("({};{})", p.x, p.y)).collect();
+                println!("Tiles to dig out: {}", removed.join(", "));
+            }
+        }
+        _ if outcome.stop_reason == StopReason::Unsat => {
+            println!("No solution found for the current constraints");
         }
+        _ => println!("Stopped after {:.1?}: {}", outcome.elapsed, outcome.stop_reason),
+    }
+    Ok(outcome.best)
+}
 
-        limits.card_limits.entry(platform_def!(1, 1)).insert_entry(layout.platform_count() - 1);
+/// Encodes `world` again for importing a solution, and checks that the
+/// encoding matches the fingerprint in the `vars` file written by `export`.
+fn reencode_for_import(vars: &Path, world: &World) -> anyhow::Result<Encoding> {
+    let file = fs::File::open(vars).context("Failed to open the variable map")?;
+    let Some(fingerprint) = export::read_var_map_fingerprint(io::BufReader::new(file))
+        .context("Failed to read the variable map")?
+    else {
+        bail!("The variable map has no fingerprint, export the instance again");
+    };
 
-        println!("Solution found ({} platforms total)", layout.platform_count());
-        let platform_stats = layout.platform_stats();
-        for (def, count) in platform_stats.iter() {
-            println!("{}: {}", def.dimensions_str(), count);
+    let encoding = Encoding::encode(&PLATFORMS_DEFAULT, world.grid());
+    if encoding.fingerprint() != fingerprint {
+        bail!(
+            "The loaded project's encoding ({:016x}) isn't the exported one ({fingerprint:016x}); \
+             the terrain, platforms or encoder have changed",
+            encoding.fingerprint()
+        );
+    }
+    Ok(encoding)
+}
+
+/// Shows solver progress on a status line. The returned task ends once the
+/// sender is dropped.
+fn status_line() -> (ProgressSender, tokio::task::JoinHandle<()>) {
+    let (progress, progress_rx) = ProgressSender::channel(PROGRESS_INTERVAL);
+    let status_line = tokio::task::spawn_blocking(move || {
+        for progress in progress_rx {
+            eprint!("{CLEAR_LINE}{progress}");
+            _ = io::stderr().flush();
         }
-        let validation = layout.validate(&project.world);
-        if validation.is_valid() {
-            info!("Solution validation OK");
-        } else {
-            warn!("Solution validation FAILED");
-            for printout in validation.iter_error_printouts() {
-                warn!("Validation failed: {}:\n{}", printout.header, printout.items.join("\n"));
+        eprint!("{CLEAR_LINE}");
+    });
+    (progress, status_line)
+}
+
+/// Stops the run on Ctrl-C, until the returned guard is dropped.
+fn stop_on_ctrl_c(stop: StopHandle) -> DropGuard {
+    let ctrl_c_cancellation = CancellationToken::new();
+    tokio::spawn({
+        let cancel = ctrl_c_cancellation.clone();
+        async move {
+            trace!(target: "solver_interrupter", "Interrupt listener ready");
+            // TODO: better ctrl-c/interrupt handling
+            // Example: on unix, this listener will continue to capture SIGINT even after
+            // going out of scope, which might eat further SIGINTs if the program gets stuck
+            // elsewhere etc.
+            // A global Ctrl-C listener that can cancel this whole task might be better
+
+            match tokio::signal::ctrl_c().with_cancellation_token_owned(cancel).await {
+                None => {
+                    trace!(target: "solver_interrupter", "Interrupt listener canceled");
+                }
+                Some(Ok(())) => {
+                    println!("Aborting...");
+                    stop.stop();
+                }
+                Some(Err(err)) => {
+                    error!("Interrupt listener error: {err}");
+                }
             }
         }
+    });
+    ctrl_c_cancellation.drop_guard()
+}
+
+/// Explains which limits and terrain regions can't be satisfied together.
+async fn print_infeasibility(
+    encoding: &Encoding,
+    limits: PlatformLimits,
+    solver_config: &SolverConfig,
+) {
+    // Needs assumptions, which only the built-in backends have
+    let kind = match solver_config.clone().without_seed() {
+        SolverConfig::Builtin(kind) => Some(kind),
+        _ => SolverKind::default_enabled(),
+    };
+    let Some(kind) = kind else {
+        return;
+    };
+
+    let encoding = encoding.clone();
+    let explanation =
+        tokio::task::spawn_blocking(move || explain_infeasibility(&encoding, &limits, kind)).await;
+    match explanation {
+        Ok(Ok(Some(infeasibility))) => println!("Reason: {infeasibility}"),
+        Ok(Ok(None)) => {}
+        Ok(Err(err)) => warn!("Could not explain why: {err}"),
+        Err(err) => error!("Could not explain why: {err}"),
+    }
+}
+
+/// How often the status line is updated while solving
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+/// Moves to the start of the line and clears it
+const CLEAR_LINE: &str = "\r\x1b[2K";
+
+fn print_support_improvement(world: &World, improvement: SupportImprovement) {
+    let layout = improvement.layout;
+    eprint!("{CLEAR_LINE}");
+    println!(
+        "Solution found after {:.1?} ({} platforms, supporting {} terrain tiles)",
+        improvement.elapsed,
+        layout.platform_count(),
+        improvement.supported
+    );
+    let validation = layout.validate(world);
+    // Unsupported terrain is expected here
+    let errors = validation.without_unsupported_terrain();
+    if errors.is_valid() {
+        info!(
+            "Solution validation OK, {} tiles left unsupported",
+            validation.unsupported_terrain.len()
+        );
+    } else {
+        warn!("Solution validation FAILED");
+        for printout in errors.iter_error_printouts() {
+            warn!("Validation failed: {}:\n{}", printout.header, printout.items.join("\n"));
+        }
+    }
+
+    info!("Solver stats:\n{:#?}", improvement.solver_stats);
+    print_world(world, Some(layout), &validation);
+}
+
+fn print_removal_improvement(world: &World, improvement: RemovalImprovement) {
+    let layout = improvement.layout;
+    eprint!("{CLEAR_LINE}");
+    println!(
+        "Solution found after {:.1?} ({} platforms, {} tiles removed, cost {})",
+        improvement.elapsed,
+        layout.platform_count(),
+        layout.removed_tiles().len(),
+        improvement.cost
+    );
+    let validation = layout.validate(world);
+    if validation.is_valid() {
+        info!("Solution validation OK");
+    } else {
+        warn!("Solution validation FAILED");
+        for printout in validation.iter_error_printouts() {
+            warn!("Validation failed: {}:\n{}", printout.header, printout.items.join("\n"));
+        }
+    }
+
+    info!("Solver stats:\n{:#?}", improvement.solver_stats);
+    print_world(world, Some(layout), &validation);
+}
 
-        info!("Solver stats:\n{:#?}", solver.stats());
-        print_world(&project.world, Some(&layout), &validation);
+fn print_improvement(world: &World, improvement: Improvement) {
+    let layout = improvement.layout;
+    eprint!("{CLEAR_LINE}");
+    println!(
+        "Solution found after {:.1?} ({} platforms total)",
+        improvement.elapsed,
+        layout.platform_count()
+    );
+    let platform_stats = layout.platform_stats();
+    for (def, count) in platform_stats.iter() {
+        println!("{}: {}", def.dimensions_str(), count);
+    }
+    let validation = layout.validate(world);
+    if validation.is_valid() {
+        info!("Solution validation OK");
+    } else {
+        warn!("Solution validation FAILED");
+        for printout in validation.iter_error_printouts() {
+            warn!("Validation failed: {}:\n{}", printout.header, printout.items.join("\n"));
+        }
     }
+
+    info!("Solver stats:\n{:#?}", improvement.solver_stats);
+    print_world(world, Some(layout), &validation);
 }
 
 #[tokio::main]
@@ -371,30 +1513,57 @@ async fn main() -> anyhow::Result<()> {
 
     repl_loop().await?;
 
-    // Earlier logging code
-    // {
-    //     fs::create_dir_all("logs/")?;
-    //     let path = format!("logs/{run_timestamp}_vars.log");
-    //     info!("Writing variable map to {path}");
-    //     let mut file = File::create_new(&path)?;
-    //     solver.vars().write_var_map(&mut file)?;
-    //
-    //     let path = format!("logs/{run_timestamp}_dimacs.log");
-    //     info!("Writing DIMACS to {path}");
-    //     let mut file = File::create(&path)?;
-    //     solver.instance().write_dimacs(&mut file)?
-    // }
     Ok(())
 }
 
+fn format_platform(plat: &Platform) -> String {
+    format!(
+        "{}x{} at ({};{})",
+        plat.dims().width,
+        plat.dims().height,
+        plat.point().x,
+        plat.point().y
+    )
+}
+
+fn format_change(change: &PlatformChange) -> String {
+    match change {
+        PlatformChange::Added(plat) => format!("{} {}", "added".green(), format_platform(plat)),
+        PlatformChange::Removed(plat) => format!("{} {}", "removed".red(), format_platform(plat)),
+        PlatformChange::Moved { from, to } => {
+            format!("{} {} to {}", "moved".cyan(), format_platform(from), format_platform(to))
+        }
+        PlatformChange::Resized { from, to } => {
+            format!("{} {} to {}", "resized".yellow(), format_platform(from), format_platform(to))
+        }
+    }
+}
+
 fn print_world(world: &World, solution: Option<&PlatformLayout>, validation: &ValidationResult) {
+    print_world_with_diff(world, solution, validation, None);
+}
+
+/// Like [`print_world`], but colors the platforms that changed in `diff`, and
+/// marks where removed platforms used to be.
+fn print_world_with_diff(
+    world: &World,
+    solution: Option<&PlatformLayout>,
+    validation: &ValidationResult,
+    diff: Option<&LayoutDiff>,
+) {
     let terrain_grid = world.grid();
     let dims = terrain_grid.dims();
 
     #[derive(Copy, Clone, Debug)]
     enum Tile {
         Empty,
-        Terrain { unsupported: bool },
+        Terrain {
+            unsupported: bool,
+        },
+        /// Terrain the solution digs out
+        Removed,
+        /// Where a platform was before the diff
+        RemovedPlatform,
         Platform(PlatformTile),
     }
 
@@ -405,8 +1574,15 @@ fn print_world(world: &World, solution: Option<&PlatformLayout>, validation: &Va
         west_edge: bool,
         east_edge: bool,
         overlapping: bool,
+        change: Option<PlatformChange>,
     }
 
+    let changes: HashMap<Point, PlatformChange> = diff
+        .iter()
+        .flat_map(|diff| diff.changes.iter())
+        .filter_map(|&change| change.after().map(|plat| (plat.point(), change)))
+        .collect();
+
     let mut tile_grid = Grid::new_fill(dims, Tile::Empty);
 
     for p in terrain_grid.enumerate().filter_map(|(p, val)| val.then_some(p)) {
@@ -415,7 +1591,18 @@ fn print_world(world: &World, solution: Option<&PlatformLayout>, validation: &Va
             .unwrap();
     }
 
+    for change in diff.iter().flat_map(|diff| diff.changes.iter()) {
+        if let PlatformChange::Removed(plat) | PlatformChange::Moved { from: plat, .. } = change {
+            for rel_point in plat.dims().iter_within() {
+                _ = tile_grid.set(rel_point + plat.point(), Tile::RemovedPlatform);
+            }
+        }
+    }
+
     if let Some(solution) = solution {
+        for &p in solution.removed_tiles() {
+            _ = tile_grid.set(p, Tile::Removed);
+        }
         for platform in solution.platforms().values() {
             let offset = platform.point();
 
@@ -427,6 +1614,7 @@ fn print_world(world: &World, solution: Option<&PlatformLayout>, validation: &Va
                     west_edge: rel_point.x == 0,
                     east_edge: rel_point.x == (dims.width - 1) as isize,
                     overlapping: validation.overlapping_platforms.contains(platform),
+                    change: changes.get(&platform.point()).copied(),
                 };
                 // Pass if out of bounds
                 _ = tile_grid.set(rel_point + offset, Tile::Platform(tile));
@@ -444,12 +1632,15 @@ fn print_world(world: &World, solution: Option<&PlatformLayout>, validation: &Va
                     let out = block_char::MEDIUM_SHADE.to_string();
                     if unsupported { out.yellow().to_string() } else { out }
                 }
+                Tile::Removed => block_char::MEDIUM_SHADE.magenta().to_string(),
+                Tile::RemovedPlatform => '×'.red().to_string(),
                 Tile::Platform(PlatformTile {
                     north_edge: mut n,
                     south_edge: mut s,
                     west_edge: mut w,
                     east_edge: mut e,
                     overlapping,
+                    change,
                 }) => {
                     // NSWE are true if there's _empty space_ in that direction
                     // The box chars function expects the opposite - where to connect to
@@ -480,7 +1671,13 @@ fn print_world(world: &World, solution: Option<&PlatformLayout>, validation: &Va
                         box_char::by_adjacency_nswe(!n, !s, !w, !e).to_string()
                     };
 
-                    if overlapping { out.red().to_string() } else { out }
+                    match change {
+                        _ if overlapping => out.red().to_string(),
+                        Some(PlatformChange::Added(_)) => out.green().to_string(),
+                        Some(PlatformChange::Moved { .. }) => out.cyan().to_string(),
+                        Some(PlatformChange::Resized { .. }) => out.yellow().to_string(),
+                        Some(PlatformChange::Removed(_)) | None => out,
+                    }
                 }
             };
             print!("{tile_str}  ");
@@ -489,6 +1686,33 @@ fn print_world(world: &World, solution: Option<&PlatformLayout>, validation: &Va
     }
 }
 
+/// Prints the support distance of each terrain tile: green while there's slack,
+/// yellow at the limit, red past it, and a red `?` for disconnected terrain.
+fn print_support_map(world: &World, layout: &PlatformLayout, map: &SupportMap) {
+    for (p, distance) in map.distances.enumerate() {
+        let is_terrain = world.grid().get(p) == Some(&true);
+        let tile_str = match *distance {
+            _ if layout.removed_tiles().contains(&p) => {
+                block_char::MEDIUM_SHADE.magenta().to_string()
+            }
+            None if is_terrain => "?".red().to_string(),
+            None => " ".to_string(),
+            Some(d) => {
+                let digit = char::from_digit(d.min(9) as u32, 10).unwrap();
+                match d.cmp(&MAX_SUPPORT_DISTANCE) {
+                    Ordering::Less => digit.green().to_string(),
+                    Ordering::Equal => digit.yellow().to_string(),
+                    Ordering::Greater => digit.red().to_string(),
+                }
+            }
+        };
+        print!("{tile_str}  ");
+        if p.x == map.distances.dims().width as isize - 1 {
+            println!();
+        }
+    }
+}
+
 fn load_toml(path: PathBuf) -> anyhow::Result<Project> {
     println!(
         "Opening file {}",
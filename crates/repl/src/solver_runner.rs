@@ -1,34 +1,105 @@
-use anyhow::{Context, anyhow};
+use std::collections::HashMap;
+
+use anyhow::anyhow;
 use futures::TryFutureExt;
-use rustsat::{
-    instances::Cnf,
-    solvers::{Interrupt, Solve, SolverResult},
+use timberborn_platform_cruncher::{
+    encoder::{Encoding, PlatformLimits},
+    solver::{
+        SolverConfig,
+        budget::{SolveBudget, StopHandle},
+        minimize::{Improvement, MinimizeOptions, MinimizeOutcome, minimize},
+        partial_support::{SupportImprovement, SupportOutcome, maximize_support},
+        progress::ProgressSender,
+        removal::{RemovalImprovement, RemovalOutcome, minimize_with_removal},
+    },
 };
 
-pub fn run_solver<S>(mut solver: S, cnf: Cnf) -> anyhow::Result<(SolverFuture<S>, S::Interrupter)>
-where
-    S: Solve + Interrupt + Send + 'static,
-{
-    solver.add_cnf(cnf).context("Failed to add CNF")?;
-    let interrupter = solver.interrupter();
+pub fn run_minimize(
+    encoding: Encoding,
+    limits: PlatformLimits,
+    solver_config: SolverConfig,
+    options: MinimizeOptions,
+    on_solution: impl FnMut(Improvement) + Send + 'static,
+) -> (MinimizeFuture, StopHandle) {
+    let stop = StopHandle::default();
+
+    let handle = tokio::task::spawn_blocking({
+        let stop = stop.clone();
+        move || minimize(&encoding, limits, &solver_config, options, &stop, on_solution)
+    });
+
+    (MinimizeFuture { handle }, stop)
+}
+
+pub fn run_maximize_support(
+    encoding: Encoding,
+    limits: PlatformLimits,
+    solver_config: SolverConfig,
+    budget: SolveBudget,
+    progress: Option<ProgressSender>,
+    on_solution: impl FnMut(SupportImprovement) + Send + 'static,
+) -> (MinimizeFuture<SupportOutcome>, StopHandle) {
+    let stop = StopHandle::default();
+
+    let handle = tokio::task::spawn_blocking({
+        let stop = stop.clone();
+        move || {
+            maximize_support(
+                &encoding,
+                &limits,
+                &HashMap::new(),
+                &solver_config,
+                budget,
+                &stop,
+                progress,
+                on_solution,
+            )
+        }
+    });
+
+    (MinimizeFuture { handle }, stop)
+}
+
+pub fn run_minimize_with_removal(
+    encoding: Encoding,
+    limits: PlatformLimits,
+    removal_cost: isize,
+    solver_config: SolverConfig,
+    budget: SolveBudget,
+    progress: Option<ProgressSender>,
+    on_solution: impl FnMut(RemovalImprovement) + Send + 'static,
+) -> (MinimizeFuture<RemovalOutcome>, StopHandle) {
+    let stop = StopHandle::default();
 
-    let handle = tokio::task::spawn_blocking(move || -> anyhow::Result<(SolverResult, S)> {
-        Ok((solver.solve()?, solver))
+    let handle = tokio::task::spawn_blocking({
+        let stop = stop.clone();
+        move || {
+            minimize_with_removal(
+                &encoding,
+                &limits,
+                removal_cost,
+                &solver_config,
+                budget,
+                &stop,
+                progress,
+                on_solution,
+            )
+        }
     });
 
-    Ok((SolverFuture { handle }, interrupter))
+    (MinimizeFuture { handle }, stop)
 }
 
-pub struct SolverFuture<S> {
-    handle: tokio::task::JoinHandle<anyhow::Result<(SolverResult, S)>>,
+pub struct MinimizeFuture<T = MinimizeOutcome> {
+    handle: tokio::task::JoinHandle<anyhow::Result<T>>,
 }
 
-impl<S> SolverFuture<S> {
-    pub fn handle(&self) -> &tokio::task::JoinHandle<anyhow::Result<(SolverResult, S)>> {
+impl<T> MinimizeFuture<T> {
+    pub fn handle(&self) -> &tokio::task::JoinHandle<anyhow::Result<T>> {
         &self.handle
     }
 
-    pub fn future(self) -> impl Future<Output = anyhow::Result<(SolverResult, S)>> {
+    pub fn future(self) -> impl Future<Output = anyhow::Result<T>> {
         self.handle.unwrap_or_else(|join_err| Err(anyhow!(join_err)))
     }
 }
@@ -0,0 +1,341 @@
+//! # Exact reference solver
+//!
+//! An exhaustive, non-SAT search for minimum platform layouts. It's only meant
+//! for tiny worlds (up to [`MAX_TILES`] tiles, e.g. 8x8), where it serves as a
+//! ground truth for checking the results of the SAT [`Encoding`].
+//!
+//! Every tile is represented by one bit of a `u64`. For each possible platform
+//! placement, the set of terrain tiles it supports is computed up front (support
+//! from several platforms is simply a union of these sets). The search then
+//! uses iterative deepening on the platform count: the first unsupported tile
+//! must be supported by one of the placements that can reach it, so only those
+//! are branched on. The first layout found is therefore a minimum one.
+//!
+//! [`Encoding`]: crate::encoder::Encoding
+
+use std::{
+    collections::HashSet,
+    error::Error,
+    fmt::{Display, Formatter},
+};
+
+use itertools::Itertools;
+
+use crate::{
+    TERRAIN_SUPPORT_DISTANCE,
+    encoder::PlatformLayout,
+    math::{Dimensions, Point},
+    platform::{Platform, PlatformDef},
+    world::World,
+};
+
+/// The largest number of tiles (width * height) the solver accepts.
+pub const MAX_TILES: usize = u64::BITS as usize;
+
+/// The world is too large for the reference solver (see [`MAX_TILES`]).
+#[derive(Copy, Clone, Debug)]
+pub struct WorldTooLarge(pub Dimensions);
+
+impl Display for WorldTooLarge {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "world is too large for the reference solver ({}x{}, at most {MAX_TILES} tiles)",
+            self.0.width, self.0.height
+        )
+    }
+}
+
+impl Error for WorldTooLarge {}
+
+/// Finds a layout with the minimum number of platforms for the given world.
+///
+/// Returns `Ok(None)` if no layout exists (e.g. if no platform fits).
+pub fn solve_minimum(
+    world: &World,
+    platform_defs: &[PlatformDef],
+) -> Result<Option<PlatformLayout>, WorldTooLarge> {
+    let board = Board::new(world)?;
+    let placements = board.placements(platform_defs);
+
+    // Candidates for each tile, largest support first, so that good layouts are
+    // found early in each iteration
+    let mut candidates: Vec<Vec<usize>> = vec![vec![]; board.tile_count()];
+    for (i, placement) in placements.iter().enumerate() {
+        for tile in iter_bits(placement.support) {
+            candidates[tile].push(i);
+        }
+    }
+    for tile_candidates in candidates.iter_mut() {
+        tile_candidates.sort_by_key(|&i| std::cmp::Reverse(placements[i].support.count_ones()));
+    }
+    // No layout can hold up a tile without candidates, however many platforms
+    if iter_bits(board.terrain).any(|tile| candidates[tile].is_empty()) {
+        return Ok(None);
+    }
+
+    let search = Search {
+        terrain: board.terrain,
+        placements: &placements,
+        candidates: &candidates,
+        max_support: placements.iter().map(|p| p.support.count_ones()).max().unwrap_or(0),
+    };
+
+    let terrain_count = board.terrain.count_ones() as usize;
+    let mut chosen = Vec::with_capacity(terrain_count);
+    for depth in 0..=terrain_count {
+        if search.search(depth, 0, 0, &mut chosen) {
+            let layout =
+                PlatformLayout::from_platforms(chosen.iter().map(|&i| placements[i].platform));
+            return Ok(Some(layout));
+        }
+    }
+
+    Ok(None)
+}
+
+struct Board {
+    dims: Dimensions,
+    terrain: u64,
+    neighbors: Vec<u64>,
+}
+
+struct Placement {
+    platform: Platform,
+    footprint: u64,
+    support: u64,
+}
+
+impl Board {
+    fn new(world: &World) -> Result<Self, WorldTooLarge> {
+        let dims = world.grid().dims();
+        if dims.width * dims.height > MAX_TILES {
+            return Err(WorldTooLarge(dims));
+        }
+
+        let mut board = Board { dims, terrain: 0, neighbors: vec![] };
+        board.terrain = board.mask(world.grid().enumerate().filter_map(|(p, &t)| t.then_some(p)));
+        board.neighbors = dims.iter_within().map(|p| board.mask(p.neighbors())).collect();
+        Ok(board)
+    }
+
+    fn tile_count(&self) -> usize {
+        self.dims.width * self.dims.height
+    }
+
+    fn bit(&self, point: Point) -> Option<u64> {
+        self.dims
+            .contains(point)
+            .then(|| 1 << (point.x as usize + point.y as usize * self.dims.width))
+    }
+
+    /// Bitmask of the given points, ignoring any that are out of bounds.
+    fn mask(&self, points: impl IntoIterator<Item = Point>) -> u64 {
+        points.into_iter().filter_map(|p| self.bit(p)).fold(0, |acc, bit| acc | bit)
+    }
+
+    /// Extends support from the given terrain tiles, same as
+    /// [`PlatformLayout::validate`].
+    fn extend_support(&self, mut supported: u64) -> u64 {
+        for _ in 0..(TERRAIN_SUPPORT_DISTANCE - 1) {
+            let reached = iter_bits(supported).fold(0, |acc, tile| acc | self.neighbors[tile]);
+            supported |= reached & self.terrain;
+        }
+        supported
+    }
+
+    /// All in-bounds placements that support at least one terrain tile.
+    fn placements(&self, platform_defs: &[PlatformDef]) -> Vec<Placement> {
+        // Rotated variants may coincide with other definitions, so placements are
+        // deduplicated by their dimensions
+        let mut seen_dims = HashSet::new();
+        let variants = platform_defs
+            .iter()
+            .flat_map(|&def| [(def, false), (def, true)])
+            .filter(|&(def, rotated)| {
+                seen_dims.insert(if rotated { def.dims().flipped() } else { def.dims() })
+            })
+            .collect_vec();
+
+        let mut placements = vec![];
+        for (def, rotated) in variants {
+            for point in self.dims.iter_within() {
+                let platform = Platform::new(point, def, rotated);
+                let Some((_, far)) = platform.area_corners() else {
+                    continue;
+                };
+                if !self.dims.contains(far) {
+                    continue;
+                }
+
+                let footprint = self.mask(platform.dims().iter_within().map(|p| p + point));
+                let support = self.extend_support(footprint & self.terrain);
+                if support != 0 {
+                    placements.push(Placement { platform, footprint, support });
+                }
+            }
+        }
+        placements
+    }
+}
+
+struct Search<'a> {
+    terrain: u64,
+    placements: &'a [Placement],
+    candidates: &'a [Vec<usize>],
+    max_support: u32,
+}
+
+impl Search<'_> {
+    /// Depth-limited search, pushing the chosen placements onto `chosen`.
+    ///
+    /// Returns true if all terrain is supported within `depth` more platforms,
+    /// in which case `chosen` holds the solution.
+    fn search(&self, depth: usize, occupied: u64, supported: u64, chosen: &mut Vec<usize>) -> bool {
+        let unsupported = self.terrain & !supported;
+        if unsupported == 0 {
+            return true;
+        }
+        // Even the best placements can't cover the rest
+        if (depth as u64) * (self.max_support as u64) < unsupported.count_ones() as u64 {
+            return false;
+        }
+
+        let tile = unsupported.trailing_zeros() as usize;
+        for &i in self.candidates[tile].iter() {
+            let placement = &self.placements[i];
+            if placement.footprint & occupied != 0 {
+                continue;
+            }
+
+            chosen.push(i);
+            if self.search(
+                depth - 1,
+                occupied | placement.footprint,
+                supported | placement.support,
+                chosen,
+            ) {
+                return true;
+            }
+            chosen.pop();
+        }
+
+        false
+    }
+}
+
+/// Iterates the indices of set bits.
+fn iter_bits(mut bits: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        (bits != 0).then(|| {
+            let i = bits.trailing_zeros() as usize;
+            bits &= bits - 1;
+            i
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rustsat::solvers::{Solve, SolverResult};
+    use rustsat_glucose::simp::Glucose;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::{
+        encoder::{Encoding, PlatformLimits},
+        math::Grid,
+        platform::PLATFORMS_DEFAULT,
+        platform_def,
+        test_utils::filled_world,
+        world::WorldGrid,
+    };
+
+    /// Minimum platform count via the SAT encoding, stepping the total platform
+    /// limit down like the REPL does.
+    fn sat_minimum(world: &World, platform_defs: &[PlatformDef]) -> Option<usize> {
+        let encoding = Encoding::encode(platform_defs, world.grid());
+        let mut limits = PlatformLimits::default();
+        let mut best = None;
+        loop {
+            let (cnf, _) = encoding.with_limits(&limits).into_cnf();
+            let mut solver = Glucose::default();
+            solver.add_cnf(cnf).unwrap();
+            match solver.solve().unwrap() {
+                SolverResult::Sat => {}
+                SolverResult::Unsat => return best,
+                SolverResult::Interrupted => unreachable!(),
+            }
+
+            let layout =
+                PlatformLayout::from_assignment(&solver.full_solution().unwrap(), encoding.vars());
+            assert!(layout.validate(world).is_valid());
+
+            let count = layout.platform_count();
+            best = Some(count);
+            let Some(next_limit) = count.checked_sub(1) else {
+                return best;
+            };
+            limits.card_limits.insert(platform_def!(1, 1), next_limit);
+        }
+    }
+
+    /// Small xorshift generator, to keep the tests deterministic without extra
+    /// dependencies.
+    fn random_world(seed: u64, dims: Dimensions) -> World {
+        let mut state = seed.max(1);
+        let grid = Grid::from_fn(dims, |_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % 3 != 0
+        });
+        World::new(WorldGrid(grid))
+    }
+
+    #[test_case(3, 3, 1)]
+    #[test_case(5, 5, 1)]
+    #[test_case(8, 8, 2)]
+    fn filled_world_minimum(width: usize, height: usize, expected: usize) {
+        let world = filled_world(width, height);
+        let layout = solve_minimum(&world, &PLATFORMS_DEFAULT).unwrap().unwrap();
+
+        assert_eq!(layout.platform_count(), expected);
+        assert!(layout.validate(&world).is_valid());
+    }
+
+    #[test]
+    fn unsupportable_tile() {
+        // A 3x3 platform doesn't fit in 2 rows
+        let world = filled_world(8, 2);
+        assert!(solve_minimum(&world, &[platform_def!(3, 3)]).unwrap().is_none());
+    }
+
+    #[test]
+    fn world_too_large() {
+        let world = filled_world(9, 8);
+        assert!(solve_minimum(&world, &PLATFORMS_DEFAULT).is_err());
+    }
+
+    #[test_case(&PLATFORMS_DEFAULT; "default platforms")]
+    #[test_case(&[platform_def!(1, 1)]; "1x1 only")]
+    #[test_case(&[platform_def!(1, 3), platform_def!(3, 3)]; "without 1x1")]
+    fn cross_check_encoding(platform_defs: &[PlatformDef]) {
+        for seed in 1..=12 {
+            let dims = Dimensions::new(4 + (seed as usize % 3), 4 + (seed as usize / 3 % 3));
+            let world = random_world(seed, dims);
+
+            let exact = solve_minimum(&world, platform_defs).unwrap();
+            if let Some(layout) = &exact {
+                assert!(layout.validate(&world).is_valid(), "seed {seed}: {layout:#?}");
+            }
+
+            assert_eq!(
+                exact.map(|layout| layout.platform_count()),
+                sat_minimum(&world, platform_defs),
+                "minimum platform count mismatch for seed {seed}:\n{:#?}",
+                world.grid()
+            );
+        }
+    }
+}
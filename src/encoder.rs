@@ -79,7 +79,7 @@
 
 use std::{
     cmp::Ordering,
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
     fmt::Debug,
     hash::Hash,
     iter,
@@ -106,13 +106,26 @@ use crate::{
     math::{Dimensions, Point},
     platform::{Platform, PlatformDef},
     typed_ix::TypedIx,
+    utils::Fnv1a,
     world::WorldGrid,
 };
 
+pub mod diagnostics;
+mod encoding_stats;
+pub mod export;
+pub mod layout_diff;
+pub mod layout_file;
+pub mod merging;
 mod platform_layout;
 mod platform_limits;
+pub mod redundancy;
+pub mod support_forest;
+pub mod support_map;
 
-pub use platform_layout::{PlatformLayout, ValidationErrorPrintout, ValidationResult};
+pub use encoding_stats::{ClauseOrigin, EncodingStats};
+pub use platform_layout::{
+    FaultToleranceResult, PlatformLayout, ValidationErrorPrintout, ValidationResult,
+};
 pub use platform_limits::PlatformLimits;
 
 use crate::math::Grid;
@@ -165,19 +178,34 @@ impl EncodingTileVars {
     pub fn for_dims(&self, dims: Dimensions) -> Option<Var> {
         self.dims_vars.get(&dims).cloned()
     }
+
+    /// The terrain support layers, starting with the tile's own support. `None`
+    /// if there's no terrain.
+    pub fn terrain(&self) -> Option<[Var; TERRAIN_SUPPORT_DISTANCE]> {
+        self.terrain
+    }
 }
 
 #[derive(Clone, Debug)]
 pub enum EncodedItem {
     Platform { point: Point, dims: Dimensions }, // TODO: use the Platform struct
+    Cell { point: Point },                       // Virtual 1x1, see CELL_DIMS
     Terrain { point: Point, layer: usize },
 }
 
+/// Dimensions of the single-tile variables, which double as markers for the
+/// top-left corner of a platform in the overlap clauses.
+///
+/// These are always encoded. If the platform set has no 1x1 platform, they are
+/// virtual "cell" variables that never count as a platform on their own.
+pub const CELL_DIMS: Dimensions = Dimensions::new(1, 1);
+
 #[derive(Clone, Debug)]
 pub struct EncodingVars {
     dim_map: HashMap<Dimensions, HashSet<PlatformDef>>,
     grid: Grid<EncodingTileVars>,
     var_map: HashMap<Var, EncodedItem>,
+    virtual_cell: bool,
 }
 
 impl EncodingVars {
@@ -187,8 +215,15 @@ impl EncodingVars {
         var_man: &mut BasicVarManager,
     ) -> Self {
         let dim_map = dims_platform_map(platform_defs);
-
-        let dim_keys: Vec<_> = dim_map.keys().cloned().collect();
+        let virtual_cell = !dim_map.contains_key(&CELL_DIMS);
+
+        // Sorted, so that the vars are created in the same order on every run
+        let dim_keys: Vec<_> = dim_map
+            .keys()
+            .cloned()
+            .chain(virtual_cell.then_some(CELL_DIMS))
+            .sorted_by_key(|dims| (dims.width, dims.height))
+            .collect();
         let grid = Grid::from_fn(terrain.dims(), |p| EncodingTileVars {
             dims_vars: dim_keys.iter().cloned().map(|k| (k, var_man.new_var())).collect(),
             terrain: terrain.get(p).unwrap().then_some(std::array::from_fn(|_| var_man.new_var())),
@@ -196,13 +231,18 @@ impl EncodingVars {
         let mut var_map = HashMap::new();
         for (point, vars) in grid.enumerate() {
             for (&dims, &var) in vars.dims_vars.iter() {
-                var_map.insert(var, EncodedItem::Platform { point, dims });
+                let item = if virtual_cell && dims == CELL_DIMS {
+                    EncodedItem::Cell { point }
+                } else {
+                    EncodedItem::Platform { point, dims }
+                };
+                var_map.insert(var, item);
             }
             for (layer, var) in vars.terrain.iter().flatten().enumerate() {
                 var_map.insert(*var, EncodedItem::Terrain { point, layer });
             }
         }
-        Self { dim_map, grid, var_map }
+        Self { dim_map, grid, var_map, virtual_cell }
     }
 
     pub fn at(&self, point: Point) -> Option<&EncodingTileVars> {
@@ -211,16 +251,62 @@ impl EncodingVars {
     pub fn for_dims_at(&self, point: Point, dims: Dimensions) -> Option<Var> {
         self.grid.get(point)?.for_dims(dims)
     }
+    /// All platform dimensions, ordered by width and then height.
     pub fn platform_dims(&self) -> impl Iterator<Item = Dimensions> + Clone {
-        self.dim_map.keys().cloned()
+        self.dim_map.keys().cloned().sorted_by_key(|dims| (dims.width, dims.height))
     }
 
+    /// All dimensions that have a variable in each tile, including the virtual
+    /// 1x1 cell (see [`CELL_DIMS`]).
+    pub fn encoded_dims(&self) -> impl Iterator<Item = Dimensions> + Clone {
+        self.platform_dims().chain(self.virtual_cell.then_some(CELL_DIMS))
+    }
+
+    /// Whether the 1x1 variables are virtual cells rather than platforms.
+    pub fn has_virtual_cell(&self) -> bool {
+        self.virtual_cell
+    }
+
+    /// Iterates the vars of the given dimensions across all tiles.
+    ///
+    /// This includes the virtual 1x1 cell vars, so a 1x1 limit always counts
+    /// platforms of any size.
     pub fn iter_dims_vars(&self, dims: Dimensions) -> Option<impl Iterator<Item = Var>> {
-        self.dim_map
-            .contains_key(&dims)
+        self.encoded_dims()
+            .contains(&dims)
             .then_some(self.grid.iter().map(move |vars| vars.dims_vars[&dims]))
     }
 
+    /// The 1x1 vars of the `n` tiles closest to the center of the grid, i.e.
+    /// whether a platform starts there. Used to split the search into cubes.
+    pub fn central_cell_vars(&self, n: usize) -> Vec<Var> {
+        let dims = self.grid.dims();
+        let (cx, cy) = (dims.width as isize - 1, dims.height as isize - 1);
+        self.grid
+            .enumerate()
+            // Doubled distances, to keep the center integral
+            .sorted_by_key(|(p, _)| (2 * p.x - cx).pow(2) + (2 * p.y - cy).pow(2))
+            .take(n)
+            .map(|(_, vars)| vars.dims_vars[&CELL_DIMS])
+            .collect()
+    }
+
+    /// The values of all per-tile platform vars (incl. virtual cells) in the
+    /// given layout. A var is set iff the platform starting on its tile
+    /// contains its dimensions, same as in the solution the layout came from.
+    pub fn layout_lits(&self, layout: &PlatformLayout) -> Vec<Lit> {
+        self.grid
+            .enumerate()
+            .flat_map(|(point, tile_vars)| {
+                let platform_dims = layout.get_platform(point).map(|plat| plat.dims());
+                self.encoded_dims().map(move |dims| {
+                    let var = tile_vars.dims_vars[&dims];
+                    var.lit(!platform_dims.is_some_and(|platform_dims| dims <= platform_dims))
+                })
+            })
+            .collect()
+    }
+
     pub fn var_map(&self) -> &HashMap<Var, EncodedItem> {
         &self.var_map
     }
@@ -235,7 +321,7 @@ impl EncodingVars {
                 let def = self
                     .dim_map
                     .get(dims)
-                    .and_then(|s| s.iter().next())
+                    .and_then(|s| s.iter().min_by_key(|def| (def.dims().width, def.dims().height)))
                     .expect("encoded platform did not map to a platform def");
 
                 debug_assert!(def.dims() == *dims || def.dims() == dims.flipped());
@@ -260,6 +346,9 @@ impl EncodingVars {
                     point.y
                 )
             }
+            EncodedItem::Cell { point } => {
+                format!("{}C({};{})", if lit.is_neg() { "~" } else { "" }, point.x, point.y)
+            }
             EncodedItem::Terrain { point, layer } => {
                 format!(
                     "{}T{}({};{})",
@@ -429,6 +518,10 @@ impl EncodingDag {
 pub struct Encoding {
     vars: EncodingVars,
     instance: SatInstance,
+    /// The origin of each clause in `instance`, by index.
+    origins: Vec<ClauseOrigin>,
+    /// See [`Self::with_fault_tolerance`]
+    fault_tolerant: bool,
 }
 
 impl Encoding {
@@ -439,13 +532,39 @@ impl Encoding {
         let mut instance = SatInstance::<BasicVarManager>::new();
 
         let vars = EncodingVars::new(platform_defs, terrain, instance.var_manager_mut());
+        let mut origins = vec![];
 
-        let dag = EncodingDag::new(vars.platform_dims());
+        let dag = EncodingDag::new(vars.encoded_dims());
         // dbg!(&dag);
 
+        // Smallest real platforms, which the virtual cell (if any) has to imply
+        let cell_successors = if vars.has_virtual_cell() {
+            dag.iter_platform_edges_reduced()
+                .filter_map(|(smaller, larger)| (smaller == CELL_DIMS).then_some(larger))
+                .collect_vec()
+        } else {
+            vec![]
+        };
+
         for current_point in terrain.dims().iter_within() {
             let current_vars = vars.at(current_point).unwrap();
 
+            // ===== Virtual cell =====
+            // Without a real 1x1 platform, the cell var is only a marker. It must not
+            // support terrain on its own, so it implies one of the smallest platforms
+            // placed here. Larger platforms already imply the cell through the DAG.
+            // With no platforms at all, this becomes a [~c] unit clause.
+            if vars.has_virtual_cell() {
+                instance.add_lit_impl_clause(
+                    current_vars.for_dims(CELL_DIMS).unwrap().pos_lit(),
+                    &cell_successors
+                        .iter()
+                        .map(|&dims| current_vars.for_dims(dims).unwrap().pos_lit())
+                        .collect_vec(),
+                );
+            }
+            origins.resize(instance.n_clauses(), ClauseOrigin::DagImplication);
+
             // ===== Platform selection DAG =====
             for (smaller, larger) in dag.iter_platform_edges_reduced() {
                 // The DAG has nodes ordered as smaller -> larger
@@ -456,6 +575,7 @@ impl Encoding {
                     current_vars.for_dims(smaller).unwrap().pos_lit(),
                 );
             }
+            origins.resize(instance.n_clauses(), ClauseOrigin::DagImplication);
 
             for ((ix_a, dims_a), (ix_b, dims_b)) in dag
                 .iter_platform_targets_by_source()
@@ -487,6 +607,7 @@ impl Encoding {
                         .collect_vec(),
                 );
             }
+            origins.resize(instance.n_clauses(), ClauseOrigin::IncomparablePair);
 
             // ===== Platform-terrain clauses =====
             // Point -> disjunction of platforms
@@ -514,6 +635,7 @@ impl Encoding {
                     &platform_vars.map(|v| v.pos_lit()).collect_vec(),
                 );
             }
+            origins.resize(instance.n_clauses(), ClauseOrigin::PlatformTerrain);
 
             // ===== Terrain support =====
 
@@ -542,6 +664,7 @@ impl Encoding {
                 // TODO: this can be optimized trivially
                 instance.add_unit(point_terrain[0].pos_lit());
             }
+            origins.resize(instance.n_clauses(), ClauseOrigin::SupportLayer);
 
             // ===== Platform overlap =====
 
@@ -549,8 +672,8 @@ impl Encoding {
             // All platforms are aligned by their top-left corner point
             // Iterate all points supported by platforms at this position
             // For each point, forbid the 1x1 point at that location
-            // (Note: this assumes the existence of a 1x1 platform - this can be
-            // resolved by adding a "fake" 1x1 if it's missing, TODO later)
+            // (The 1x1 var always exists - if there's no 1x1 platform, it's a virtual
+            // cell var, see CELL_DIMS)
             // Then, for each point at the top edge (x; 0), iterate the left edge
             // (0; y), map those points to platforms supporting them, and forbid those.
 
@@ -561,9 +684,7 @@ impl Encoding {
                     // Skip (relative) 0;0 because that would make a platform restrict itself
                     (offset != Point::new(0, 0)).then_some((
                         current_vars.dims_vars[&dims],
-                        // Assumes that 1x1 exists!
-                        vars.at(current_point + offset)
-                            .map(|v| v.dims_vars[&Dimensions::new(1, 1)])?,
+                        vars.at(current_point + offset).map(|v| v.dims_vars[&CELL_DIMS])?,
                     ))
                 })
             {
@@ -594,6 +715,7 @@ impl Encoding {
                     instance.add_lit_impl_lit(plat_var.pos_lit(), other_plat_var.neg_lit());
                 }
             }
+            origins.resize(instance.n_clauses(), ClauseOrigin::Overlap);
 
             // ===== Out-of-bounds platforms =====
 
@@ -607,35 +729,192 @@ impl Encoding {
             }) {
                 instance.add_unit(plat_var.neg_lit());
             }
+            origins.resize(instance.n_clauses(), ClauseOrigin::OutOfBounds);
         }
 
-        Encoding { vars, instance }
+        Encoding { vars, instance, origins, fault_tolerant: false }
     }
 
     pub fn vars(&self) -> &EncodingVars {
         &self.vars
     }
 
+    /// Additionally requires every terrain tile to stay supported when any one
+    /// platform is removed, i.e. to be within reach of at least two platforms.
+    ///
+    /// Terrain doesn't change, so which tiles a platform at a given point would
+    /// support is known up front. For each tile, the platforms that can reach
+    /// it are grouped by their top-left point (there's one platform per point
+    /// in a layout), and at least two of these points must hold a platform.
+    /// Check the result with [`PlatformLayout::validate_fault_tolerance`].
+    pub fn with_fault_tolerance(mut self) -> Self {
+        let dims = self.vars.grid.dims();
+        let is_terrain = |p: Point| self.vars.at(p).is_some_and(|v| v.terrain.is_some());
+
+        // For each tile, the platforms that would support it, by point
+        let mut supporters: Grid<BTreeMap<Point, Vec<Dimensions>>> = Grid::new(dims);
+        for point in dims.iter_within() {
+            for plat_dims in self.vars.platform_dims() {
+                let Some(far) = plat_dims.corner_point_incl().map(|corner| corner + point) else {
+                    continue;
+                };
+                if !dims.contains(far) {
+                    continue;
+                }
+
+                let mut distances: HashMap<Point, usize> = plat_dims
+                    .iter_within()
+                    .map(|offset| offset + point)
+                    .filter(|&p| is_terrain(p))
+                    .map(|p| (p, 0))
+                    .collect();
+                let mut queue: VecDeque<Point> = distances.keys().copied().sorted().collect();
+                while let Some(p) = queue.pop_front() {
+                    let next = distances[&p] + 1;
+                    if next >= TERRAIN_SUPPORT_DISTANCE {
+                        continue;
+                    }
+                    for neighbor in p.neighbors() {
+                        if is_terrain(neighbor) && !distances.contains_key(&neighbor) {
+                            distances.insert(neighbor, next);
+                            queue.push_back(neighbor);
+                        }
+                    }
+                }
+                for &p in distances.keys() {
+                    supporters.get_mut(p).unwrap().entry(point).or_default().push(plat_dims);
+                }
+            }
+        }
+
+        for by_point in supporters.iter() {
+            let lits = by_point
+                .iter()
+                .map(|(&point, all_dims)| {
+                    // Larger platforms imply the ones they contain, so the smallest
+                    // ones suffice
+                    let smallest = all_dims
+                        .iter()
+                        .filter(|&dims| !all_dims.iter().any(|other| other < dims))
+                        .map(|&dims| self.vars.for_dims_at(point, dims).unwrap().pos_lit())
+                        .collect_vec();
+                    match *smallest.as_slice() {
+                        [lit] => lit,
+                        _ => {
+                            let any = self.instance.var_manager_mut().new_var().pos_lit();
+                            self.instance.add_lit_impl_clause(any, &smallest);
+                            any
+                        }
+                    }
+                })
+                .collect_vec();
+            if !lits.is_empty() {
+                add_at_least_two(&mut self.instance, &lits);
+            }
+        }
+        self.origins.resize(self.instance.n_clauses(), ClauseOrigin::FaultTolerance);
+        self.fault_tolerant = true;
+        self
+    }
+
+    /// Whether [`Self::with_fault_tolerance`] was applied.
+    pub fn is_fault_tolerant(&self) -> bool {
+        self.fault_tolerant
+    }
+
+    /// A hash of the base instance (without limits), which is the same for the
+    /// same terrain and platforms across runs and builds.
+    ///
+    /// This is FNV-1a over the variable count and the clauses, see [`Fnv1a`].
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = Fnv1a::new();
+        hasher.write(&self.instance.var_manager_ref().n_used().to_le_bytes());
+        for clause in self.instance.cnf().iter() {
+            for lit in clause.iter() {
+                hasher.write(&lit.to_ipasir().to_le_bytes());
+            }
+            // Clause separator, same as in DIMACS
+            hasher.write(&0i32.to_le_bytes());
+        }
+        hasher.finish()
+    }
+
     pub fn with_limits(&self, limits: &PlatformLimits) -> SatInstance {
+        self.with_limit_constraints(limits).0
+    }
+
+    /// The base instance without the requirement for all terrain to be
+    /// supported, and the literal that requires it for each terrain tile.
+    ///
+    /// Used to add the requirement per region, see
+    /// [`crate::solver::infeasibility`].
+    pub fn without_support_requirement(&self) -> (SatInstance, Vec<(Point, Lit)>) {
+        let required = self
+            .vars
+            .grid
+            .enumerate()
+            .filter_map(|(point, tile_vars)| Some((point, tile_vars.terrain?[0].pos_lit())))
+            .collect_vec();
+        let required_lits: HashSet<Lit> = required.iter().map(|&(_, lit)| lit).collect();
+
+        let mut instance = SatInstance::new_with_manager(self.instance.var_manager_ref().clone());
+        for clause in self.instance.cnf().iter() {
+            let is_requirement =
+                clause.len() == 1 && clause.iter().all(|lit| required_lits.contains(lit));
+            if !is_requirement {
+                instance.add_clause(clause.clone());
+            }
+        }
+        (instance, required)
+    }
+
+    /// Same as [`Self::with_limits`], but also returns the added constraints.
+    pub fn with_limit_constraints(
+        &self,
+        limits: &PlatformLimits,
+    ) -> (SatInstance, LimitConstraints) {
         let mut instance = self.instance.clone();
-        let mut weight_pb = limits.weight_limit.map(|limit| PbConstraint::new_ub([], limit));
+        let constraints = self.limit_constraints(limits, instance.var_manager_mut());
+        constraints.add_to(&mut instance);
+
+        // TODO: might need a Result?
+        (instance, constraints)
+    }
+
+    /// Builds the constraints for the given limits, without adding them to an
+    /// instance. Auxiliary variables are taken from `var_manager`.
+    ///
+    /// [`Self::with_limits`] adds these to a copy of the base instance.
+    pub fn limit_constraints(
+        &self,
+        limits: &PlatformLimits,
+        var_manager: &mut BasicVarManager,
+    ) -> LimitConstraints {
+        let mut constraints =
+            LimitConstraints { weight_limit: limits.weight_limit, ..Default::default() };
 
         // Iterate all platforms that have limits and/or weights defined
         // For each platform def, either take the single platform type var for each
         // tile, or, if there are multiple per tile, create a new var implied by
         // all of them. Add those to the card constraint if there's a limit
         // defined, ditto for weights.
-        for &platform_type in limits.card_limits.keys().chain(limits.weights.keys()).unique() {
+        for &platform_type in limits
+            .card_limits
+            .keys()
+            .chain(limits.weights.keys())
+            .unique()
+            .sorted_by_key(|def| (def.dims().width, def.dims().height))
+        {
             let lits: Vec<Lit> = if platform_type.rectangular() {
                 let mut limit_lits = vec![];
                 for tile_vars in self.vars.iter_by_points() {
-                    let limit_var = instance.new_var();
+                    let limit_var = var_manager.new_var();
                     limit_lits.push(limit_var.pos_lit());
                     for var in [platform_type.dims(), platform_type.dims().flipped()]
                         .iter()
                         .filter_map(|d| tile_vars.for_dims(*d))
                     {
-                        instance.add_lit_impl_lit(var.pos_lit(), limit_var.pos_lit());
+                        constraints.implications.push((var.pos_lit(), limit_var.pos_lit()));
                     }
                 }
                 limit_lits
@@ -647,24 +926,69 @@ impl Encoding {
             };
 
             if let Some(&limit) = limits.card_limits.get(&platform_type) {
-                // println!("Limiting {platform_type} platforms to n <= {limit}");
-                instance.add_card_constr(CardConstraint::new_ub(lits.iter().copied(), limit));
+                constraints.cards.push((lits.clone(), limit));
             }
 
-            if let Some(weight_pb) = weight_pb.as_mut()
-                && let Some(weight) = limits.weights.get(&platform_type).copied()
-            {
-                weight_pb.add(lits.iter().copied().zip(iter::repeat(weight)));
+            if let Some(weight) = limits.weights.get(&platform_type).copied() {
+                constraints.weighted.extend(lits.iter().copied().zip(iter::repeat(weight)));
             }
         }
 
-        if let Some(weight_pb) = weight_pb {
-            instance.add_pb_constr(weight_pb);
+        constraints
+    }
+}
+
+/// Constraints for a set of [`PlatformLimits`], see
+/// [`Encoding::limit_constraints`].
+#[derive(Clone, Debug, Default)]
+pub struct LimitConstraints {
+    /// Implications from platform vars to auxiliary per-tile limit vars
+    pub implications: Vec<(Lit, Lit)>,
+    /// Cardinality upper bounds
+    pub cards: Vec<(Vec<Lit>, usize)>,
+    /// Weighted literals for all platform types with a weight, i.e. the
+    /// objective to minimize
+    pub weighted: Vec<(Lit, isize)>,
+    /// Upper bound for the sum of `weighted`
+    pub weight_limit: Option<isize>,
+}
+
+impl LimitConstraints {
+    pub fn add_to(&self, instance: &mut SatInstance) {
+        for &(a, b) in self.implications.iter() {
+            instance.add_lit_impl_lit(a, b);
+        }
+        for (lits, limit) in self.cards.iter() {
+            instance.add_card_constr(CardConstraint::new_ub(lits.iter().copied(), *limit));
+        }
+        if let Some(weight_limit) = self.weight_limit {
+            instance
+                .add_pb_constr(PbConstraint::new_ub(self.weighted.iter().copied(), weight_limit));
         }
+    }
+}
 
-        // TODO: might need a Result?
-        instance
+/// Requires at least two of `lits` to be true, with a sequential counter.
+fn add_at_least_two(instance: &mut SatInstance, lits: &[Lit]) {
+    // `any[i]` -> one of `lits[..=i]` is true, `pair[i]` -> `lits[i]` and one
+    // before it are true, and one of the pairs is required
+    let mut pairs = vec![];
+    let mut prev_any: Option<Lit> = None;
+    for &lit in lits {
+        if let Some(prev_any) = prev_any {
+            let pair = instance.var_manager_mut().new_var().pos_lit();
+            instance.add_lit_impl_lit(pair, lit);
+            instance.add_lit_impl_lit(pair, prev_any);
+            pairs.push(pair);
+        }
+        let any = instance.var_manager_mut().new_var().pos_lit();
+        match prev_any {
+            Some(prev_any) => instance.add_lit_impl_clause(any, &[prev_any, lit]),
+            None => instance.add_lit_impl_lit(any, lit),
+        }
+        prev_any = Some(any);
     }
+    instance.add_clause(pairs.into_iter().collect());
 }
 
 pub fn assignment_total_weight(
@@ -690,3 +1014,105 @@ pub fn assignment_total_weight(
         })
         .sum()
 }
+
+#[cfg(test)]
+mod tests {
+    use rustsat::solvers::{Solve, SolverResult};
+    use rustsat_glucose::simp::Glucose;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::{platform_def, test_utils::filled_world, world::World};
+
+    fn solve_layout(
+        platform_defs: &[PlatformDef],
+        world: &World,
+        limits: &PlatformLimits,
+    ) -> Option<PlatformLayout> {
+        let encoding = Encoding::encode(platform_defs, world.grid());
+        let (cnf, _) = encoding.with_limits(limits).into_cnf();
+        let mut solver = Glucose::default();
+        solver.add_cnf(cnf).unwrap();
+        match solver.solve().unwrap() {
+            SolverResult::Sat => Some(PlatformLayout::from_assignment(
+                &solver.full_solution().unwrap(),
+                encoding.vars(),
+            )),
+            SolverResult::Unsat => None,
+            SolverResult::Interrupted => unreachable!(),
+        }
+    }
+
+    #[test_case(&[platform_def!(3, 3)])]
+    #[test_case(&[platform_def!(1, 2), platform_def!(3, 3)])]
+    #[test_case(&[platform_def!(1, 3), platform_def!(5, 5)])]
+    fn encode_without_1x1(platform_defs: &[PlatformDef]) {
+        let world = filled_world(9, 7);
+        let layout = solve_layout(platform_defs, &world, &PlatformLimits::default())
+            .expect("expected a solution without 1x1 platforms");
+
+        let validation = layout.validate(&world);
+        assert!(validation.is_valid(), "{validation:#?}");
+        assert!(layout.platform_count() > 0);
+        assert!(
+            layout.platforms().values().all(|plat| platform_defs.contains(&plat.def())),
+            "layout contains platforms outside of the given set: {layout:#?}"
+        );
+    }
+
+    #[test]
+    fn clause_origins_cover_all_clauses() {
+        let world = filled_world(6, 5);
+        let encoding = Encoding::encode(&crate::platform::PLATFORMS_DEFAULT, world.grid());
+        let limits = PlatformLimits::new_unweighted([(platform_def!(1, 3), 2)].into());
+
+        let base = encoding.stats(None);
+        assert_eq!(base.n_clauses(), encoding.with_limits(&Default::default()).n_clauses());
+        assert_eq!(base.clauses_by_origin[&ClauseOrigin::Limits], 0);
+        let optional = [ClauseOrigin::Limits, ClauseOrigin::FaultTolerance];
+        for origin in ClauseOrigin::ALL.into_iter().filter(|o| !optional.contains(o)) {
+            assert!(base.clauses_by_origin[&origin] > 0, "no clauses for {origin}");
+        }
+
+        let limited = encoding.stats(Some(&limits));
+        assert_eq!(limited.n_clauses(), encoding.with_limits(&limits).n_clauses());
+        assert!(limited.clauses_by_origin[&ClauseOrigin::Limits] > 0);
+        assert_eq!(limited.n_cards, 1);
+    }
+
+    #[test]
+    fn fault_tolerant_layouts_survive_any_single_loss() {
+        let world = filled_world(7, 6);
+        let encoding = Encoding::encode(&crate::platform::PLATFORMS_DEFAULT, world.grid())
+            .with_fault_tolerance();
+        assert!(encoding.stats(None).clauses_by_origin[&ClauseOrigin::FaultTolerance] > 0);
+
+        let (cnf, _) = encoding.with_limits(&PlatformLimits::default()).into_cnf();
+        let mut solver = Glucose::default();
+        solver.add_cnf(cnf).unwrap();
+        assert_eq!(solver.solve().unwrap(), SolverResult::Sat);
+        let layout =
+            PlatformLayout::from_assignment(&solver.full_solution().unwrap(), encoding.vars());
+
+        assert!(layout.validate(&world).is_valid());
+        let fault_tolerance = layout.validate_fault_tolerance(&world);
+        assert!(fault_tolerance.is_fault_tolerant(), "{fault_tolerance:#?}");
+    }
+
+    #[test]
+    fn fault_tolerance_needs_room_for_two_platforms() {
+        let world = filled_world(1, 1);
+        let encoding =
+            Encoding::encode(&[platform_def!(1, 1)], world.grid()).with_fault_tolerance();
+        let (cnf, _) = encoding.with_limits(&PlatformLimits::default()).into_cnf();
+        let mut solver = Glucose::default();
+        solver.add_cnf(cnf).unwrap();
+        assert_eq!(solver.solve().unwrap(), SolverResult::Unsat);
+    }
+
+    #[test]
+    fn encode_without_platforms_is_unsat() {
+        let world = filled_world(2, 2);
+        assert!(solve_layout(&[], &world, &PlatformLimits::default()).is_none());
+    }
+}
@@ -0,0 +1,206 @@
+//! Typed validation diagnostics.
+//!
+//! [`ValidationResult::diagnostics`] turns a validation into a list of
+//! [`Diagnostic`]s, each with the affected points or platforms and a suggested
+//! fix. They serialize to JSON (or any other serde format), so that the REPL,
+//! the GUI and external tools all report the same findings.
+
+use std::{
+    collections::HashSet,
+    fmt::{Display, Formatter},
+};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{TERRAIN_SUPPORT_DISTANCE, encoder::ValidationResult, math::Point, platform::Platform};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiagnosticKind {
+    UnsupportedTerrain,
+    OverlappingPlatform,
+    OutOfBoundsPlatform,
+    /// A platform that doesn't cover any terrain
+    IdlePlatform,
+}
+
+impl DiagnosticKind {
+    pub const fn description(self) -> &'static str {
+        match self {
+            DiagnosticKind::UnsupportedTerrain => "unsupported terrain",
+            DiagnosticKind::OverlappingPlatform => "overlapping platforms",
+            DiagnosticKind::OutOfBoundsPlatform => "out-of-bounds platforms",
+            DiagnosticKind::IdlePlatform => "idle platforms",
+        }
+    }
+}
+
+/// Errors make a layout invalid, warnings don't.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A single finding of a layout validation.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub severity: Severity,
+    /// Affected tiles
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub points: Vec<Point>,
+    /// Affected platforms
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub platforms: Vec<Platform>,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "; {suggestion}")?;
+        }
+        Ok(())
+    }
+}
+
+fn format_point(p: Point) -> String {
+    format!("({};{})", p.x, p.y)
+}
+
+fn format_platform(plat: &Platform) -> String {
+    format!(
+        "{}x{} platform at {}",
+        plat.dims().width,
+        plat.dims().height,
+        format_point(plat.point())
+    )
+}
+
+pub(super) fn from_validation(validation: &ValidationResult) -> Vec<Diagnostic> {
+    let max_distance = TERRAIN_SUPPORT_DISTANCE - 1;
+    let unsupported =
+        validation.unsupported_terrain.iter().sorted_by_key(|p| (p.y, p.x)).map(|&p| {
+            let (message, suggestion) = match validation.nearest_platform.get(&p) {
+                Some(distance) => (
+                    format!(
+                        "tile {} is {distance} tiles from the nearest platform",
+                        format_point(p)
+                    ),
+                    format!("support reaches {max_distance} tiles, place a platform closer to it"),
+                ),
+                None => (
+                    format!("tile {} isn't connected to any platform", format_point(p)),
+                    format!("place a platform within {max_distance} tiles of it"),
+                ),
+            };
+            Diagnostic {
+                kind: DiagnosticKind::UnsupportedTerrain,
+                severity: Severity::Error,
+                points: vec![p],
+                platforms: vec![],
+                message,
+                suggestion: Some(suggestion),
+            }
+        });
+
+    let by_platform =
+        |kind, severity, platforms: &HashSet<Platform>, message: &str, suggestion: &str| {
+            sorted_platforms(platforms)
+                .map(|plat| Diagnostic {
+                    kind,
+                    severity,
+                    points: vec![],
+                    platforms: vec![plat],
+                    message: format!("{} {message}", format_platform(&plat)),
+                    suggestion: Some(suggestion.to_owned()),
+                })
+                .collect_vec()
+        };
+    let overlapping = by_platform(
+        DiagnosticKind::OverlappingPlatform,
+        Severity::Error,
+        &validation.overlapping_platforms,
+        "overlaps another platform",
+        "move or remove one of them",
+    );
+    let out_of_bounds = by_platform(
+        DiagnosticKind::OutOfBoundsPlatform,
+        Severity::Error,
+        &validation.out_of_bounds_platforms,
+        "extends past the edge of the map",
+        "move it inside the map",
+    );
+    let idle = by_platform(
+        DiagnosticKind::IdlePlatform,
+        Severity::Warning,
+        &validation.idle_platforms,
+        "doesn't cover any terrain",
+        "remove it",
+    );
+
+    unsupported.chain(overlapping).chain(out_of_bounds).chain(idle).collect()
+}
+
+fn sorted_platforms(platforms: &HashSet<Platform>) -> impl Iterator<Item = Platform> {
+    platforms.iter().copied().sorted_by_key(|plat| (plat.point().y, plat.point().x))
+}
+
+#[cfg(test)]
+mod tests {
+    use assertables::assert_contains;
+
+    use super::*;
+    use crate::{
+        encoder::PlatformLayout,
+        math::{Dimensions, Grid},
+        platform_def,
+        world::{World, WorldGrid},
+    };
+
+    #[test]
+    fn reports_distance_to_the_nearest_platform() {
+        let world = World::new(WorldGrid(Grid::new_fill(Dimensions::new(8, 1), true)));
+        let layout = PlatformLayout::from_platforms([Platform::new(
+            Point::new(0, 0),
+            platform_def!(1, 1),
+            false,
+        )]);
+
+        let diagnostics = layout.validate(&world).diagnostics();
+        assert_eq!(diagnostics.len(), 4);
+        assert_eq!(diagnostics[0].points, [Point::new(4, 0)]);
+        assert_eq!(diagnostics[0].message, "tile (4;0) is 4 tiles from the nearest platform");
+        assert!(diagnostics.iter().all(|d| d.kind == DiagnosticKind::UnsupportedTerrain));
+
+        let json = serde_json::to_string(&diagnostics).unwrap();
+        assert_contains!(json, r#""kind":"unsupported-terrain""#);
+        let parsed: Vec<Diagnostic> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, diagnostics);
+    }
+
+    #[test]
+    fn idle_platforms_are_warnings() {
+        let mut terrain = Grid::new_fill(Dimensions::new(4, 4), false);
+        terrain.set(Point::new(0, 0), true).unwrap();
+        let world = World::new(WorldGrid(terrain));
+        let layout = PlatformLayout::from_platforms([
+            Platform::new(Point::new(0, 0), platform_def!(1, 1), false),
+            Platform::new(Point::new(3, 3), platform_def!(1, 1), false),
+        ]);
+
+        let validation = layout.validate(&world);
+        assert!(validation.is_valid());
+        let diagnostics = validation.diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].kind, DiagnosticKind::IdlePlatform);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(validation.iter_error_printouts().count(), 0);
+    }
+}
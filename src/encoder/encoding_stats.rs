@@ -0,0 +1,131 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+    io,
+    io::Write,
+};
+
+use itertools::Itertools;
+use rustsat::instances::ManageVars;
+
+use crate::encoder::{Encoding, PlatformLimits};
+
+/// The part of the encoding a clause was generated by.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum ClauseOrigin {
+    /// Implications from larger to smaller platforms (including the virtual
+    /// cell).
+    DagImplication,
+    /// Clauses forbidding two incomparable platforms in one tile.
+    IncomparablePair,
+    /// Closest terrain support layer -> platforms that cover the tile.
+    PlatformTerrain,
+    /// Support propagation between terrain layers, and the required top layer.
+    SupportLayer,
+    Overlap,
+    OutOfBounds,
+    /// At least two platforms in reach of each tile, see
+    /// [`Encoding::with_fault_tolerance`].
+    FaultTolerance,
+    /// Auxiliary clauses added by [`Encoding::with_limits`].
+    Limits,
+}
+
+impl ClauseOrigin {
+    pub const ALL: [ClauseOrigin; 8] = [
+        ClauseOrigin::DagImplication,
+        ClauseOrigin::IncomparablePair,
+        ClauseOrigin::PlatformTerrain,
+        ClauseOrigin::SupportLayer,
+        ClauseOrigin::Overlap,
+        ClauseOrigin::OutOfBounds,
+        ClauseOrigin::FaultTolerance,
+        ClauseOrigin::Limits,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            ClauseOrigin::DagImplication => "dag implication",
+            ClauseOrigin::IncomparablePair => "incomparable pair",
+            ClauseOrigin::PlatformTerrain => "platform-terrain",
+            ClauseOrigin::SupportLayer => "support layer",
+            ClauseOrigin::Overlap => "overlap",
+            ClauseOrigin::OutOfBounds => "out-of-bounds",
+            ClauseOrigin::FaultTolerance => "fault tolerance",
+            ClauseOrigin::Limits => "limits",
+        }
+    }
+}
+
+impl Display for ClauseOrigin {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Size of an encoded instance, with clauses counted by their origin.
+#[derive(Clone, Debug, Default)]
+pub struct EncodingStats {
+    pub n_vars: u32,
+    pub clauses_by_origin: BTreeMap<ClauseOrigin, usize>,
+    /// Cardinality constraints (not yet encoded as clauses)
+    pub n_cards: usize,
+    /// Pseudo-boolean constraints (not yet encoded as clauses)
+    pub n_pbs: usize,
+}
+
+impl EncodingStats {
+    pub fn n_clauses(&self) -> usize {
+        self.clauses_by_origin.values().sum()
+    }
+}
+
+impl Encoding {
+    /// The origin of each clause in the base instance, by index.
+    pub fn clause_origins(&self) -> &[ClauseOrigin] {
+        &self.origins
+    }
+
+    /// Counts variables and clauses, optionally including the given limits.
+    pub fn stats(&self, limits: Option<&PlatformLimits>) -> EncodingStats {
+        let instance = limits.map_or_else(|| self.instance.clone(), |l| self.with_limits(l));
+
+        let mut clauses_by_origin: BTreeMap<_, _> =
+            ClauseOrigin::ALL.into_iter().map(|origin| (origin, 0)).collect();
+        for &origin in self.origins.iter() {
+            *clauses_by_origin.get_mut(&origin).unwrap() += 1;
+        }
+        // Everything past the base instance comes from the limits
+        clauses_by_origin.insert(ClauseOrigin::Limits, instance.n_clauses() - self.origins.len());
+
+        EncodingStats {
+            n_vars: instance.var_manager_ref().n_used(),
+            clauses_by_origin,
+            n_cards: instance.n_cards(),
+            n_pbs: instance.n_pbs(),
+        }
+    }
+
+    /// Writes every clause on its own line, tagged with its origin and using
+    /// [readable literal names][`crate::encoder::EncodingVars::lit_readable_name`].
+    ///
+    /// Auxiliary variables without a readable name are written in their debug
+    /// form. Cardinality and PB constraints are not included.
+    pub fn write_clause_dump(
+        &self,
+        writer: &mut impl Write,
+        limits: Option<&PlatformLimits>,
+    ) -> io::Result<()> {
+        let instance = limits.map_or_else(|| self.instance.clone(), |l| self.with_limits(l));
+
+        for (i, clause) in instance.cnf().iter().enumerate() {
+            let origin = self.origins.get(i).copied().unwrap_or(ClauseOrigin::Limits);
+            let lits = clause
+                .iter()
+                .map(|&lit| self.vars.lit_readable_name(lit).unwrap_or(format!("{lit:?}")))
+                .join(" | ");
+            writeln!(writer, "[{origin}] {lits}")?;
+        }
+        Ok(())
+    }
+}
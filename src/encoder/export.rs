@@ -0,0 +1,314 @@
+//! Writing encoded instances in standard formats for external solvers, and
+//! reading their solutions back.
+//!
+//! All formats number variables the same way (DIMACS/IPASIR numbering, i.e.
+//! starting at 1), and [`Encoding::write_var_map`] maps them back to readable
+//! names. A model produced by any solver for the written instance can then be
+//! read with [`parse_solver_output`], and turned into a layout with
+//! [`PlatformLayout::from_assignment`][crate::encoder::PlatformLayout::from_assignment].
+//!
+//! Encoding the same terrain again gives the same numbering, so a model can be
+//! imported later on, as long as the encoding's fingerprint still matches the
+//! one recorded in the variable map (see [`read_var_map_fingerprint`]).
+
+use std::{
+    error::Error,
+    fmt::{Display, Formatter},
+    io,
+    io::{BufRead, Write},
+};
+
+use itertools::Itertools;
+use rustsat::{
+    instances::{Cnf, ManageVars},
+    types::{Assignment, Clause, Lit},
+};
+
+use crate::encoder::{Encoding, EncodingVars, PlatformLimits};
+
+/// Writes a CNF in the DIMACS format.
+pub fn write_dimacs(writer: &mut impl Write, cnf: &Cnf, n_vars: u32) -> io::Result<()> {
+    writeln!(writer, "p cnf {n_vars} {}", cnf.len())?;
+    for clause in cnf.iter() {
+        writeln!(writer, "{} 0", clause_ipasir(clause))?;
+    }
+    Ok(())
+}
+
+fn clause_ipasir(clause: &Clause) -> String {
+    clause.iter().map(|lit| lit.to_ipasir()).join(" ")
+}
+
+fn opb_lit(lit: Lit) -> String {
+    let var = lit.var().idx() + 1;
+    if lit.is_neg() { format!("~x{var}") } else { format!("x{var}") }
+}
+
+impl Encoding {
+    /// Writes the instance with limits as a DIMACS CNF. Cardinality and PB
+    /// constraints are encoded into clauses, exactly like for the solver.
+    pub fn write_dimacs(&self, writer: &mut impl Write, limits: &PlatformLimits) -> io::Result<()> {
+        let (cnf, var_manager) = self.with_limits(limits).into_cnf();
+        write_dimacs(writer, &cnf, var_manager.n_used())
+    }
+
+    /// Writes the instance with limits as a WCNF, with the platform weights as
+    /// soft clauses. Returns `Ok(false)` without writing anything if the limits
+    /// define no weights, i.e. there is no objective.
+    ///
+    /// Uses the current (2022+) MaxSAT Evaluation format without a `p` line.
+    pub fn write_wcnf(&self, writer: &mut impl Write, limits: &PlatformLimits) -> io::Result<bool> {
+        let (instance, constraints) = self.with_limit_constraints(limits);
+        let objective = constraints.weighted;
+        if objective.is_empty() {
+            return Ok(false);
+        }
+
+        let (cnf, _) = instance.into_cnf();
+        for clause in cnf.iter() {
+            writeln!(writer, "h {} 0", clause_ipasir(clause))?;
+        }
+        // Minimizing sum(w * l) = falsifying as few weighted [~l] as possible
+        for (lit, weight) in objective {
+            match weight {
+                0 => {}
+                w if w > 0 => writeln!(writer, "{w} {} 0", (!lit).to_ipasir())?,
+                w => writeln!(writer, "{} {} 0", -w, lit.to_ipasir())?,
+            }
+        }
+        Ok(true)
+    }
+
+    /// Writes the instance with limits in the OPB format. Unlike the CNF
+    /// formats, cardinality and PB constraints are kept as they are, and the
+    /// platform weights (if any) are written as the objective.
+    pub fn write_opb(&self, writer: &mut impl Write, limits: &PlatformLimits) -> io::Result<()> {
+        let mut instance = self.instance.clone();
+        let constraints = self.limit_constraints(limits, instance.var_manager_mut());
+        let n_vars = instance.var_manager_ref().n_used();
+
+        // Every constraint is written as `sum >= bound`
+        let mut lines = vec![];
+        for clause in instance.cnf().iter() {
+            lines.push(format!(
+                "{} >= 1 ;",
+                clause.iter().map(|&l| format!("+1 {}", opb_lit(l))).join(" ")
+            ));
+        }
+        for &(a, b) in constraints.implications.iter() {
+            lines.push(format!("+1 {} +1 {} >= 1 ;", opb_lit(!a), opb_lit(b)));
+        }
+        for (lits, limit) in constraints.cards.iter() {
+            let sum = lits.iter().map(|&l| format!("-1 {}", opb_lit(l))).join(" ");
+            lines.push(format!("{sum} >= -{limit} ;"));
+        }
+        if let Some(weight_limit) = constraints.weight_limit {
+            let sum = constraints
+                .weighted
+                .iter()
+                .map(|&(l, w)| format!("{:+} {}", -w, opb_lit(l)))
+                .join(" ");
+            lines.push(format!("{sum} >= {} ;", -weight_limit));
+        }
+
+        writeln!(writer, "* #variable= {n_vars} #constraint= {}", lines.len())?;
+        if !constraints.weighted.is_empty() {
+            let objective = constraints
+                .weighted
+                .iter()
+                .map(|&(l, w)| format!("{w:+} {}", opb_lit(l)))
+                .join(" ");
+            writeln!(writer, "min: {objective} ;")?;
+        }
+        for line in lines {
+            writeln!(writer, "{line}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Encoding {
+    /// Writes a `c fingerprint <hex>` line with [`Self::fingerprint`], then the
+    /// variables as in [`EncodingVars::write_var_map`].
+    pub fn write_var_map(&self, writer: &mut impl Write) -> io::Result<()> {
+        writeln!(writer, "c {FINGERPRINT_KEY} {:016x}", self.fingerprint())?;
+        self.vars().write_var_map(writer)
+    }
+}
+
+const FINGERPRINT_KEY: &str = "fingerprint";
+
+/// Reads the fingerprint written by [`Encoding::write_var_map`]. `None` if
+/// there is none, e.g. in variable maps from older versions.
+pub fn read_var_map_fingerprint(reader: impl BufRead) -> io::Result<Option<u64>> {
+    for line in reader.lines() {
+        let line = line?;
+        let mut tokens = line.split_whitespace();
+        if tokens.next() != Some("c") {
+            // Comments only come before the variables
+            break;
+        }
+        if tokens.next() == Some(FINGERPRINT_KEY) {
+            return Ok(tokens.next().and_then(|hex| u64::from_str_radix(hex, 16).ok()));
+        }
+    }
+    Ok(None)
+}
+
+impl EncodingVars {
+    /// Writes one `<var> <name>` line per encoded variable, using DIMACS
+    /// numbering and [`Self::lit_readable_name`].
+    pub fn write_var_map(&self, writer: &mut impl Write) -> io::Result<()> {
+        for &var in self.var_map.keys().sorted() {
+            let name = self.lit_readable_name(var.pos_lit()).unwrap_or_default();
+            writeln!(writer, "{} {name}", var.pos_lit().to_ipasir())?;
+        }
+        Ok(())
+    }
+}
+
+/// The result reported by an external solver.
+#[derive(Clone, Debug)]
+pub enum SolverOutput {
+    Sat(Assignment),
+    Unsat,
+    Unknown,
+}
+
+#[derive(Debug)]
+pub enum SolverOutputError {
+    Io(io::Error),
+    /// A `v` line contained something other than a DIMACS literal
+    InvalidLiteral {
+        line: usize,
+        token: String,
+    },
+    /// `s SATISFIABLE` was reported, but there was no model
+    MissingModel,
+}
+
+impl Display for SolverOutputError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolverOutputError::Io(err) => write!(f, "failed to read solver output: {err}"),
+            SolverOutputError::InvalidLiteral { line, token } => {
+                write!(f, "invalid literal `{token}` on line {line}")
+            }
+            SolverOutputError::MissingModel => {
+                write!(f, "solver reported SATISFIABLE, but returned no model")
+            }
+        }
+    }
+}
+
+impl Error for SolverOutputError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SolverOutputError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SolverOutputError {
+    fn from(err: io::Error) -> Self {
+        SolverOutputError::Io(err)
+    }
+}
+
+/// Parses standard SAT competition solver output: an `s` status line, and `v`
+/// lines with the model. Other lines (comments etc.) are ignored.
+///
+/// A file with only `v` lines and no status line (e.g. a bare model) is treated
+/// as satisfiable.
+pub fn parse_solver_output(reader: impl BufRead) -> Result<SolverOutput, SolverOutputError> {
+    let mut status = None;
+    let mut assignment = Assignment::default();
+    let mut has_model = false;
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("s") => status = tokens.next().map(str::to_owned),
+            Some("v") => {
+                has_model = true;
+                for token in tokens {
+                    let invalid = || SolverOutputError::InvalidLiteral {
+                        line: i + 1,
+                        token: token.to_owned(),
+                    };
+                    let value: i32 = token.parse().map_err(|_| invalid())?;
+                    if value == 0 {
+                        break;
+                    }
+                    assignment.assign_lit(Lit::from_ipasir(value).map_err(|_| invalid())?);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    match status.as_deref() {
+        Some("SATISFIABLE") | None if has_model => Ok(SolverOutput::Sat(assignment)),
+        Some("SATISFIABLE") => Err(SolverOutputError::MissingModel),
+        Some("UNSATISFIABLE") => Ok(SolverOutput::Unsat),
+        _ => Ok(SolverOutput::Unknown),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustsat::types::TernaryVal;
+
+    use super::*;
+    use crate::{
+        math::{Dimensions, Grid},
+        platform::PLATFORMS_DEFAULT,
+        world::WorldGrid,
+    };
+
+    #[test]
+    fn var_map_records_fingerprint() {
+        let terrain = WorldGrid(Grid::new_fill(Dimensions::new(4, 3), true));
+        let encoding = Encoding::encode(&PLATFORMS_DEFAULT, &terrain);
+        let mut var_map = vec![];
+        encoding.write_var_map(&mut var_map).unwrap();
+
+        let fingerprint = read_var_map_fingerprint(var_map.as_slice()).unwrap();
+        assert_eq!(fingerprint, Some(encoding.fingerprint()));
+        assert_eq!(read_var_map_fingerprint("1 x1\n".as_bytes()).unwrap(), None);
+    }
+
+    #[test]
+    fn parse_model() {
+        let output = "c comment\ns SATISFIABLE\nv 1 -2 3\nv -4 0\n";
+        let SolverOutput::Sat(assignment) = parse_solver_output(output.as_bytes()).unwrap() else {
+            panic!("expected a model");
+        };
+
+        let value = |ipasir| assignment.lit_value(Lit::from_ipasir(ipasir).unwrap());
+        assert_eq!(value(1), TernaryVal::True);
+        assert_eq!(value(2), TernaryVal::False);
+        assert_eq!(value(3), TernaryVal::True);
+        assert_eq!(value(4), TernaryVal::False);
+    }
+
+    #[test]
+    fn parse_unsat_and_unknown() {
+        assert!(matches!(
+            parse_solver_output("s UNSATISFIABLE\n".as_bytes()).unwrap(),
+            SolverOutput::Unsat
+        ));
+        assert!(matches!(
+            parse_solver_output("s UNKNOWN\n".as_bytes()).unwrap(),
+            SolverOutput::Unknown
+        ));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(parse_solver_output("s SATISFIABLE\n".as_bytes()).is_err());
+        assert!(parse_solver_output("s SATISFIABLE\nv 1 x 0\n".as_bytes()).is_err());
+    }
+}
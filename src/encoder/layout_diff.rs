@@ -0,0 +1,217 @@
+//! Differences between two layouts, e.g. before and after changing the terrain
+//! or the limits.
+//!
+//! Platforms are matched up in three passes: identical platforms are
+//! unchanged, a different platform on the same tile is a resize, and a platform
+//! of the same size nearby is a move (closest pairs first). Whatever's left was
+//! added or removed.
+
+use std::fmt::{Display, Formatter};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{encoder::PlatformLayout, platform::Platform};
+
+/// How far (in tiles, between the top-left corners) a platform may be from
+/// its old position to count as moved rather than removed and added.
+pub const MOVE_DISTANCE: usize = 4;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PlatformChange {
+    Added(Platform),
+    Removed(Platform),
+    /// Same size and orientation, different position
+    Moved {
+        from: Platform,
+        to: Platform,
+    },
+    /// Same position, different size or orientation
+    Resized {
+        from: Platform,
+        to: Platform,
+    },
+}
+
+impl PlatformChange {
+    /// The platform in the old layout, if any.
+    pub fn before(&self) -> Option<Platform> {
+        match *self {
+            PlatformChange::Added(_) => None,
+            PlatformChange::Removed(from)
+            | PlatformChange::Moved { from, .. }
+            | PlatformChange::Resized { from, .. } => Some(from),
+        }
+    }
+
+    /// The platform in the new layout, if any.
+    pub fn after(&self) -> Option<Platform> {
+        match *self {
+            PlatformChange::Removed(_) => None,
+            PlatformChange::Added(to)
+            | PlatformChange::Moved { to, .. }
+            | PlatformChange::Resized { to, .. } => Some(to),
+        }
+    }
+}
+
+/// All differences between an old and a new layout, see [`diff_layouts`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LayoutDiff {
+    /// Platforms in both layouts
+    pub unchanged: Vec<Platform>,
+    /// Sorted by position in the new layout, or the old one for removals
+    pub changes: Vec<PlatformChange>,
+}
+
+impl LayoutDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn added(&self) -> usize {
+        self.count(|change| matches!(change, PlatformChange::Added(_)))
+    }
+
+    pub fn removed(&self) -> usize {
+        self.count(|change| matches!(change, PlatformChange::Removed(_)))
+    }
+
+    pub fn moved(&self) -> usize {
+        self.count(|change| matches!(change, PlatformChange::Moved { .. }))
+    }
+
+    pub fn resized(&self) -> usize {
+        self.count(|change| matches!(change, PlatformChange::Resized { .. }))
+    }
+
+    fn count(&self, predicate: impl Fn(&PlatformChange) -> bool) -> usize {
+        self.changes.iter().filter(|change| predicate(change)).count()
+    }
+}
+
+impl Display for LayoutDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} added, {} removed, {} moved, {} resized, {} unchanged",
+            self.added(),
+            self.removed(),
+            self.moved(),
+            self.resized(),
+            self.unchanged.len()
+        )
+    }
+}
+
+/// Finds the platforms that were added, removed, moved or resized from `old`
+/// to `new`.
+pub fn diff_layouts(old: &PlatformLayout, new: &PlatformLayout) -> LayoutDiff {
+    let mut removed = old.platforms().values().copied().collect_vec();
+    let mut added = new.platforms().values().copied().collect_vec();
+    let mut diff = LayoutDiff::default();
+
+    removed.retain(|plat| {
+        let unchanged = new.get_platform(plat.point()) == Some(*plat);
+        if unchanged {
+            diff.unchanged.push(*plat);
+        }
+        !unchanged
+    });
+    added.retain(|plat| old.get_platform(plat.point()) != Some(*plat));
+
+    // Resizes
+    removed.retain(|&from| {
+        let Some(i) = added.iter().position(|to| to.point() == from.point()) else {
+            return true;
+        };
+        diff.changes.push(PlatformChange::Resized { from, to: added.swap_remove(i) });
+        false
+    });
+
+    // Moves, closest first
+    let candidates = removed
+        .iter()
+        .cartesian_product(added.iter())
+        .filter(|(from, to)| from.dims() == to.dims())
+        .map(|(&from, &to)| (from.point().manhattan_to(to.point()), from, to))
+        .filter(|&(distance, _, _)| distance <= MOVE_DISTANCE)
+        .sorted_by_key(|&(distance, from, to)| {
+            (distance, from.point().y, from.point().x, to.point().y, to.point().x)
+        })
+        .collect_vec();
+    for (_, from, to) in candidates {
+        if let (Some(i), Some(j)) =
+            (removed.iter().position(|&p| p == from), added.iter().position(|&p| p == to))
+        {
+            removed.swap_remove(i);
+            added.swap_remove(j);
+            diff.changes.push(PlatformChange::Moved { from, to });
+        }
+    }
+
+    diff.changes.extend(added.into_iter().map(PlatformChange::Added));
+    diff.changes.extend(removed.into_iter().map(PlatformChange::Removed));
+    diff.changes.sort_by_key(|change| {
+        let plat = change.after().or(change.before()).unwrap();
+        (plat.point().y, plat.point().x)
+    });
+    diff.unchanged.sort_by_key(|plat| (plat.point().y, plat.point().x));
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        math::{Dimensions, Point},
+        platform::PlatformDef,
+        platform_def,
+    };
+
+    fn plat(x: isize, y: isize, def: PlatformDef) -> Platform {
+        Platform::new(Point::new(x, y), def, false)
+    }
+
+    #[test]
+    fn classifies_changes() {
+        let old = PlatformLayout::from_platforms([
+            plat(0, 0, platform_def!(3, 3)),
+            plat(5, 0, platform_def!(3, 3)),
+            plat(10, 0, platform_def!(1, 1)),
+            plat(20, 0, platform_def!(5, 5)),
+        ]);
+        let new = PlatformLayout::from_platforms([
+            plat(0, 0, platform_def!(3, 3)),
+            plat(6, 1, platform_def!(3, 3)),
+            plat(10, 0, platform_def!(1, 3)),
+            plat(30, 0, platform_def!(1, 1)),
+        ]);
+
+        let diff = diff_layouts(&old, &new);
+        assert_eq!(diff.unchanged, [plat(0, 0, platform_def!(3, 3))]);
+        assert_eq!(
+            diff.changes,
+            [
+                PlatformChange::Moved {
+                    from: plat(5, 0, platform_def!(3, 3)),
+                    to: plat(6, 1, platform_def!(3, 3))
+                },
+                PlatformChange::Resized {
+                    from: plat(10, 0, platform_def!(1, 1)),
+                    to: plat(10, 0, platform_def!(1, 3))
+                },
+                PlatformChange::Removed(plat(20, 0, platform_def!(5, 5))),
+                PlatformChange::Added(plat(30, 0, platform_def!(1, 1))),
+            ]
+        );
+        assert_eq!(diff.to_string(), "1 added, 1 removed, 1 moved, 1 resized, 1 unchanged");
+    }
+
+    #[test]
+    fn same_layout_has_no_changes() {
+        let layout = PlatformLayout::from_platforms([plat(2, 3, platform_def!(1, 4))]);
+        assert!(diff_layouts(&layout, &layout).is_empty());
+    }
+}
@@ -0,0 +1,134 @@
+//! Saving layouts to files, and loading them back.
+//!
+//! A saved layout records the dimensions and the [`World::fingerprint`] of the
+//! world it was made for, so loading it for a different world can be caught.
+//! Files ending in `.json` are JSON, anything else is TOML.
+
+use std::{fs, path::Path};
+
+use anyhow::{Context, bail};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    encoder::{PlatformLayout, ValidationResult},
+    math::Dimensions,
+    world::World,
+};
+
+/// Version of the file format, bumped on incompatible changes.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// A layout, along with what it was made for.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LayoutFile {
+    pub format_version: u32,
+    /// [`World::fingerprint`] of the world, as hex
+    pub project_hash: String,
+    /// Dimensions of the world
+    pub dims: Dimensions,
+    pub layout: PlatformLayout,
+}
+
+impl LayoutFile {
+    pub fn new(layout: PlatformLayout, world: &World) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            project_hash: format!("{:016x}", world.fingerprint()),
+            dims: world.grid().dims(),
+            layout,
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let contents = if is_json(path) {
+            serde_json::to_string_pretty(self).context("Failed to serialize the layout")?
+        } else {
+            toml::to_string_pretty(self).context("Failed to serialize the layout")?
+        };
+        fs::write(path, contents).context("Failed to write the layout file")
+    }
+
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let contents = fs::read_to_string(path).context("Failed to read the layout file")?;
+        let file: LayoutFile = if is_json(path) {
+            serde_json::from_str(&contents).context("Failed to parse the layout file")?
+        } else {
+            toml::from_str(&contents).context("Failed to parse the layout file")?
+        };
+        if file.format_version > FORMAT_VERSION {
+            bail!("The layout file has a newer format version ({})", file.format_version);
+        }
+        Ok(file)
+    }
+
+    /// Checks that the layout fits `world`, and validates it there.
+    ///
+    /// Different dimensions are an error. Other terrain with the same
+    /// dimensions is only warned about, as the layout may still be useful.
+    pub fn check(&self, world: &World) -> anyhow::Result<ValidationResult> {
+        if self.dims != world.grid().dims() {
+            bail!(
+                "The layout was made for a {}x{} world, but this one is {}x{}",
+                self.dims.width,
+                self.dims.height,
+                world.grid().dims().width,
+                world.grid().dims().height
+            );
+        }
+        if self.project_hash != format!("{:016x}", world.fingerprint()) {
+            warn!("The layout was made for different terrain");
+        }
+        Ok(self.layout.validate(world))
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+    use crate::{
+        math::{Grid, Point},
+        platform::Platform,
+        platform_def,
+        world::WorldGrid,
+    };
+
+    fn world() -> World {
+        World::new(WorldGrid(Grid::new_fill(Dimensions::new(5, 3), true)))
+    }
+
+    fn layout() -> PlatformLayout {
+        PlatformLayout::from_platforms([
+            Platform::new(Point::new(0, 0), platform_def!(3, 3), false),
+            Platform::new(Point::new(3, 0), platform_def!(1, 2), true),
+        ])
+    }
+
+    #[test_case("layout.toml"; "toml")]
+    #[test_case("layout.json"; "json")]
+    fn round_trips(name: &str) {
+        let dir = std::env::temp_dir().join(format!("layout-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+
+        LayoutFile::new(layout(), &world()).save(&path).unwrap();
+        let loaded = LayoutFile::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.layout.platforms(), layout().platforms());
+        assert!(loaded.check(&world()).unwrap().is_valid());
+    }
+
+    #[test]
+    fn rejects_other_dimensions() {
+        let file = LayoutFile::new(layout(), &world());
+        let other = World::new(WorldGrid(Grid::new_fill(Dimensions::new(4, 4), true)));
+        assert!(file.check(&other).is_err());
+    }
+}
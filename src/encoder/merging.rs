@@ -0,0 +1,205 @@
+//! Merging clusters of small platforms into larger ones.
+//!
+//! Solutions often contain adjacent small platforms that fit inside the area of
+//! a single larger one. A larger platform supports everything the smaller ones
+//! did, so replacing them keeps the layout valid, as long as it stays in bounds
+//! and doesn't overlap any other platform. Merges are applied greedily, the one
+//! saving the most first, until none is left that saves anything.
+
+use std::collections::{HashMap, HashSet};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    encoder::{PlatformLayout, ValidationResult},
+    math::Grid,
+    platform::{Platform, PlatformDef},
+    world::World,
+};
+
+/// A group of platforms replaced by a larger one.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Merge {
+    /// In reading order
+    pub replaced: Vec<Platform>,
+    pub platform: Platform,
+}
+
+/// Replaces groups of platforms in `layout` with single larger platforms from
+/// `platform_defs`, and returns the merges in the order they were made.
+///
+/// Without `weights`, merges save platforms. With weights, a platform costs the
+/// weights of all definitions that fit inside it (as in
+/// [`PlatformLayout::total_weight`]), and merges lower the total cost.
+pub fn merge_platforms(
+    layout: &mut PlatformLayout,
+    world: &World,
+    platform_defs: &[PlatformDef],
+    weights: Option<&HashMap<PlatformDef, isize>>,
+) -> Vec<Merge> {
+    let cost = |plats: &[Platform]| match weights {
+        Some(weights) => {
+            PlatformLayout::from_platforms(plats.iter().copied()).total_weight(weights)
+        }
+        None => plats.len() as isize,
+    };
+    let orientations = platform_defs
+        .iter()
+        .flat_map(|&def| {
+            let square = def.dims().width == def.dims().height;
+            [false, true]
+                .into_iter()
+                .filter(move |&rotated| !(square && rotated))
+                .map(move |rotated| (def, rotated))
+        })
+        .collect_vec();
+
+    let mut merges = vec![];
+    loop {
+        let before = layout.validate(world);
+        let occupied_by = &occupancy(layout, world);
+
+        // The merge saving the most, then the largest, then in reading order
+        let best = orientations
+            .iter()
+            .flat_map(|&(def, rotated)| {
+                world.grid().dims().iter_within().filter_map(move |p| {
+                    let platform = Platform::new(p, def, rotated);
+                    let replaced = replaceable(occupied_by, platform)?;
+                    Some(Merge { replaced, platform })
+                })
+            })
+            .map(|merge| (cost(&merge.replaced) - cost(&[merge.platform]), merge))
+            .filter(|&(saving, _)| saving > 0)
+            .sorted_by_key(|(saving, merge)| {
+                let dims = merge.platform.dims();
+                let p = merge.platform.point();
+                (std::cmp::Reverse((*saving, dims.width * dims.height)), p.y, p.x)
+            })
+            .map(|(_, merge)| merge)
+            .find(|merge| {
+                let merged = apply(layout, merge);
+                no_worse(&before, &merged.validate(world))
+            });
+
+        let Some(merge) = best else {
+            break;
+        };
+        *layout = apply(layout, &merge);
+        merges.push(merge);
+    }
+    merges
+}
+
+/// The platform covering each tile of the world.
+fn occupancy(layout: &PlatformLayout, world: &World) -> Grid<Option<Platform>> {
+    let mut occupied_by = Grid::new_fill(world.grid().dims(), None);
+    for plat in layout.platforms().values() {
+        for offset in plat.dims().iter_within() {
+            _ = occupied_by.set(plat.point() + offset, Some(*plat));
+        }
+    }
+    occupied_by
+}
+
+/// The platforms within the area of `platform`, if it fits in the world and no
+/// other platform sticks into it.
+fn replaceable(occupied_by: &Grid<Option<Platform>>, platform: Platform) -> Option<Vec<Platform>> {
+    let (near, far) = platform.area_corners()?;
+    if !occupied_by.dims().contains(far) {
+        return None;
+    }
+
+    let mut inside = HashSet::new();
+    for offset in platform.dims().iter_within() {
+        if let Some(plat) = occupied_by.get(near + offset)? {
+            let (plat_near, plat_far) = plat.area_corners()?;
+            let fits = plat_near.x >= near.x
+                && plat_near.y >= near.y
+                && plat_far.x <= far.x
+                && plat_far.y <= far.y;
+            if !fits {
+                return None;
+            }
+            inside.insert(*plat);
+        }
+    }
+    (!inside.is_empty()).then(|| {
+        inside.into_iter().sorted_by_key(|plat| (plat.point().y, plat.point().x)).collect()
+    })
+}
+
+fn apply(layout: &PlatformLayout, merge: &Merge) -> PlatformLayout {
+    layout
+        .without_platforms(merge.replaced.iter().map(Platform::point))
+        .with_platforms([merge.platform])
+}
+
+/// Whether a merge didn't add any validation errors.
+fn no_worse(before: &ValidationResult, after: &ValidationResult) -> bool {
+    after.unsupported_terrain.is_subset(&before.unsupported_terrain)
+        && after.overlapping_platforms.len() <= before.overlapping_platforms.len()
+        && after.out_of_bounds_platforms.len() <= before.out_of_bounds_platforms.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+    use crate::{math::Dimensions, platform::PLATFORMS_DEFAULT, platform_def, world::WorldGrid};
+
+    fn filled(width: usize, height: usize) -> World {
+        World::new(WorldGrid(Grid::new_fill(Dimensions::new(width, height), true)))
+    }
+
+    #[test]
+    fn merges_a_block_of_small_platforms() {
+        let world = filled(3, 3);
+        let mut layout = PlatformLayout::from_platforms(
+            Dimensions::new(3, 3)
+                .iter_within()
+                .map(|p| Platform::new(p, platform_def!(1, 1), false)),
+        );
+
+        let merges = merge_platforms(&mut layout, &world, &PLATFORMS_DEFAULT, None);
+        assert_eq!(merges.len(), 1);
+        assert_eq!(merges[0].replaced.len(), 9);
+        assert_eq!(
+            layout.platforms().values().collect_vec(),
+            [&Platform::new(Point::new(0, 0), platform_def!(3, 3), false)]
+        );
+        assert!(layout.validate(&world).is_valid());
+    }
+
+    #[test_case(5, 1, true; "cheaper")]
+    #[test_case(1, 5, false; "more expensive")]
+    fn merges_by_weight(weight_1x1: isize, weight_1x2: isize, merged: bool) {
+        let world = filled(1, 2);
+        let mut layout = PlatformLayout::from_platforms([
+            Platform::new(Point::new(0, 0), platform_def!(1, 1), false),
+            Platform::new(Point::new(0, 1), platform_def!(1, 1), false),
+        ]);
+        let weights: HashMap<_, _> =
+            [(platform_def!(1, 1), weight_1x1), (platform_def!(1, 2), weight_1x2)].into();
+
+        let merges = merge_platforms(&mut layout, &world, &PLATFORMS_DEFAULT, Some(&weights));
+        assert_eq!(!merges.is_empty(), merged);
+        assert_eq!(layout.platform_count(), if merged { 1 } else { 2 });
+    }
+
+    #[test]
+    fn keeps_platforms_sticking_out() {
+        // The 3x1 platform sticks out of the 3x3 area around the 1x1 platforms
+        let world = filled(4, 3);
+        let mut layout = PlatformLayout::from_platforms([
+            Platform::new(Point::new(0, 0), platform_def!(1, 1), false),
+            Platform::new(Point::new(1, 1), platform_def!(1, 3), true),
+            Platform::new(Point::new(0, 2), platform_def!(1, 1), false),
+        ]);
+
+        merge_platforms(&mut layout, &world, &[platform_def!(3, 3)], None);
+        assert_eq!(layout.platform_count(), 3);
+    }
+}
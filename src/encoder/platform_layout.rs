@@ -1,25 +1,62 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
     num::NonZero,
-    ops::Not,
 };
 
 use itertools::Itertools;
 use log::trace;
 use new_zealand::nz;
 use rustsat::types::Assignment;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     TERRAIN_SUPPORT_DISTANCE,
-    encoder::EncodingVars,
-    math::{Grid, Point},
+    encoder::{
+        EncodingVars,
+        diagnostics::{self, Diagnostic, Severity},
+    },
+    math::{Dimensions, Grid, Point, Transform},
     platform::{Platform, PlatformDef},
     world::World,
 };
 
+/// Serialized as a list of platforms (plus removed tiles), see
+/// [`super::layout_file`].
 #[derive(Clone, Debug, Default)]
+#[derive(Serialize, Deserialize)]
+#[serde(from = "SerializedLayout", into = "SerializedLayout")]
 pub struct PlatformLayout {
     platforms: HashMap<Point, Platform>,
+    /// Terrain tiles to dig out rather than support
+    removed: HashSet<Point>,
+}
+
+/// [`PlatformLayout`] with its maps flattened into lists, since neither TOML
+/// nor JSON allow non-string keys. Sorted, so that saved files are stable.
+#[derive(Serialize, Deserialize)]
+struct SerializedLayout {
+    platforms: Vec<Platform>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    removed: Vec<Point>,
+}
+
+impl From<SerializedLayout> for PlatformLayout {
+    fn from(serialized: SerializedLayout) -> Self {
+        PlatformLayout::from_platforms(serialized.platforms).with_removed_tiles(serialized.removed)
+    }
+}
+
+impl From<PlatformLayout> for SerializedLayout {
+    fn from(layout: PlatformLayout) -> Self {
+        SerializedLayout {
+            platforms: layout
+                .platforms
+                .into_values()
+                .sorted_by_key(|plat| (plat.point().y, plat.point().x))
+                .collect(),
+            removed: layout.removed.into_iter().sorted_by_key(|p| (p.y, p.x)).collect(),
+        }
+    }
 }
 
 impl PlatformLayout {
@@ -48,7 +85,29 @@ impl PlatformLayout {
             trace!(target: "solution_lit", "=> {plat:?}");
         }
 
-        PlatformLayout { platforms }
+        PlatformLayout { platforms, removed: HashSet::new() }
+    }
+
+    /// Creates a layout from a set of platforms, keyed by their top-left point.
+    ///
+    /// If several platforms share a point, the last one is kept.
+    pub fn from_platforms(platforms: impl IntoIterator<Item = Platform>) -> Self {
+        PlatformLayout {
+            platforms: platforms.into_iter().map(|plat| (plat.point(), plat)).collect(),
+            removed: HashSet::new(),
+        }
+    }
+
+    /// Marks terrain tiles for removal. They don't need support, and don't
+    /// pass it on either.
+    pub fn with_removed_tiles(mut self, tiles: impl IntoIterator<Item = Point>) -> Self {
+        self.removed.extend(tiles);
+        self
+    }
+
+    /// Terrain tiles to dig out rather than support.
+    pub fn removed_tiles(&self) -> &HashSet<Point> {
+        &self.removed
     }
 
     pub fn platforms(&self) -> &HashMap<Point, Platform> {
@@ -82,70 +141,150 @@ impl PlatformLayout {
         self.platforms.get(&p).copied()
     }
 
-    pub fn validate(&self, world: &World) -> ValidationResult {
-        struct Tile<'a> {
-            terrain_supported: Option<bool>,
-            occupied_by: Option<&'a Platform>,
+    /// Adds platforms, replacing any at the same points.
+    pub fn with_platforms(mut self, platforms: impl IntoIterator<Item = Platform>) -> Self {
+        self.platforms.extend(platforms.into_iter().map(|plat| (plat.point(), plat)));
+        self
+    }
+
+    /// A copy of this layout without the platforms at the given points.
+    pub fn without_platforms(&self, points: impl IntoIterator<Item = Point>) -> Self {
+        let mut layout = self.clone();
+        for p in points {
+            layout.platforms.remove(&p);
+        }
+        layout
+    }
+
+    /// For each terrain tile, the number of steps through terrain to the
+    /// nearest tile covered by a platform, which is 0 for covered tiles.
+    ///
+    /// `None` for empty and removed tiles, and for terrain that isn't connected
+    /// to any platform. Terrain is supported if this is below
+    /// [`TERRAIN_SUPPORT_DISTANCE`].
+    pub fn support_distances(&self, world: &World) -> Grid<Option<usize>> {
+        let is_terrain =
+            |p: Point| world.grid().get(p) == Some(&true) && !self.removed.contains(&p);
+
+        let mut distances = Grid::new_fill(world.grid().dims(), None);
+        let mut queue: VecDeque<Point> = self
+            .platforms
+            .values()
+            .flat_map(|plat| plat.dims().iter_within().map(move |offset| offset + plat.point()))
+            .filter(|&p| is_terrain(p))
+            .collect();
+        for &p in queue.iter() {
+            distances.set(p, Some(0)).unwrap();
+        }
+
+        // Breadth-first, so each tile is reached the shortest way first
+        while let Some(p) = queue.pop_front() {
+            let next = distances.get(p).copied().flatten().unwrap() + 1;
+            for neighbor in p.neighbors() {
+                if is_terrain(neighbor) && distances.get(neighbor) == Some(&None) {
+                    distances.set(neighbor, Some(next)).unwrap();
+                    queue.push_back(neighbor);
+                }
+            }
         }
+        distances
+    }
 
+    pub fn validate(&self, world: &World) -> ValidationResult {
         let mut overlapping_platforms: HashSet<Platform> = HashSet::new();
         let mut out_of_bounds_platforms: HashSet<Platform> = HashSet::new();
+        let mut idle_platforms: HashSet<Platform> = HashSet::new();
 
-        let mut tracking_grid = Grid::try_from_vec(
-            world.grid().dims(),
-            world
-                .grid()
-                .iter()
-                .map(|b| Tile { terrain_supported: b.then_some(false), occupied_by: None })
-                .collect_vec(),
-        )
-        .unwrap();
-
+        let mut occupied_by: Grid<Option<&Platform>> = Grid::new_fill(world.grid().dims(), None);
         for (_, plat) in self.platforms.iter() {
+            let mut covers_terrain = false;
             for offset in plat.dims().iter_within() {
                 let point = offset + plat.point();
 
-                if let Some(tile) = tracking_grid.get_mut(point) {
-                    if let Some(other) = tile.occupied_by {
+                if let Some(tile) = occupied_by.get_mut(point) {
+                    if let Some(other) = tile {
                         // Platform overlap!
                         overlapping_platforms.insert(*plat);
-                        overlapping_platforms.insert(*other);
+                        overlapping_platforms.insert(**other);
                     } else {
-                        tile.occupied_by = Some(plat);
-                    }
-                    // Only terrain can be supported
-                    if let Some(supported) = tile.terrain_supported.as_mut() {
-                        *supported = true;
+                        *tile = Some(plat);
                     }
+                    covers_terrain |= world.grid().get(point) == Some(&true);
                 } else {
                     out_of_bounds_platforms.insert(*plat);
                 }
             }
+            if !covers_terrain {
+                idle_platforms.insert(*plat);
+            }
         }
 
-        // Extend terrain support
-        for _ in 0..(TERRAIN_SUPPORT_DISTANCE - 1) {
-            let supported_set: HashSet<Point> = tracking_grid
-                .enumerate()
-                .filter_map(|(p, t)| (Some(true) == t.terrain_supported).then_some(p))
-                .flat_map(Point::neighbors)
-                .collect();
-            for p in supported_set {
-                if let Some(tile) = tracking_grid.get_mut(p)
-                    && tile.terrain_supported.is_some()
-                {
-                    // Extend support only if there's terrain
-                    tile.terrain_supported = Some(true);
+        let mut unsupported_terrain = HashSet::new();
+        let mut nearest_platform = HashMap::new();
+        for (p, &distance) in self.support_distances(world).enumerate() {
+            let is_terrain = world.grid().get(p) == Some(&true) && !self.removed.contains(&p);
+            match distance {
+                _ if !is_terrain => {}
+                Some(distance) if distance < TERRAIN_SUPPORT_DISTANCE => {}
+                Some(distance) => {
+                    unsupported_terrain.insert(p);
+                    nearest_platform.insert(p, distance);
+                }
+                None => {
+                    unsupported_terrain.insert(p);
                 }
             }
         }
 
-        let unsupported_terrain = tracking_grid
-            .enumerate()
-            .filter_map(|(p, t)| (t.terrain_supported == Some(false)).then_some(p))
-            .collect();
+        ValidationResult {
+            overlapping_platforms,
+            unsupported_terrain,
+            out_of_bounds_platforms,
+            idle_platforms,
+            nearest_platform,
+        }
+    }
 
-        ValidationResult { overlapping_platforms, unsupported_terrain, out_of_bounds_platforms }
+    /// This layout on a grid with `dims` after `transform`, to go along with the
+    /// world transformed the same way (see [`World::transformed`]).
+    ///
+    /// Platforms and removed tiles that don't end up entirely within the new
+    /// dimensions, e.g. because they were cropped off, are dropped.
+    pub fn transformed(&self, transform: Transform, dims: Dimensions) -> Self {
+        let new_dims = transform.dims(dims);
+        let platforms = self.platforms.values().filter_map(|plat| {
+            let plat = plat.transformed(transform, dims)?;
+            let (near, far) = plat.area_corners()?;
+            (new_dims.contains(near) && new_dims.contains(far)).then_some(plat)
+        });
+        let removed = self
+            .removed
+            .iter()
+            .map(|&p| transform.point(p, dims))
+            .filter(|&p| new_dims.contains(p));
+        PlatformLayout::from_platforms(platforms).with_removed_tiles(removed)
+    }
+
+    /// Validates the layout once without each of its platforms, to find the
+    /// platforms that some terrain relies on alone. Terrain that's unsupported
+    /// with all platforms in place isn't counted.
+    pub fn validate_fault_tolerance(&self, world: &World) -> FaultToleranceResult {
+        let unsupported = self.validate(world).unsupported_terrain;
+        let single_points_of_failure = self
+            .platforms
+            .values()
+            .filter_map(|plat| {
+                let lost: HashSet<Point> = self
+                    .without_platforms([plat.point()])
+                    .validate(world)
+                    .unsupported_terrain
+                    .difference(&unsupported)
+                    .copied()
+                    .collect();
+                (!lost.is_empty()).then_some((*plat, lost))
+            })
+            .collect();
+        FaultToleranceResult { single_points_of_failure }
     }
 
     pub fn run_trivial_optimization(&mut self, world: &World) {
@@ -188,6 +327,25 @@ pub struct ValidationResult {
     pub unsupported_terrain: HashSet<Point>,
     pub overlapping_platforms: HashSet<Platform>,
     pub out_of_bounds_platforms: HashSet<Platform>,
+    /// Platforms that don't cover any terrain. These are allowed, just useless.
+    pub idle_platforms: HashSet<Platform>,
+    /// Distance through terrain to the nearest platform, for the unsupported
+    /// tiles that are connected to one (see
+    /// [`PlatformLayout::support_distances`])
+    pub nearest_platform: HashMap<Point, usize>,
+}
+
+/// See [`PlatformLayout::validate_fault_tolerance`].
+#[derive(Clone, Debug, Default)]
+pub struct FaultToleranceResult {
+    /// Platforms whose loss would leave terrain unsupported, with that terrain
+    pub single_points_of_failure: HashMap<Platform, HashSet<Point>>,
+}
+
+impl FaultToleranceResult {
+    pub fn is_fault_tolerant(&self) -> bool {
+        self.single_points_of_failure.is_empty()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -203,36 +361,33 @@ impl ValidationResult {
             && self.out_of_bounds_platforms.is_empty()
     }
 
-    pub fn iter_error_printouts(&self) -> impl Iterator<Item = ValidationErrorPrintout> {
-        fn format_platform(plat: &Platform) -> String {
-            format!(
-                "{}x{} at ({:>3};{:>3})",
-                plat.dims().width,
-                plat.dims().height,
-                plat.point().x,
-                plat.point().y
-            )
+    /// The same result, but with unsupported terrain not counting as an error,
+    /// for layouts that only support part of the terrain on purpose.
+    pub fn without_unsupported_terrain(&self) -> ValidationResult {
+        ValidationResult {
+            unsupported_terrain: HashSet::new(),
+            nearest_platform: HashMap::new(),
+            ..self.clone()
         }
+    }
 
-        [
-            self.unsupported_terrain.is_empty().not().then_some(ValidationErrorPrintout {
-                header: "unsupported terrain".to_string(),
-                items: self
-                    .unsupported_terrain
-                    .iter()
-                    .map(|point| format!("({:>3};{:>3})", point.x, point.y))
-                    .collect(),
-            }),
-            self.overlapping_platforms.is_empty().not().then_some(ValidationErrorPrintout {
-                header: "overlapping platforms".to_string(),
-                items: self.overlapping_platforms.iter().map(format_platform).collect(),
-            }),
-            self.out_of_bounds_platforms.is_empty().not().then_some(ValidationErrorPrintout {
-                header: "out-of-bounds platforms".to_string(),
-                items: self.out_of_bounds_platforms.iter().map(format_platform).collect(),
-            }),
-        ]
-        .into_iter()
-        .flatten()
+    /// All findings as typed [`Diagnostic`]s, errors first, in reading order.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        diagnostics::from_validation(self)
+    }
+
+    /// The error diagnostics, grouped by kind.
+    pub fn iter_error_printouts(&self) -> impl Iterator<Item = ValidationErrorPrintout> {
+        self.diagnostics()
+            .into_iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .chunk_by(|diagnostic| diagnostic.kind)
+            .into_iter()
+            .map(|(kind, diagnostics)| ValidationErrorPrintout {
+                header: kind.description().to_string(),
+                items: diagnostics.map(|diagnostic| diagnostic.to_string()).collect(),
+            })
+            .collect_vec()
+            .into_iter()
     }
 }
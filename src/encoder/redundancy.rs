@@ -0,0 +1,221 @@
+//! Removing redundant platforms from a finished layout.
+//!
+//! [`PlatformLayout::run_trivial_optimization`] only drops platforms that don't
+//! cover any terrain. A platform can also be redundant if all the terrain it
+//! supports is supported through its neighbours as well. Removing one platform
+//! can make another one necessary, so which ones to remove is a choice:
+//! [`PruneStrategy::Greedy`] tries them one by one, largest first, while
+//! [`PruneStrategy::Optimal`] finds the largest set that can be removed
+//! together.
+//!
+//! Every removal is checked with [`PlatformLayout::validate`]. A removal is
+//! allowed as long as no terrain becomes unsupported that was supported before,
+//! so this works on invalid (e.g. hand-made) layouts as well.
+
+use std::collections::HashSet;
+
+use anyhow::bail;
+use itertools::Itertools;
+
+use crate::{encoder::PlatformLayout, math::Point, platform::Platform, world::World};
+
+/// The largest number of individually redundant platforms
+/// [`PruneStrategy::Optimal`] accepts, as it searches through their subsets.
+/// Each subset is validated with a full search through the terrain, so this
+/// keeps the worst case at about a million validations.
+pub const MAX_OPTIMAL_CANDIDATES: usize = 20;
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum PruneStrategy {
+    /// Try each platform once, largest first, and remove it if it's redundant
+    #[default]
+    Greedy,
+    /// Remove as many platforms as possible (the largest ones on ties)
+    Optimal,
+}
+
+/// Removes redundant platforms from `layout`, and returns the removed ones in
+/// reading order.
+///
+/// Fails only for [`PruneStrategy::Optimal`] with more than
+/// [`MAX_OPTIMAL_CANDIDATES`] candidates.
+pub fn remove_redundant_platforms(
+    layout: &mut PlatformLayout,
+    world: &World,
+    strategy: PruneStrategy,
+) -> anyhow::Result<Vec<Platform>> {
+    let check = SupportCheck::new(layout, world);
+
+    // Largest first, as those are the most costly, then in reading order
+    let candidates = layout
+        .platforms()
+        .values()
+        .copied()
+        .sorted_by_key(|plat| {
+            (
+                std::cmp::Reverse(plat.dims().width * plat.dims().height),
+                plat.point().y,
+                plat.point().x,
+            )
+        })
+        .filter(|plat| check.allows(&layout.without_platforms([plat.point()])))
+        .collect_vec();
+
+    let removed = match strategy {
+        PruneStrategy::Greedy => {
+            let mut removed = vec![];
+            for plat in candidates {
+                let pruned =
+                    layout.without_platforms(removed.iter().chain([&plat]).map(Platform::point));
+                if check.allows(&pruned) {
+                    removed.push(plat);
+                }
+            }
+            removed
+        }
+        PruneStrategy::Optimal => {
+            if candidates.len() > MAX_OPTIMAL_CANDIDATES {
+                bail!(
+                    "Too many redundant platforms to search through ({}, at most \
+                     {MAX_OPTIMAL_CANDIDATES}), use the greedy strategy instead",
+                    candidates.len()
+                );
+            }
+            let mut search =
+                Search { layout, check: &check, candidates: &candidates, best: vec![] };
+            search.run(0, &mut vec![]);
+            search.best
+        }
+    };
+
+    *layout = layout.without_platforms(removed.iter().map(Platform::point));
+    Ok(removed.into_iter().sorted_by_key(|plat| (plat.point().y, plat.point().x)).collect())
+}
+
+/// Whether a pruned layout still supports everything the original one did.
+struct SupportCheck<'a> {
+    world: &'a World,
+    unsupported: HashSet<Point>,
+}
+
+impl<'a> SupportCheck<'a> {
+    fn new(layout: &PlatformLayout, world: &'a World) -> Self {
+        Self { world, unsupported: layout.validate(world).unsupported_terrain }
+    }
+
+    fn allows(&self, pruned: &PlatformLayout) -> bool {
+        pruned.validate(self.world).unsupported_terrain.is_subset(&self.unsupported)
+    }
+}
+
+/// Depth-first search through the subsets of the candidates. Removing fewer
+/// platforms never breaks support, so a subset is only extended while it's
+/// still allowed.
+struct Search<'a> {
+    layout: &'a PlatformLayout,
+    check: &'a SupportCheck<'a>,
+    /// Sorted largest first, so that the first best subset found prefers them
+    candidates: &'a [Platform],
+    best: Vec<Platform>,
+}
+
+impl Search<'_> {
+    fn run(&mut self, next: usize, removed: &mut Vec<Platform>) {
+        if removed.len() > self.best.len() {
+            self.best = removed.clone();
+        }
+        // Bound: even removing all remaining candidates wouldn't be better
+        if removed.len() + (self.candidates.len() - next) <= self.best.len() {
+            return;
+        }
+
+        let candidates = self.candidates;
+        for (i, &plat) in candidates.iter().enumerate().skip(next) {
+            let pruned =
+                self.layout.without_platforms(removed.iter().chain([&plat]).map(Platform::point));
+            if self.check.allows(&pruned) {
+                removed.push(plat);
+                self.run(i + 1, removed);
+                removed.pop();
+            }
+            if removed.len() + (candidates.len() - i - 1) <= self.best.len() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+    use crate::{
+        math::{Dimensions, Grid},
+        platform::PlatformDef,
+        platform_def,
+        world::WorldGrid,
+    };
+
+    fn plat(x: isize, y: isize, def: PlatformDef) -> Platform {
+        Platform::new(Point::new(x, y), def, false)
+    }
+
+    fn strip(width: usize) -> World {
+        World::new(WorldGrid(Grid::new_fill(Dimensions::new(width, 1), true)))
+    }
+
+    #[test_case(PruneStrategy::Greedy; "greedy")]
+    #[test_case(PruneStrategy::Optimal; "optimal")]
+    fn removes_platforms_covered_by_neighbours(strategy: PruneStrategy) {
+        // Support reaches 3 tiles, so the platforms at 1 and 8 hold up the
+        // whole strip on their own
+        let world = strip(9);
+        let mut layout = PlatformLayout::from_platforms([
+            plat(1, 0, platform_def!(1, 1)),
+            plat(4, 0, platform_def!(1, 1)),
+            plat(8, 0, platform_def!(1, 1)),
+        ]);
+
+        let removed = remove_redundant_platforms(&mut layout, &world, strategy).unwrap();
+        assert_eq!(removed, [plat(4, 0, platform_def!(1, 1))]);
+        assert_eq!(layout.platform_count(), 2);
+        assert!(layout.validate(&world).is_valid());
+    }
+
+    #[test]
+    fn optimal_beats_greedy() {
+        // The greedy pass removes the 3x1 platform first, although it could
+        // hold up the whole strip on its own
+        let world = strip(8);
+        let layout = PlatformLayout::from_platforms([
+            plat(0, 0, platform_def!(1, 1)),
+            plat(2, 0, platform_def!(3, 1)),
+            plat(6, 0, platform_def!(2, 1)),
+        ]);
+
+        let mut greedy = layout.clone();
+        remove_redundant_platforms(&mut greedy, &world, PruneStrategy::Greedy).unwrap();
+        let mut optimal = layout.clone();
+        remove_redundant_platforms(&mut optimal, &world, PruneStrategy::Optimal).unwrap();
+
+        assert!(greedy.validate(&world).is_valid());
+        assert!(optimal.validate(&world).is_valid());
+        assert_eq!(greedy.platform_count(), 2);
+        assert_eq!(optimal.platform_count(), 1);
+    }
+
+    #[test]
+    fn keeps_invalid_layouts_no_worse() {
+        let world = strip(12);
+        let mut layout = PlatformLayout::from_platforms([
+            plat(0, 0, platform_def!(1, 1)),
+            plat(1, 0, platform_def!(1, 1)),
+        ]);
+        let unsupported = layout.validate(&world).unsupported_terrain;
+
+        remove_redundant_platforms(&mut layout, &world, PruneStrategy::Greedy).unwrap();
+        assert_eq!(layout.platform_count(), 1);
+        assert_eq!(layout.validate(&world).unsupported_terrain, unsupported);
+    }
+}
@@ -0,0 +1,238 @@
+//! Which platform holds up each terrain tile, and through which tiles.
+//!
+//! Support spreads from the tiles a platform covers through adjacent terrain.
+//! For every supported tile, a [`SupportForest`] holds one [`SupportPath`]: the
+//! chain of tiles from it to a tile covered by a platform. Paths that end at the
+//! same platform form a tree, and [`SupportForest::held_up_by`] groups the tiles
+//! by platform.
+//!
+//! The paths can be taken from a solver's assignment, following the terrain
+//! layer variables ([`EncodedItem::Terrain`]) down to a platform, or found as
+//! shortest paths in any layout. The latter also works after a layout was
+//! changed, e.g. pruned or merged.
+//!
+//! [`EncodedItem::Terrain`]: crate::encoder::EncodedItem::Terrain
+
+use std::collections::{HashMap, VecDeque};
+
+use itertools::Itertools;
+use rustsat::types::{Assignment, TernaryVal};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    TERRAIN_SUPPORT_DISTANCE,
+    encoder::{EncodingVars, PlatformLayout},
+    math::{Grid, Point},
+    platform::Platform,
+    world::World,
+};
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SupportPath {
+    /// From the supported tile to the tile on the platform, each adjacent to
+    /// the one before
+    pub tiles: Vec<Point>,
+    pub platform: Platform,
+}
+
+impl SupportPath {
+    /// Number of steps from the tile to the platform.
+    pub fn len(&self) -> usize {
+        self.tiles.len() - 1
+    }
+
+    /// Whether the tile is on the platform itself.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SupportForest {
+    paths: Grid<Option<SupportPath>>,
+}
+
+impl SupportForest {
+    /// Shortest support paths in `layout`, ties broken in a fixed order.
+    pub fn new(layout: &PlatformLayout, world: &World) -> Self {
+        let is_terrain =
+            |p: Point| world.grid().get(p) == Some(&true) && !layout.removed_tiles().contains(&p);
+        let covered_by = coverage(layout, world);
+
+        // Breadth-first from the covered tiles, remembering where each tile was
+        // reached from
+        let mut next: Grid<Option<Point>> = Grid::new_fill(world.grid().dims(), None);
+        let mut distances: Grid<Option<usize>> = Grid::new_fill(world.grid().dims(), None);
+        let mut queue: VecDeque<Point> = covered_by
+            .enumerate()
+            .filter(|&(p, plat)| plat.is_some() && is_terrain(p))
+            .map(|(p, _)| p)
+            .collect();
+        for &p in queue.iter() {
+            distances.set(p, Some(0)).unwrap();
+        }
+        while let Some(p) = queue.pop_front() {
+            let distance = distances.get(p).copied().flatten().unwrap() + 1;
+            if distance >= TERRAIN_SUPPORT_DISTANCE {
+                continue;
+            }
+            for neighbor in p.neighbors() {
+                if is_terrain(neighbor) && distances.get(neighbor) == Some(&None) {
+                    distances.set(neighbor, Some(distance)).unwrap();
+                    next.set(neighbor, Some(p)).unwrap();
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        let paths = Grid::from_fn(world.grid().dims(), |p| {
+            distances.get(p).copied().flatten()?;
+            let tiles = std::iter::successors(Some(p), |&tile| next.get(tile).copied().flatten())
+                .collect_vec();
+            let platform = covered_by.get(*tiles.last()?).copied().flatten()?;
+            Some(SupportPath { tiles, platform })
+        });
+        Self { paths }
+    }
+
+    /// The support paths a solver found, read from the terrain layer variables
+    /// of `assignment`. `layout` is the layout of the same assignment (see
+    /// [`PlatformLayout::from_assignment`]), for looking up the platforms.
+    ///
+    /// Each tile is followed down the layers, one neighbour (or itself) per
+    /// layer, until it reaches a tile covered by a platform. Tiles that can't be
+    /// followed, e.g. because the assignment leaves them out, get no path.
+    pub fn from_assignment(
+        assignment: &Assignment,
+        vars: &EncodingVars,
+        layout: &PlatformLayout,
+        world: &World,
+    ) -> Self {
+        let covered_by = coverage(layout, world);
+        let is_set = |p: Point, layer: usize| {
+            vars.at(p).and_then(|tile_vars| tile_vars.terrain()).is_some_and(|terrain| {
+                assignment.lit_value(terrain[layer].pos_lit()) == TernaryVal::True
+            })
+        };
+
+        let paths = Grid::from_fn(world.grid().dims(), |p| {
+            if !is_set(p, 0) {
+                return None;
+            }
+            let mut tiles = vec![p];
+            for layer in 1..TERRAIN_SUPPORT_DISTANCE {
+                let current = *tiles.last().unwrap();
+                if covered_by.get(current).copied().flatten().is_some() {
+                    break;
+                }
+                // Moving on is preferred, staying is allowed by the encoding
+                let step = current
+                    .neighbors()
+                    .into_iter()
+                    .chain([current])
+                    .find(|&candidate| is_set(candidate, layer))?;
+                if step != current {
+                    tiles.push(step);
+                }
+            }
+            let platform = covered_by.get(*tiles.last().unwrap()).copied().flatten()?;
+            Some(SupportPath { tiles, platform })
+        });
+        Self { paths }
+    }
+
+    /// The path from `p` to its platform, `None` if it isn't supported.
+    pub fn path(&self, p: Point) -> Option<&SupportPath> {
+        self.paths.get(p)?.as_ref()
+    }
+
+    /// The tiles each platform holds up, in reading order. Tiles covered by a
+    /// platform are included.
+    pub fn held_up_by(&self) -> HashMap<Platform, Vec<Point>> {
+        self.paths
+            .enumerate()
+            .filter_map(|(p, path)| Some((path.as_ref()?.platform, p)))
+            .sorted_by_key(|&(_, p)| (p.y, p.x))
+            .into_group_map()
+    }
+}
+
+/// The platform covering each tile of the world.
+fn coverage(layout: &PlatformLayout, world: &World) -> Grid<Option<Platform>> {
+    let mut covered_by = Grid::new_fill(world.grid().dims(), None);
+    for plat in layout.platforms().values().sorted_by_key(|plat| (plat.point().y, plat.point().x)) {
+        for offset in plat.dims().iter_within() {
+            _ = covered_by.set(plat.point() + offset, Some(*plat));
+        }
+    }
+    covered_by
+}
+
+#[cfg(test)]
+mod tests {
+    use rustsat::solvers::{Solve, SolverResult};
+    use rustsat_glucose::simp::Glucose;
+
+    use super::*;
+    use crate::{
+        encoder::{Encoding, PlatformLimits},
+        math::Dimensions,
+        platform::PLATFORMS_DEFAULT,
+        platform_def,
+        world::WorldGrid,
+    };
+
+    fn filled(width: usize, height: usize) -> World {
+        World::new(WorldGrid(Grid::new_fill(Dimensions::new(width, height), true)))
+    }
+
+    fn assert_paths_valid(forest: &SupportForest, layout: &PlatformLayout, world: &World) {
+        for (p, _) in world.grid().enumerate().filter(|&(_, &terrain)| terrain) {
+            let path = forest.path(p).unwrap_or_else(|| panic!("no path for {p}"));
+            assert_eq!(path.tiles[0], p);
+            assert!(path.len() < TERRAIN_SUPPORT_DISTANCE, "{path:?} is too long");
+            for (a, b) in path.tiles.iter().tuple_windows() {
+                assert_eq!(a.manhattan_to(*b), 1, "{path:?} isn't connected");
+            }
+            assert_eq!(layout.get_platform(path.platform.point()), Some(path.platform));
+            let (near, far) = path.platform.area_corners().unwrap();
+            let end = *path.tiles.last().unwrap();
+            assert!(near.x <= end.x && end.x <= far.x && near.y <= end.y && end.y <= far.y);
+        }
+    }
+
+    #[test]
+    fn shortest_paths_lead_to_the_nearest_platform() {
+        let world = filled(9, 1);
+        let layout = PlatformLayout::from_platforms([
+            Platform::new(Point::new(1, 0), platform_def!(1, 1), false),
+            Platform::new(Point::new(7, 0), platform_def!(1, 2), true),
+        ]);
+        let forest = SupportForest::new(&layout, &world);
+
+        assert_paths_valid(&forest, &layout, &world);
+        let path = forest.path(Point::new(4, 0)).unwrap();
+        assert_eq!(
+            path.tiles,
+            [Point::new(4, 0), Point::new(3, 0), Point::new(2, 0), Point::new(1, 0)]
+        );
+        let held_up_by = forest.held_up_by();
+        assert_eq!(held_up_by[&layout.get_platform(Point::new(1, 0)).unwrap()].len(), 5);
+        assert_eq!(held_up_by[&layout.get_platform(Point::new(7, 0)).unwrap()].len(), 4);
+    }
+
+    #[test]
+    fn paths_follow_the_assignment() {
+        let world = filled(8, 7);
+        let encoding = Encoding::encode(&PLATFORMS_DEFAULT, world.grid());
+        let (cnf, _) = encoding.with_limits(&PlatformLimits::default()).into_cnf();
+        let mut solver = Glucose::default();
+        solver.add_cnf(cnf).unwrap();
+        assert_eq!(solver.solve().unwrap(), SolverResult::Sat);
+        let assignment = solver.full_solution().unwrap();
+        let layout = PlatformLayout::from_assignment(&assignment, encoding.vars());
+
+        let forest = SupportForest::from_assignment(&assignment, encoding.vars(), &layout, &world);
+        assert_paths_valid(&forest, &layout, &world);
+    }
+}
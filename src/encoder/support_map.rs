@@ -0,0 +1,119 @@
+//! How close a layout is to failing.
+//!
+//! Terrain is supported up to [`TERRAIN_SUPPORT_DISTANCE`] - 1 steps away from a
+//! platform. Tiles exactly at that limit are critical: moving a platform by a
+//! single tile, or removing one tile of terrain on the way, can leave them
+//! unsupported. A [`SupportMap`] holds the support distance of every terrain
+//! tile, along with a histogram of the distances and the critical tiles.
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    TERRAIN_SUPPORT_DISTANCE,
+    encoder::PlatformLayout,
+    math::{Grid, Point},
+    world::World,
+};
+
+/// The largest distance at which terrain is still supported.
+pub const MAX_SUPPORT_DISTANCE: usize = TERRAIN_SUPPORT_DISTANCE - 1;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SupportMap {
+    /// See [`PlatformLayout::support_distances`]
+    pub distances: Grid<Option<usize>>,
+    /// The largest distance of any terrain tile connected to a platform
+    pub max: Option<usize>,
+    /// Number of terrain tiles at each distance, up to [`Self::max`]
+    pub histogram: Vec<usize>,
+    /// Tiles at exactly [`MAX_SUPPORT_DISTANCE`], in reading order
+    pub critical: Vec<Point>,
+    /// Terrain tiles that aren't connected to any platform
+    pub disconnected: usize,
+}
+
+impl SupportMap {
+    pub fn new(layout: &PlatformLayout, world: &World) -> Self {
+        let distances = layout.support_distances(world);
+        let max = distances.iter().flatten().copied().max();
+
+        let mut histogram = vec![0; max.map_or(0, |max| max + 1)];
+        for &distance in distances.iter().flatten() {
+            histogram[distance] += 1;
+        }
+        let critical = distances
+            .enumerate()
+            .filter(|&(_, &distance)| distance == Some(MAX_SUPPORT_DISTANCE))
+            .map(|(p, _)| p)
+            .sorted_by_key(|p| (p.y, p.x))
+            .collect();
+        let disconnected = distances
+            .enumerate()
+            .filter(|&(p, distance)| {
+                distance.is_none()
+                    && world.grid().get(p) == Some(&true)
+                    && !layout.removed_tiles().contains(&p)
+            })
+            .count();
+
+        Self { distances, max, histogram, critical, disconnected }
+    }
+
+    /// How many more steps the farthest supported tile could be from its
+    /// platform, `None` if no tile is supported or any tile is too far.
+    pub fn slack(&self) -> Option<usize> {
+        MAX_SUPPORT_DISTANCE.checked_sub(self.max?)
+    }
+
+    /// Number of terrain tiles farther away than [`MAX_SUPPORT_DISTANCE`],
+    /// including disconnected ones.
+    pub fn unsupported(&self) -> usize {
+        self.histogram.iter().skip(TERRAIN_SUPPORT_DISTANCE).sum::<usize>() + self.disconnected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        math::Dimensions,
+        platform::{Platform, PlatformDef},
+        platform_def,
+        world::WorldGrid,
+    };
+
+    fn layout(platforms: impl IntoIterator<Item = (isize, PlatformDef)>) -> PlatformLayout {
+        PlatformLayout::from_platforms(
+            platforms.into_iter().map(|(x, def)| Platform::new(Point::new(x, 0), def, false)),
+        )
+    }
+
+    #[test]
+    fn counts_distances_along_a_strip() {
+        let mut terrain = Grid::new_fill(Dimensions::new(10, 1), true);
+        terrain.set(Point::new(9, 0), false).unwrap();
+        let world = World::new(WorldGrid(terrain));
+        let map = SupportMap::new(&layout([(2, platform_def!(1, 1))]), &world);
+
+        assert_eq!(map.max, Some(6));
+        assert_eq!(map.histogram, [1, 2, 2, 1, 1, 1, 1]);
+        assert_eq!(map.critical, [Point::new(5, 0)]);
+        assert_eq!(map.slack(), None);
+        assert_eq!(map.unsupported(), 3);
+    }
+
+    #[test]
+    fn reports_slack_and_disconnected_terrain() {
+        let mut terrain = Grid::new_fill(Dimensions::new(7, 1), true);
+        terrain.set(Point::new(4, 0), false).unwrap();
+        let world = World::new(WorldGrid(terrain));
+        let map = SupportMap::new(&layout([(1, platform_def!(1, 1))]), &world);
+
+        assert_eq!(map.max, Some(2));
+        assert_eq!(map.slack(), Some(1));
+        assert!(map.critical.is_empty());
+        assert_eq!(map.disconnected, 2);
+        assert_eq!(map.unsupported(), 2);
+    }
+}
@@ -2,9 +2,13 @@ use serde::{Deserialize, Serialize};
 
 use crate::world::World;
 
+pub mod brute_force;
 pub mod encoder;
 pub mod math;
 pub mod platform;
+pub mod solver;
+#[cfg(test)]
+mod test_utils;
 mod typed_ix;
 pub mod utils;
 pub mod world;
@@ -1,7 +1,9 @@
 pub mod dimensions;
 pub mod grid;
 pub mod point;
+pub mod transform;
 
 pub use dimensions::Dimensions;
 pub use grid::Grid;
 pub use point::Point;
+pub use transform::{Rotation, Transform};
@@ -0,0 +1,264 @@
+//! Rotating, mirroring, moving and resizing grids, and anything placed on them.
+//!
+//! A [`Transform`] maps the points of a grid with given dimensions to the
+//! points of the transformed grid. [`Grid::transformed`] fills each tile of the
+//! new grid from the tile it came from, and platforms (see
+//! [`Platform::transformed`]) and layouts map their corners the same way, so a
+//! world and its layout stay in line when both get the same transform.
+//!
+//! [`Platform::transformed`]: crate::platform::Platform::transformed
+
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::math::{Dimensions, Grid, Point};
+
+/// Clockwise, with y pointing down as in the rendered grids.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum Rotation {
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+impl Rotation {
+    /// Whether this swaps the width and height.
+    pub const fn is_quarter_turn(self) -> bool {
+        matches!(self, Rotation::Cw90 | Rotation::Cw270)
+    }
+
+    pub const fn degrees(self) -> u16 {
+        match self {
+            Rotation::Cw90 => 90,
+            Rotation::Cw180 => 180,
+            Rotation::Cw270 => 270,
+        }
+    }
+
+    pub const fn from_degrees(degrees: u16) -> Option<Self> {
+        match degrees {
+            90 => Some(Rotation::Cw90),
+            180 => Some(Rotation::Cw180),
+            270 => Some(Rotation::Cw270),
+            _ => None,
+        }
+    }
+
+    const fn inverse(self) -> Self {
+        match self {
+            Rotation::Cw90 => Rotation::Cw270,
+            Rotation::Cw180 => Rotation::Cw180,
+            Rotation::Cw270 => Rotation::Cw90,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum Transform {
+    Rotate(Rotation),
+    /// Swaps the left and right sides
+    MirrorLeftRight,
+    /// Swaps the top and bottom
+    MirrorTopBottom,
+    /// Moves everything by an offset, keeping the dimensions. Whatever is moved
+    /// out of bounds is lost.
+    Translate(Point),
+    /// Keeps only the area with its top-left corner at `origin`
+    Crop {
+        origin: Point,
+        dims: Dimensions,
+    },
+    /// Adds empty tiles on each side
+    Pad {
+        left: usize,
+        top: usize,
+        right: usize,
+        bottom: usize,
+    },
+}
+
+impl Transform {
+    /// The dimensions of a grid with `dims` after the transform.
+    pub fn dims(self, dims: Dimensions) -> Dimensions {
+        match self {
+            Transform::Rotate(rotation) if rotation.is_quarter_turn() => dims.flipped(),
+            Transform::Crop { dims, .. } => dims,
+            Transform::Pad { left, top, right, bottom } => {
+                Dimensions::new(dims.width + left + right, dims.height + top + bottom)
+            }
+            _ => dims,
+        }
+    }
+
+    /// Where `p` in a grid with `dims` ends up. The result can be out of
+    /// bounds, e.g. for the parts that are cropped off.
+    pub fn point(self, p: Point, dims: Dimensions) -> Point {
+        let (width, height) = (dims.width as isize, dims.height as isize);
+        match self {
+            Transform::Rotate(Rotation::Cw90) => Point::new(height - 1 - p.y, p.x),
+            Transform::Rotate(Rotation::Cw180) => Point::new(width - 1 - p.x, height - 1 - p.y),
+            Transform::Rotate(Rotation::Cw270) => Point::new(p.y, width - 1 - p.x),
+            Transform::MirrorLeftRight => Point::new(width - 1 - p.x, p.y),
+            Transform::MirrorTopBottom => Point::new(p.x, height - 1 - p.y),
+            Transform::Translate(offset) => p + offset,
+            Transform::Crop { origin, .. } => p - origin,
+            Transform::Pad { left, top, .. } => p + Point::new(left as isize, top as isize),
+        }
+    }
+
+    /// Where `p` in the transformed grid came from, the inverse of
+    /// [`Self::point`].
+    fn source_point(self, p: Point, dims: Dimensions) -> Point {
+        match self {
+            Transform::Rotate(rotation) => {
+                Transform::Rotate(rotation.inverse()).point(p, self.dims(dims))
+            }
+            Transform::MirrorLeftRight | Transform::MirrorTopBottom => self.point(p, dims),
+            Transform::Translate(offset) => p - offset,
+            Transform::Crop { origin, .. } => p + origin,
+            Transform::Pad { left, top, .. } => p - Point::new(left as isize, top as isize),
+        }
+    }
+
+    /// Where the area from `near` to `far` (both inclusive) ends up, as its new
+    /// near and far corners.
+    pub fn area(self, near: Point, far: Point, dims: Dimensions) -> (Point, Point) {
+        let (a, b) = (self.point(near, dims), self.point(far, dims));
+        (Point::new(a.x.min(b.x), a.y.min(b.y)), Point::new(a.x.max(b.x), a.y.max(b.y)))
+    }
+}
+
+impl Display for Transform {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Transform::Rotate(rotation) => write!(f, "rotate by {}°", rotation.degrees()),
+            Transform::MirrorLeftRight => write!(f, "mirror left to right"),
+            Transform::MirrorTopBottom => write!(f, "mirror top to bottom"),
+            Transform::Translate(offset) => write!(f, "translate by {offset}"),
+            Transform::Crop { origin, dims } => {
+                write!(f, "crop to {}x{} at {origin}", dims.width, dims.height)
+            }
+            Transform::Pad { left, top, right, bottom } => {
+                write!(f, "pad by {left} left, {top} top, {right} right, {bottom} bottom")
+            }
+        }
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    /// A transformed copy of this grid. Tiles that don't come from this grid,
+    /// e.g. padding, are set to `fill`.
+    pub fn transformed(&self, transform: Transform, fill: T) -> Grid<T> {
+        Grid::from_fn(transform.dims(self.dims()), |p| {
+            self.get(transform.source_point(p, self.dims()))
+                .cloned()
+                .unwrap_or_else(|| fill.clone())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use test_case::test_case;
+
+    use super::*;
+    use crate::{
+        encoder::PlatformLayout,
+        platform::Platform,
+        platform_def,
+        world::{World, WorldGrid},
+    };
+
+    /// Each tile holds its own position in the original grid.
+    fn numbered(width: usize, height: usize) -> Grid<Option<Point>> {
+        Grid::from_fn(Dimensions::new(width, height), Some)
+    }
+
+    fn rows(grid: &Grid<Option<Point>>) -> Vec<Vec<Option<(isize, isize)>>> {
+        grid.iter_rows().map(|row| row.iter().map(|p| p.map(|p| (p.x, p.y))).collect()).collect()
+    }
+
+    #[test]
+    fn rotates_clockwise() {
+        let rotated = numbered(3, 2).transformed(Transform::Rotate(Rotation::Cw90), None);
+        assert_eq!(
+            rows(&rotated),
+            [
+                [Some((0, 1)), Some((0, 0))],
+                [Some((1, 1)), Some((1, 0))],
+                [Some((2, 1)), Some((2, 0))],
+            ]
+        );
+    }
+
+    #[test]
+    fn crops_and_pads() {
+        let cropped = numbered(4, 3).transformed(
+            Transform::Crop { origin: Point::new(1, 1), dims: Dimensions::new(2, 2) },
+            None,
+        );
+        assert_eq!(rows(&cropped), [[Some((1, 1)), Some((2, 1))], [Some((1, 2)), Some((2, 2))]]);
+
+        let padded = numbered(1, 1)
+            .transformed(Transform::Pad { left: 1, top: 0, right: 0, bottom: 1 }, None);
+        assert_eq!(rows(&padded), [[None, Some((0, 0))], [None, None]]);
+    }
+
+    #[test_case(Transform::Rotate(Rotation::Cw90))]
+    #[test_case(Transform::Rotate(Rotation::Cw180))]
+    #[test_case(Transform::Rotate(Rotation::Cw270))]
+    #[test_case(Transform::MirrorLeftRight)]
+    #[test_case(Transform::MirrorTopBottom)]
+    #[test_case(Transform::Translate(Point::new(2, -1)))]
+    #[test_case(Transform::Crop { origin: Point::new(1, 2), dims: Dimensions::new(3, 1) })]
+    #[test_case(Transform::Pad { left: 1, top: 2, right: 3, bottom: 0 })]
+    fn tiles_move_with_their_points(transform: Transform) {
+        let dims = Dimensions::new(5, 4);
+        let transformed = numbered(5, 4).transformed(transform, None);
+        assert_eq!(transformed.dims(), transform.dims(dims));
+        for p in dims.iter_within() {
+            let moved = transform.point(p, dims);
+            if transformed.dims().contains(moved) {
+                assert_eq!(transformed.get(moved), Some(&Some(p)));
+            }
+        }
+    }
+
+    #[test_case(Transform::Rotate(Rotation::Cw90))]
+    #[test_case(Transform::Rotate(Rotation::Cw270))]
+    #[test_case(Transform::MirrorTopBottom)]
+    #[test_case(Transform::Pad { left: 2, top: 1, right: 0, bottom: 3 })]
+    fn layouts_stay_in_line_with_their_world(transform: Transform) {
+        let mut terrain = Grid::new_fill(Dimensions::new(9, 4), true);
+        terrain.set(Point::new(3, 0), false).unwrap();
+        let world = World::new(WorldGrid(terrain));
+        let layout = PlatformLayout::from_platforms([
+            Platform::new(Point::new(0, 0), platform_def!(1, 1), false),
+            Platform::new(Point::new(6, 2), platform_def!(1, 2), true),
+        ]);
+        let dims = world.grid().dims();
+
+        let moved_world = world.transformed(transform);
+        let moved_layout = layout.transformed(transform, dims);
+        assert_eq!(moved_layout.platform_count(), layout.platform_count());
+        let expected: HashSet<Point> = layout
+            .validate(&world)
+            .unsupported_terrain
+            .into_iter()
+            .map(|p| transform.point(p, dims))
+            .collect();
+        assert_eq!(moved_layout.validate(&moved_world).unsupported_terrain, expected);
+    }
+
+    #[test]
+    fn four_quarter_turns_are_the_identity() {
+        let grid = numbered(3, 2);
+        let turned = (0..4).fold(grid.clone(), |grid, _| {
+            grid.transformed(Transform::Rotate(Rotation::Cw90), None)
+        });
+        assert_eq!(rows(&turned), rows(&grid));
+    }
+}
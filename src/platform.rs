@@ -4,7 +4,7 @@ use std::fmt::{Display, Formatter};
 
 use serde::{Deserialize, Serialize};
 
-use crate::math::{Dimensions, Point};
+use crate::math::{Dimensions, Point, Transform};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 #[derive(Serialize, Deserialize)]
@@ -16,7 +16,7 @@ pub struct PlatformDef {
 macro_rules! platform_def {
     ($x:literal, $y:literal) => {{
         use $crate::platform::PlatformDef;
-        PlatformDef::new(Dimensions::new($x, $y))
+        PlatformDef::new($crate::math::Dimensions::new($x, $y))
     }};
 }
 
@@ -115,6 +115,18 @@ impl Platform {
     pub fn def(&self) -> PlatformDef {
         self.def
     }
+
+    /// This platform on a grid with `dims` after `transform`, see
+    /// [`Transform::area`]. Quarter turns flip the rotation of rectangular
+    /// platforms.
+    ///
+    /// Returns `None` if the platform has no area.
+    pub fn transformed(&self, transform: Transform, dims: Dimensions) -> Option<Self> {
+        let (near, far) = self.area_corners()?;
+        let (near, _) = transform.area(near, far, dims);
+        let turned = matches!(transform, Transform::Rotate(rotation) if rotation.is_quarter_turn());
+        Some(Self::new(near, self.def, self.rotated ^ (turned && self.def.rectangular())))
+    }
 }
 
 #[allow(unused_macros)]
@@ -123,7 +135,7 @@ macro_rules! platform {
         Platform::new(Point::new($x, $y), platform_def!(1, 1), false)
     };
     (1x2 @ $x:literal, $y:literal) => {
-        Platform::new(Point::new($x, $y), platform_def!(1, 2))
+        Platform::new(Point::new($x, $y), platform_def!(1, 2), false)
     };
     (2x1 @ $x:literal, $y:literal) => {
         Platform::new(Point::new($x, $y), platform_def!(1, 2), true)
@@ -142,6 +154,7 @@ mod tests {
     use test_case::{test_case, test_matrix};
 
     use super::*;
+    use crate::math::Rotation;
 
     // TODO: test other platform types?
 
@@ -230,4 +243,14 @@ mod tests {
             a.area_corners()
         );
     }
+
+    #[test_case(Transform::Rotate(Rotation::Cw90), platform!(2x1 @ 1, 1))]
+    #[test_case(Transform::Rotate(Rotation::Cw180), platform!(1x2 @ 7, 1))]
+    #[test_case(Transform::Rotate(Rotation::Cw270), platform!(2x1 @ 3, 7))]
+    #[test_case(Transform::MirrorLeftRight, platform!(1x2 @ 7, 3))]
+    fn platform_transform(transform: Transform, expected: Platform) {
+        let dims = Dimensions::new(9, 6);
+        let plat = Platform::new(Point::new(1, 3), platform_def!(1, 2), false);
+        assert_eq!(plat.transformed(transform, dims), Some(expected));
+    }
 }
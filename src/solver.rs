@@ -0,0 +1,505 @@
+//! # SAT solver backends
+//!
+//! Backends are enabled through cargo features, one per solver crate:
+//! `glucose` (default), `cadical`, `minisat` and `kissat`. All of them are
+//! listed in [`SolverKind`], and can be selected by name at runtime - the
+//! disabled ones just fail to start with [`SolverUnavailable`].
+//!
+//! Solver types differ per backend, so they're used through the
+//! object-safe [`DynSolver`] trait.
+//!
+//! Solvers without Rust bindings can be run as a subprocess instead, see
+//! [`external`]. Several solvers can be raced against each other, see
+//! [`portfolio`], or work on separate parts of the search space, see
+//! [`cube_and_conquer`]. Any of them can be run with a seed, see [`seeded`].
+//! [`SolverConfig`] selects any of these.
+
+use std::{
+    error::Error,
+    fmt::{Display, Formatter},
+    iter::Sum,
+    ops::Add,
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::bail;
+use itertools::Itertools;
+use rustsat::{
+    instances::Cnf,
+    solvers::{InterruptSolver, SolverResult, SolverStats},
+    types::{Assignment, Lit},
+};
+
+use crate::{
+    encoder::EncodingVars,
+    solver::{
+        cube_and_conquer::{CubeConfig, CubeSolver},
+        external::{ExternalProcessSolver, ExternalSolver},
+        portfolio::{PortfolioMember, PortfolioSolver},
+        seeded::SeededSolver,
+    },
+};
+
+pub mod alternatives;
+pub mod budget;
+pub mod certificate;
+pub mod cube_and_conquer;
+pub mod external;
+pub mod infeasibility;
+pub mod manifest;
+pub mod minimize;
+pub mod partial_support;
+pub mod portfolio;
+pub mod progress;
+pub mod removal;
+pub mod seeded;
+
+/// An object-safe subset of the rustsat solver traits.
+pub trait DynSolver: Send {
+    fn add_cnf(&mut self, cnf: Cnf) -> anyhow::Result<()>;
+
+    fn solve(&mut self) -> anyhow::Result<SolverResult>;
+
+    fn full_solution(&self) -> anyhow::Result<Assignment>;
+
+    fn stats(&self) -> SolverStats;
+
+    /// A handle that can interrupt a running [`Self::solve`] call from another
+    /// thread.
+    fn interrupter(&mut self) -> Box<dyn InterruptSolver + Send>;
+
+    /// Solver name and version.
+    fn signature(&self) -> &'static str;
+
+    /// Limits the number of conflicts for the next [`Self::solve`] call, after
+    /// which it returns [`SolverResult::Interrupted`].
+    fn limit_conflicts(&mut self, _limit: Option<u32>) -> anyhow::Result<()> {
+        bail!("`{}` doesn't support conflict limits", self.signature())
+    }
+
+    /// Limits the number of propagations for the next [`Self::solve`] call,
+    /// after which it returns [`SolverResult::Interrupted`].
+    fn limit_propagations(&mut self, _limit: Option<u32>) -> anyhow::Result<()> {
+        bail!("`{}` doesn't support propagation limits", self.signature())
+    }
+
+    /// Writes a DRAT proof to `path` while solving, so that an UNSAT answer
+    /// can be checked independently.
+    fn trace_proof(&mut self, _path: &Path) -> anyhow::Result<()> {
+        bail!("`{}` doesn't support proof logging", self.signature())
+    }
+
+    /// Like [`Self::solve`], but with the given literals assumed to be true.
+    fn solve_assumps(&mut self, _assumps: &[Lit]) -> anyhow::Result<SolverResult> {
+        bail!("`{}` doesn't support assumptions", self.signature())
+    }
+
+    /// After [`Self::solve_assumps`] returned [`SolverResult::Unsat`], the
+    /// assumptions that were needed for it, negated.
+    fn core(&mut self) -> anyhow::Result<Vec<Lit>> {
+        bail!("`{}` doesn't support assumptions", self.signature())
+    }
+
+    /// The solver's own search counters, summed over all calls so far. `None`
+    /// if the backend doesn't keep them.
+    fn search_stats(&self) -> Option<SearchStats> {
+        None
+    }
+}
+
+/// Counters of the search itself, as kept by the backend. None of the
+/// backends report restarts.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct SearchStats {
+    pub conflicts: u64,
+    pub decisions: u64,
+    pub propagations: u64,
+}
+
+impl Add for SearchStats {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            conflicts: self.conflicts + rhs.conflicts,
+            decisions: self.decisions + rhs.decisions,
+            propagations: self.propagations + rhs.propagations,
+        }
+    }
+}
+
+impl Sum for SearchStats {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+/// Implements [`DynSolver`] by forwarding to the rustsat traits.
+#[allow(unused_macros)]
+macro_rules! impl_dyn_solver {
+    ($solver:ty $(, $limit:ident)*) => {
+        impl DynSolver for $solver {
+            fn add_cnf(&mut self, cnf: Cnf) -> anyhow::Result<()> {
+                rustsat::solvers::Solve::add_cnf(self, cnf)
+            }
+
+            fn solve(&mut self) -> anyhow::Result<SolverResult> {
+                rustsat::solvers::Solve::solve(self)
+            }
+
+            fn full_solution(&self) -> anyhow::Result<Assignment> {
+                rustsat::solvers::Solve::full_solution(self)
+            }
+
+            fn stats(&self) -> SolverStats {
+                rustsat::solvers::SolveStats::stats(self)
+            }
+
+            fn interrupter(&mut self) -> Box<dyn InterruptSolver + Send> {
+                Box::new(rustsat::solvers::Interrupt::interrupter(self))
+            }
+
+            fn signature(&self) -> &'static str {
+                rustsat::solvers::Solve::signature(self)
+            }
+
+            $(impl_dyn_solver!(@$limit);)*
+        }
+    };
+    (@assumptions) => {
+        fn solve_assumps(&mut self, assumps: &[Lit]) -> anyhow::Result<SolverResult> {
+            rustsat::solvers::SolveIncremental::solve_assumps(self, assumps)
+        }
+
+        fn core(&mut self) -> anyhow::Result<Vec<Lit>> {
+            rustsat::solvers::SolveIncremental::core(self)
+        }
+    };
+    (@conflicts) => {
+        fn limit_conflicts(&mut self, limit: Option<u32>) -> anyhow::Result<()> {
+            rustsat::solvers::LimitConflicts::limit_conflicts(self, limit)
+        }
+    };
+    (@proof) => {
+        fn trace_proof(&mut self, path: &Path) -> anyhow::Result<()> {
+            let format = rustsat_cadical::ProofFormat::Drat { binary: false };
+            Ok(rustsat_cadical::CaDiCaL::trace_proof(self, path, format)?)
+        }
+    };
+    (@propagations) => {
+        fn limit_propagations(&mut self, limit: Option<u32>) -> anyhow::Result<()> {
+            rustsat::solvers::LimitPropagations::limit_propagations(self, limit)
+        }
+    };
+    (@search_stats) => {
+        fn search_stats(&self) -> Option<SearchStats> {
+            use rustsat::solvers::GetInternalStats;
+            Some(SearchStats {
+                conflicts: self.conflicts() as u64,
+                decisions: self.decisions() as u64,
+                propagations: self.propagations() as u64,
+            })
+        }
+    };
+}
+
+#[cfg(feature = "glucose")]
+impl_dyn_solver!(
+    rustsat_glucose::simp::Glucose,
+    assumptions,
+    conflicts,
+    propagations,
+    search_stats
+);
+#[cfg(feature = "cadical")]
+impl_dyn_solver!(
+    rustsat_cadical::CaDiCaL<'static, 'static>,
+    assumptions,
+    conflicts,
+    proof,
+    search_stats
+);
+#[cfg(feature = "minisat")]
+impl_dyn_solver!(
+    rustsat_minisat::core::Minisat,
+    assumptions,
+    conflicts,
+    propagations,
+    search_stats
+);
+#[cfg(feature = "kissat")]
+impl_dyn_solver!(rustsat_kissat::Kissat<'static>, conflicts);
+
+/// All known solver backends, regardless of whether they're enabled.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum SolverKind {
+    Glucose,
+    Cadical,
+    Minisat,
+    Kissat,
+}
+
+impl SolverKind {
+    pub const ALL: [SolverKind; 4] =
+        [SolverKind::Glucose, SolverKind::Cadical, SolverKind::Minisat, SolverKind::Kissat];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            SolverKind::Glucose => "glucose",
+            SolverKind::Cadical => "cadical",
+            SolverKind::Minisat => "minisat",
+            SolverKind::Kissat => "kissat",
+        }
+    }
+
+    /// Whether the backend's cargo feature is enabled.
+    pub const fn is_enabled(self) -> bool {
+        match self {
+            SolverKind::Glucose => cfg!(feature = "glucose"),
+            SolverKind::Cadical => cfg!(feature = "cadical"),
+            SolverKind::Minisat => cfg!(feature = "minisat"),
+            SolverKind::Kissat => cfg!(feature = "kissat"),
+        }
+    }
+
+    pub fn iter_enabled() -> impl Iterator<Item = SolverKind> {
+        Self::ALL.into_iter().filter(|kind| kind.is_enabled())
+    }
+
+    /// The first enabled backend, if any.
+    pub fn default_enabled() -> Option<SolverKind> {
+        Self::iter_enabled().next()
+    }
+
+    /// Creates a new solver instance with the default configuration.
+    pub fn create(self) -> Result<Box<dyn DynSolver>, SolverUnavailable> {
+        match self {
+            #[cfg(feature = "glucose")]
+            SolverKind::Glucose => Ok(Box::new(rustsat_glucose::simp::Glucose::default())),
+            #[cfg(feature = "cadical")]
+            SolverKind::Cadical => Ok(Box::new(rustsat_cadical::CaDiCaL::default())),
+            #[cfg(feature = "minisat")]
+            SolverKind::Minisat => Ok(Box::new(rustsat_minisat::core::Minisat::default())),
+            #[cfg(feature = "kissat")]
+            SolverKind::Kissat => Ok(Box::new(rustsat_kissat::Kissat::default())),
+            #[allow(unreachable_patterns)]
+            kind => Err(SolverUnavailable(kind)),
+        }
+    }
+}
+
+impl Display for SolverKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for SolverKind {
+    type Err = UnknownSolver;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| UnknownSolver(s.to_owned()))
+    }
+}
+
+/// A built-in backend, an external solver executable, or a portfolio of them.
+///
+/// Parsed from either a [`SolverKind`] name, `external:<command>` (e.g.
+/// `external:kissat -q`), `portfolio:<solver>[@seed],...` (e.g.
+/// `portfolio:glucose,glucose@1,cadical`), or `cubes:<solver>[:<depth>]` (e.g.
+/// `cubes:glucose:6`). A built-in backend can be seeded with `<solver>@<seed>`
+/// (e.g. `glucose@7`), any other one with `seeded:<seed>:<solver>` (e.g.
+/// `seeded:7:cubes:glucose:6`), which is also how they're displayed.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum SolverConfig {
+    Builtin(SolverKind),
+    External(ExternalSolver),
+    Portfolio(Vec<PortfolioMember>),
+    CubeAndConquer(CubeConfig),
+    Seeded { solver: Box<SolverConfig>, seed: u64 },
+}
+
+impl SolverConfig {
+    /// Creates a solver for an instance with the given variables. Only
+    /// cube-and-conquer needs them, to pick the split variables.
+    pub fn create(&self, vars: &EncodingVars) -> anyhow::Result<Box<dyn DynSolver>> {
+        Ok(match self {
+            SolverConfig::Builtin(kind) => kind.create()?,
+            SolverConfig::External(command) => {
+                Box::new(ExternalProcessSolver::new(command.clone()))
+            }
+            SolverConfig::Portfolio(members) => Box::new(PortfolioSolver::new(members, vars)?),
+            SolverConfig::CubeAndConquer(config) => {
+                Box::new(CubeSolver::new(config.clone(), vars)?)
+            }
+            SolverConfig::Seeded { solver, seed } => {
+                Box::new(SeededSolver::new(solver.create(vars)?, *seed))
+            }
+        })
+    }
+
+    /// Runs this solver with the given seed, replacing any previous one.
+    pub fn with_seed(self, seed: u64) -> Self {
+        SolverConfig::Seeded { solver: Box::new(self.without_seed()), seed }
+    }
+
+    pub fn without_seed(self) -> Self {
+        match self {
+            SolverConfig::Seeded { solver, .. } => *solver,
+            solver => solver,
+        }
+    }
+
+    /// The seed set with [`Self::with_seed`], if any.
+    pub fn seed(&self) -> Option<u64> {
+        match self {
+            SolverConfig::Seeded { seed, .. } => Some(*seed),
+            _ => None,
+        }
+    }
+}
+
+impl From<SolverKind> for SolverConfig {
+    fn from(kind: SolverKind) -> Self {
+        SolverConfig::Builtin(kind)
+    }
+}
+
+impl Display for SolverConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolverConfig::Builtin(kind) => write!(f, "{kind}"),
+            SolverConfig::External(command) => write!(f, "external:{command}"),
+            SolverConfig::Portfolio(members) => {
+                write!(f, "portfolio:{}", members.iter().map(ToString::to_string).join(","))
+            }
+            SolverConfig::CubeAndConquer(config) => write!(f, "cubes:{config}"),
+            SolverConfig::Seeded { solver, seed } => match **solver {
+                SolverConfig::Builtin(kind) => write!(f, "{kind}@{seed}"),
+                // `@` could be part of an external command or portfolio member
+                _ => write!(f, "{SEEDED_PREFIX}{seed}:{solver}"),
+            },
+        }
+    }
+}
+
+const SEEDED_PREFIX: &str = "seeded:";
+
+impl FromStr for SolverConfig {
+    type Err = UnknownSolver;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(seeded) = s.strip_prefix(SEEDED_PREFIX) {
+            let (seed, solver) =
+                seeded.split_once(':').ok_or_else(|| UnknownSolver(s.to_owned()))?;
+            let seed = seed.trim().parse().map_err(|_| UnknownSolver(s.to_owned()))?;
+            Ok(solver.parse::<SolverConfig>()?.with_seed(seed))
+        } else if let Some(command) = s.strip_prefix("external:") {
+            ExternalSolver::parse(command)
+                .map(SolverConfig::External)
+                .ok_or_else(|| UnknownSolver(s.to_owned()))
+        } else if let Some(members) = s.strip_prefix("portfolio:") {
+            portfolio::parse_members(members).map(SolverConfig::Portfolio)
+        } else if let Some(cubes) = s.strip_prefix("cubes:") {
+            let (solver, depth) = match cubes.split_once(':') {
+                Some((solver, depth)) => {
+                    (solver, depth.trim().parse().map_err(|_| UnknownSolver(s.to_owned()))?)
+                }
+                None => (cubes, CubeConfig::DEFAULT_DEPTH),
+            };
+            Ok(SolverConfig::CubeAndConquer(CubeConfig {
+                solver: solver.parse()?,
+                depth,
+                threads: None,
+            }))
+        } else if let Some((solver, seed)) = s.rsplit_once('@') {
+            let seed = seed.trim().parse().map_err(|_| UnknownSolver(s.to_owned()))?;
+            Ok(SolverConfig::Builtin(solver.parse()?).with_seed(seed))
+        } else {
+            s.parse().map(SolverConfig::Builtin)
+        }
+    }
+}
+
+/// The backend exists, but its cargo feature is not enabled.
+#[derive(Copy, Clone, Debug)]
+pub struct SolverUnavailable(pub SolverKind);
+
+impl Display for SolverUnavailable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "solver `{0}` is not available (build with the `{0}` feature)", self.0)
+    }
+}
+
+impl Error for SolverUnavailable {}
+
+#[derive(Clone, Debug)]
+pub struct UnknownSolver(pub String);
+
+impl Display for UnknownSolver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown solver `{}` (expected one of: ", self.0)?;
+        for (i, kind) in SolverKind::ALL.into_iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            f.write_str(kind.name())?;
+        }
+        f.write_str(", external:<command>, portfolio:<solver>,..., cubes:<solver>)")
+    }
+}
+
+impl Error for UnknownSolver {}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    fn cubes(depth: usize) -> SolverConfig {
+        SolverConfig::CubeAndConquer(CubeConfig {
+            solver: SolverKind::Glucose,
+            depth,
+            threads: None,
+        })
+    }
+
+    fn portfolio() -> SolverConfig {
+        SolverConfig::Portfolio(vec![
+            PortfolioMember { solver: SolverKind::Glucose.into(), seed: None },
+            PortfolioMember { solver: SolverKind::Cadical.into(), seed: Some(1) },
+        ])
+    }
+
+    fn external() -> SolverConfig {
+        SolverConfig::External(ExternalSolver::new("kissat").with_args(["-q", "--seed@7"]))
+    }
+
+    #[test_case(SolverKind::Glucose.into())]
+    #[test_case(external())]
+    #[test_case(portfolio())]
+    #[test_case(cubes(6))]
+    #[test_case(SolverConfig::from(SolverKind::Minisat).with_seed(7))]
+    #[test_case(external().with_seed(7))]
+    #[test_case(portfolio().with_seed(7))]
+    #[test_case(cubes(6).with_seed(3))]
+    fn config_round_trips(config: SolverConfig) {
+        assert_eq!(config.to_string().parse::<SolverConfig>().unwrap(), config);
+    }
+
+    #[test]
+    fn parse_seeded() {
+        assert_eq!("glucose@7".parse::<SolverConfig>().unwrap().seed(), Some(7));
+        assert_eq!(
+            "seeded:3:cubes:glucose:6".parse::<SolverConfig>().unwrap(),
+            cubes(6).with_seed(3)
+        );
+        assert!("seeded:x:glucose".parse::<SolverConfig>().is_err());
+    }
+}
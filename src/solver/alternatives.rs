@@ -0,0 +1,156 @@
+//! Enumerating several equally good layouts.
+//!
+//! Once the optimum is known, the objective is fixed at it, and each found
+//! layout is excluded by a constraint over the per-tile platform vars (see
+//! [`EncodingVars::layout_lits`][crate::encoder::EncodingVars::layout_lits]).
+//! With a minimum distance of 1 that's a plain blocking clause; larger
+//! distances require that many vars to differ from every previous layout.
+
+use rustsat::types::constraints::CardConstraint;
+
+use crate::{
+    encoder::{Encoding, PlatformLayout, PlatformLimits},
+    solver::{
+        SolverConfig,
+        budget::{BudgetTracker, CallOutcome, StopHandle, StopReason},
+    },
+};
+
+/// How many alternative layouts to look for.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct AlternativesOptions {
+    /// Maximum number of layouts, including the first optimal one
+    pub count: usize,
+    /// Minimum Hamming distance between any two layouts, counted in per-tile
+    /// platform vars. A platform that's added or removed counts once for each
+    /// encoded size it contains, so e.g. moving a single 1x1 platform counts 2.
+    pub min_distance: usize,
+}
+
+impl Default for AlternativesOptions {
+    fn default() -> Self {
+        Self { count: 1, min_distance: 1 }
+    }
+}
+
+/// Distinct layouts that are all optimal.
+#[derive(Clone, Debug)]
+pub struct Alternatives {
+    /// The first one is the layout the optimum was found with
+    pub layouts: Vec<PlatformLayout>,
+    /// `None` if `count` layouts were found, [`StopReason::Unsat`] if there are
+    /// no further layouts, or the budget limit that was hit
+    pub stop_reason: Option<StopReason>,
+}
+
+/// Finds up to `options.count` layouts as good as `first`, whose objective
+/// `limits` has to be bounded at (see
+/// [`limit_objective`][crate::solver::minimize::limit_objective]).
+///
+/// Every layout found is passed to `on_layout`, starting with `first`.
+#[allow(clippy::too_many_arguments)]
+pub fn enumerate_optimal(
+    encoding: &Encoding,
+    limits: &PlatformLimits,
+    solver_config: &SolverConfig,
+    first: PlatformLayout,
+    options: AlternativesOptions,
+    tracker: &mut BudgetTracker,
+    stop: &StopHandle,
+    mut on_layout: impl FnMut(&PlatformLayout),
+) -> anyhow::Result<Alternatives> {
+    on_layout(&first);
+    let mut layouts = vec![first];
+
+    let stop_reason = loop {
+        if layouts.len() >= options.count {
+            break None;
+        }
+
+        let mut instance = encoding.with_limits(limits);
+        for layout in layouts.iter() {
+            let differing = encoding.vars().layout_lits(layout).into_iter().map(|lit| !lit);
+            instance.add_card_constr(CardConstraint::new_lb(differing, options.min_distance));
+        }
+        let (cnf, _) = instance.into_cnf();
+        let mut solver = solver_config.create(encoding.vars())?;
+        solver.add_cnf(cnf)?;
+
+        match tracker.solve(solver.as_mut(), stop)? {
+            CallOutcome::Sat => {}
+            CallOutcome::Unsat => break Some(StopReason::Unsat),
+            CallOutcome::Stopped(reason) => break Some(reason),
+        }
+
+        let layout = PlatformLayout::from_assignment(&solver.full_solution()?, encoding.vars());
+        on_layout(&layout);
+        layouts.push(layout);
+    };
+
+    Ok(Alternatives { layouts, stop_reason })
+}
+
+#[cfg(all(test, feature = "glucose"))]
+mod tests {
+    use itertools::Itertools;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::{
+        platform::PLATFORMS_DEFAULT,
+        solver::{
+            SolverKind,
+            budget::SolveBudget,
+            minimize::{MinimizeOptions, limit_objective, minimize},
+        },
+        test_utils::filled_world,
+    };
+
+    fn distance(encoding: &Encoding, a: &PlatformLayout, b: &PlatformLayout) -> usize {
+        let vars = encoding.vars();
+        vars.layout_lits(a).into_iter().zip(vars.layout_lits(b)).filter(|(a, b)| a != b).count()
+    }
+
+    #[test_case(1; "distinct")]
+    #[test_case(4; "diverse")]
+    fn alternatives_are_optimal_and_apart(min_distance: usize) {
+        let world = filled_world(6, 6);
+        let encoding = Encoding::encode(&PLATFORMS_DEFAULT, world.grid());
+        let solver_config = SolverKind::Glucose.into();
+
+        let outcome = minimize(
+            &encoding,
+            PlatformLimits::default(),
+            &solver_config,
+            MinimizeOptions::default(),
+            &StopHandle::default(),
+            |_| {},
+        )
+        .unwrap();
+        let first = outcome.best.unwrap();
+        let optimum = first.platform_count();
+
+        let mut limits = PlatformLimits::default();
+        limit_objective(&mut limits, optimum as isize);
+        let alternatives = enumerate_optimal(
+            &encoding,
+            &limits,
+            &solver_config,
+            first,
+            AlternativesOptions { count: 5, min_distance },
+            &mut BudgetTracker::new(SolveBudget::default()),
+            &StopHandle::default(),
+            |_| {},
+        )
+        .unwrap();
+
+        assert!(alternatives.layouts.len() > 1);
+        for layout in alternatives.layouts.iter() {
+            assert_eq!(layout.platform_count(), optimum);
+            assert!(layout.validate(&world).is_valid());
+        }
+        for (a, b) in alternatives.layouts.iter().tuple_combinations() {
+            assert!(distance(&encoding, a, b) >= min_distance);
+        }
+    }
+}
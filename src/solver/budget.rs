@@ -0,0 +1,472 @@
+//! Stopping criteria for solver runs.
+//!
+//! A run is a sequence of solver calls, e.g. the steps of
+//! [`minimize`][crate::solver::minimize::minimize]. [`BudgetTracker`] runs the
+//! individual calls, and stops them once any part of the [`SolveBudget`] is
+//! used up, or when stopped from outside through a [`StopHandle`].
+
+use std::{
+    fmt::{Display, Formatter},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+        mpsc::RecvTimeoutError,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use rustsat::solvers::{InterruptSolver, SolverResult};
+
+use crate::solver::{
+    DynSolver,
+    progress::{PROGRESS_SLICE, Progress, ProgressSender},
+};
+
+/// Conflict budgets are enforced by solving in slices of at most this many
+/// conflicts, since solvers can only be limited per call. Each slice is charged
+/// the conflicts the solver reports (see [`DynSolver::search_stats`]), or the
+/// whole slice for backends that don't count them. The last slice is cut down
+/// to what's left of the budget.
+///
+/// So conflict budgets are approximate: without counters, a run can stop
+/// before using its whole budget, and solvers may go a little past a limit
+/// before noticing it. Solvers that pass the limit on to several solvers, like
+/// [`PortfolioSolver`], can spend up to that many times a slice per slice.
+///
+/// [`PortfolioSolver`]: crate::solver::portfolio::PortfolioSolver
+pub const CONFLICT_SLICE: u32 = 10_000;
+
+/// Limits for a solver run. Everything is unlimited by default.
+#[derive(Clone, Debug, Default)]
+pub struct SolveBudget {
+    /// Wall-clock limit for the whole run
+    pub time_limit: Option<Duration>,
+    /// Conflict limit for each solver call, approximate (see
+    /// [`CONFLICT_SLICE`])
+    pub conflicts_per_call: Option<u32>,
+    /// Propagation limit for each solver call
+    pub propagations_per_call: Option<u32>,
+    /// Conflict limit for all solver calls of the run together, approximate
+    /// (see [`CONFLICT_SLICE`])
+    pub total_conflicts: Option<u64>,
+    /// Stop if no better solution was found for this long
+    pub no_improvement: Option<Duration>,
+}
+
+impl SolveBudget {
+    pub fn is_unlimited(&self) -> bool {
+        self.time_limit.is_none()
+            && self.conflicts_per_call.is_none()
+            && self.propagations_per_call.is_none()
+            && self.total_conflicts.is_none()
+            && self.no_improvement.is_none()
+    }
+
+    fn has_conflict_limit(&self) -> bool {
+        self.conflicts_per_call.is_some() || self.total_conflicts.is_some()
+    }
+}
+
+/// Why a run stopped.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum StopReason {
+    /// The last solution can't be improved on
+    Optimal,
+    /// There is no solution at all
+    Unsat,
+    /// Stopped through a [`StopHandle`]
+    Interrupted,
+    TimeLimit,
+    ConflictLimit,
+    PropagationLimit,
+    TotalConflictLimit,
+    NoImprovement,
+}
+
+impl StopReason {
+    /// Whether the run stopped because of a [`SolveBudget`] limit, i.e. the
+    /// best solution might not be optimal.
+    pub fn is_budget(self) -> bool {
+        !matches!(self, StopReason::Optimal | StopReason::Unsat | StopReason::Interrupted)
+    }
+}
+
+impl Display for StopReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            StopReason::Optimal => "optimal solution found",
+            StopReason::Unsat => "no solution exists",
+            StopReason::Interrupted => "interrupted",
+            StopReason::TimeLimit => "time limit reached",
+            StopReason::ConflictLimit => "conflict limit per call reached",
+            StopReason::PropagationLimit => "propagation limit per call reached",
+            StopReason::TotalConflictLimit => "total conflict limit reached",
+            StopReason::NoImprovement => "no improvement within the time limit",
+        })
+    }
+}
+
+/// Stops a run from another thread, e.g. on Ctrl-C.
+#[derive(Clone, Default)]
+pub struct StopHandle(Arc<StopState>);
+
+#[derive(Default)]
+struct StopState {
+    stopped: AtomicBool,
+    /// Interrupter of the solver call that's currently running
+    current: Mutex<Option<Box<dyn InterruptSolver + Send>>>,
+}
+
+impl StopHandle {
+    pub fn stop(&self) {
+        let current = self.0.current.lock().unwrap();
+        self.0.stopped.store(true, Ordering::SeqCst);
+        if let Some(interrupter) = current.as_ref() {
+            interrupter.interrupt();
+        }
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.0.stopped.load(Ordering::SeqCst)
+    }
+
+    fn set_current(&self, interrupter: Option<Box<dyn InterruptSolver + Send>>) {
+        *self.0.current.lock().unwrap() = interrupter;
+    }
+}
+
+/// The outcome of one solver call within a budget.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CallOutcome {
+    Sat,
+    Unsat,
+    Stopped(StopReason),
+}
+
+/// Tracks the use of a [`SolveBudget`] across the solver calls of a run.
+#[derive(Clone, Debug)]
+pub struct BudgetTracker {
+    budget: SolveBudget,
+    start: Instant,
+    last_improvement: Instant,
+    total_conflicts: u64,
+    progress: Option<ProgressSender>,
+    /// Latest progress, shared with the ticker thread while solving
+    status: Arc<Mutex<Progress>>,
+}
+
+impl BudgetTracker {
+    pub fn new(budget: SolveBudget) -> Self {
+        let now = Instant::now();
+        Self {
+            budget,
+            start: now,
+            last_improvement: now,
+            total_conflicts: 0,
+            progress: None,
+            status: Default::default(),
+        }
+    }
+
+    /// Publishes [`Progress`] while solving. This also makes calls run in
+    /// slices, if the backend supports conflict limits.
+    pub fn with_progress(mut self, progress: ProgressSender) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// The latest progress, with the current elapsed time.
+    pub fn progress(&self) -> Progress {
+        Progress { elapsed: self.elapsed(), ..self.status.lock().unwrap().clone() }
+    }
+
+    fn publish_progress(&self) {
+        if let Some(progress) = &self.progress {
+            // Nobody listening anymore is fine
+            _ = progress.tx.send(self.progress());
+        }
+    }
+
+    pub fn budget(&self) -> &SolveBudget {
+        &self.budget
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Conflicts used so far, as charged for the slices (see
+    /// [`CONFLICT_SLICE`]). Only tracked when solving in slices.
+    pub fn total_conflicts(&self) -> u64 {
+        self.total_conflicts
+    }
+
+    /// Resets the no-improvement timer, and publishes the new best objective.
+    pub fn record_improvement(&mut self, objective: isize) {
+        self.last_improvement = Instant::now();
+        {
+            let mut status = self.status.lock().unwrap();
+            status.n_solutions += 1;
+            status.best_objective = Some(objective);
+        }
+        self.publish_progress();
+    }
+
+    /// The earliest time-based limit.
+    fn deadline(&self) -> Option<(Instant, StopReason)> {
+        let time_limit = self.budget.time_limit.map(|t| (self.start + t, StopReason::TimeLimit));
+        let no_improvement = self
+            .budget
+            .no_improvement
+            .map(|t| (self.last_improvement + t, StopReason::NoImprovement));
+        time_limit.into_iter().chain(no_improvement).min_by_key(|&(deadline, _)| deadline)
+    }
+
+    /// Checks the limits that apply to the run as a whole.
+    pub fn exhausted(&self) -> Option<StopReason> {
+        if let Some((deadline, reason)) = self.deadline()
+            && Instant::now() >= deadline
+        {
+            return Some(reason);
+        }
+        if let Some(total) = self.budget.total_conflicts
+            && self.total_conflicts >= total
+        {
+            return Some(StopReason::TotalConflictLimit);
+        }
+        None
+    }
+
+    /// Runs one solver call within the budget. The solver is interrupted when
+    /// a time limit passes, or when `stop` is used.
+    pub fn solve(
+        &mut self,
+        solver: &mut dyn DynSolver,
+        stop: &StopHandle,
+    ) -> anyhow::Result<CallOutcome> {
+        if let Some(reason) = self.exhausted() {
+            return Ok(CallOutcome::Stopped(reason));
+        }
+        if let Some(limit) = self.budget.propagations_per_call {
+            solver.limit_propagations(Some(limit))?;
+        }
+
+        stop.set_current(Some(solver.interrupter()));
+        // Checked after registering, so a concurrent stop can't be missed
+        if stop.is_stopped() {
+            stop.set_current(None);
+            return Ok(CallOutcome::Stopped(StopReason::Interrupted));
+        }
+
+        {
+            let mut status = self.status.lock().unwrap();
+            status.n_calls += 1;
+            status.solver_stats = solver.stats();
+            status.search_stats = solver.search_stats();
+        }
+        self.publish_progress();
+
+        let timed_out = AtomicBool::new(false);
+        let watchdog_interrupter = solver.interrupter();
+        let deadline = self.deadline();
+        let ticker = self.progress.clone().map(|progress| (progress, self.clone()));
+        let result = thread::scope(|scope| {
+            let (done_tx, done_rx) = mpsc::channel::<()>();
+            let (ticker_done_tx, ticker_done_rx) = mpsc::channel::<()>();
+            if let Some((progress, tracker)) = ticker {
+                scope.spawn(move || {
+                    while let Err(RecvTimeoutError::Timeout) =
+                        ticker_done_rx.recv_timeout(progress.interval)
+                    {
+                        if progress.tx.send(tracker.progress()).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            if let Some((deadline, _)) = deadline {
+                let timed_out = &timed_out;
+                let interrupter = watchdog_interrupter;
+                scope.spawn(move || {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    if let Err(RecvTimeoutError::Timeout) = done_rx.recv_timeout(timeout) {
+                        timed_out.store(true, Ordering::SeqCst);
+                        interrupter.interrupt();
+                    }
+                });
+            }
+
+            let result = self.solve_sliced(solver, stop, &timed_out);
+            drop(done_tx);
+            drop(ticker_done_tx);
+            result
+        });
+        stop.set_current(None);
+        {
+            let mut status = self.status.lock().unwrap();
+            status.solver_stats = solver.stats();
+            status.search_stats = solver.search_stats();
+        }
+        self.publish_progress();
+
+        Ok(match result? {
+            SolverResult::Sat => CallOutcome::Sat,
+            SolverResult::Unsat => CallOutcome::Unsat,
+            SolverResult::Interrupted => {
+                CallOutcome::Stopped(self.interrupt_reason(stop, &timed_out, deadline))
+            }
+        })
+    }
+
+    /// Figures out which limit made a call return [`SolverResult::Interrupted`].
+    fn interrupt_reason(
+        &self,
+        stop: &StopHandle,
+        timed_out: &AtomicBool,
+        deadline: Option<(Instant, StopReason)>,
+    ) -> StopReason {
+        if stop.is_stopped() {
+            StopReason::Interrupted
+        } else if let Some((_, reason)) = deadline
+            && timed_out.load(Ordering::SeqCst)
+        {
+            reason
+        } else if let Some(reason) = self.exhausted() {
+            reason
+        } else if self.budget.conflicts_per_call.is_some() {
+            StopReason::ConflictLimit
+        } else if self.budget.propagations_per_call.is_some() {
+            StopReason::PropagationLimit
+        } else {
+            StopReason::Interrupted
+        }
+    }
+
+    /// Solves in conflict slices if there's a conflict limit or progress is
+    /// reported, otherwise in one go. Returns [`SolverResult::Interrupted`] once
+    /// any limit is hit.
+    fn solve_sliced(
+        &mut self,
+        solver: &mut dyn DynSolver,
+        stop: &StopHandle,
+        timed_out: &AtomicBool,
+    ) -> anyhow::Result<SolverResult> {
+        if !self.budget.has_conflict_limit() {
+            // Slicing just for progress is optional
+            if self.progress.is_none() || solver.limit_conflicts(Some(PROGRESS_SLICE)).is_err() {
+                return solver.solve();
+            }
+        }
+        let slice_size = if self.progress.is_some() { PROGRESS_SLICE } else { CONFLICT_SLICE };
+
+        let mut call_conflicts = 0u64;
+        let mut solver_conflicts = solver.search_stats().map(|stats| stats.conflicts);
+        loop {
+            let remaining_call =
+                self.budget.conflicts_per_call.map(|limit| u64::from(limit) - call_conflicts);
+            let remaining_total =
+                self.budget.total_conflicts.map(|limit| limit.saturating_sub(self.total_conflicts));
+            let remaining =
+                remaining_call.into_iter().chain(remaining_total).min().unwrap_or(u64::MAX);
+            if remaining == 0 {
+                return Ok(SolverResult::Interrupted);
+            }
+
+            // Never past the remaining budget
+            let slice = remaining.min(u64::from(slice_size)) as u32;
+            solver.limit_conflicts(Some(slice))?;
+            let result = solver.solve()?;
+            let conflicts_after = solver.search_stats().map(|stats| stats.conflicts);
+            let used = match (solver_conflicts, conflicts_after) {
+                (Some(before), Some(after)) => after.saturating_sub(before),
+                _ => u64::from(slice),
+            };
+            solver_conflicts = conflicts_after;
+            call_conflicts += used;
+            self.total_conflicts += used;
+            {
+                let mut status = self.status.lock().unwrap();
+                status.conflicts = Some(self.total_conflicts);
+                status.solver_stats = solver.stats();
+                status.search_stats = solver.search_stats();
+            }
+
+            let stopped = stop.is_stopped() || timed_out.load(Ordering::SeqCst);
+            if result != SolverResult::Interrupted || stopped {
+                return Ok(result);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "glucose"))]
+mod tests {
+    use super::*;
+    use crate::{encoder::PlatformLimits, solver::SolverKind, test_utils::filled_encoding};
+
+    fn test_solver(width: usize, height: usize) -> Box<dyn DynSolver> {
+        let encoding = filled_encoding(width, height);
+        let mut solver = SolverKind::Glucose.create().unwrap();
+        solver.add_cnf(encoding.with_limits(&PlatformLimits::default()).into_cnf().0).unwrap();
+        solver
+    }
+
+    #[test]
+    fn unlimited_budget_solves() {
+        let mut tracker = BudgetTracker::new(SolveBudget::default());
+        let outcome = tracker.solve(test_solver(6, 6).as_mut(), &Default::default());
+        assert_eq!(outcome.unwrap(), CallOutcome::Sat);
+    }
+
+    #[test]
+    fn zero_time_limit_stops() {
+        let budget = SolveBudget { time_limit: Some(Duration::ZERO), ..Default::default() };
+        let mut tracker = BudgetTracker::new(budget);
+        let outcome = tracker.solve(test_solver(6, 6).as_mut(), &Default::default());
+        assert_eq!(outcome.unwrap(), CallOutcome::Stopped(StopReason::TimeLimit));
+    }
+
+    #[test]
+    fn stopped_before_solving() {
+        let stop = StopHandle::default();
+        stop.stop();
+        let mut tracker = BudgetTracker::new(SolveBudget::default());
+        let outcome = tracker.solve(test_solver(6, 6).as_mut(), &stop);
+        assert_eq!(outcome.unwrap(), CallOutcome::Stopped(StopReason::Interrupted));
+    }
+
+    #[test]
+    fn total_conflicts_are_tracked() {
+        let budget = SolveBudget { total_conflicts: Some(1), ..Default::default() };
+        let mut tracker = BudgetTracker::new(budget);
+        let mut solver = test_solver(6, 6);
+
+        // Either solved within the single conflict, or stopped on it
+        let outcome = tracker.solve(solver.as_mut(), &Default::default()).unwrap();
+        assert_eq!(tracker.total_conflicts(), solver.search_stats().unwrap().conflicts);
+        match outcome {
+            CallOutcome::Sat => {}
+            CallOutcome::Stopped(StopReason::TotalConflictLimit) => {
+                assert_eq!(tracker.exhausted(), Some(StopReason::TotalConflictLimit));
+            }
+            outcome => panic!("unexpected outcome {outcome:?}"),
+        }
+    }
+
+    #[test]
+    fn quick_calls_are_charged_their_conflicts() {
+        let budget =
+            SolveBudget { total_conflicts: Some(CONFLICT_SLICE.into()), ..Default::default() };
+        let mut tracker = BudgetTracker::new(budget);
+        let mut solver = test_solver(6, 6);
+
+        for _ in 0..5 {
+            let outcome = tracker.solve(solver.as_mut(), &Default::default()).unwrap();
+            assert_eq!(outcome, CallOutcome::Sat);
+        }
+        assert!(tracker.total_conflicts() < CONFLICT_SLICE.into());
+        assert_eq!(tracker.exhausted(), None);
+    }
+}
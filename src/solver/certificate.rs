@@ -0,0 +1,87 @@
+//! Proof-logged optimality certificates.
+//!
+//! A minimisation run is optimal once the instance asking for something better
+//! than the best solution comes back UNSAT. With proof logging, that final
+//! instance is stored next to the solver's DRAT proof of its unsatisfiability
+//! and the claimed optimum, so the claim can be checked offline, e.g. with
+//! `drat-trim instance.cnf proof.drat`.
+
+use std::{
+    fmt::{Display, Formatter},
+    fs,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use rustsat::instances::Cnf;
+use serde::{Deserialize, Serialize};
+
+use crate::encoder::export::write_dimacs;
+
+pub const CERTIFICATE_FILE: &str = "certificate.toml";
+pub const INSTANCE_FILE: &str = "instance.cnf";
+pub const PROOF_FILE: &str = "proof.drat";
+
+/// What the optimum is counted in.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Objective {
+    PlatformCount,
+    Weight,
+}
+
+impl Display for Objective {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Objective::PlatformCount => "platform count",
+            Objective::Weight => "weight",
+        })
+    }
+}
+
+/// The claim that no layout is better than `optimum`, i.e. that the stored
+/// instance (requiring at most `optimum - 1`) is unsatisfiable.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct OptimalityCertificate {
+    pub objective: Objective,
+    pub optimum: isize,
+    /// Solver that produced the proof
+    pub solver: String,
+    /// DIMACS instance, relative to the certificate
+    pub instance: PathBuf,
+    /// DRAT proof of the instance's unsatisfiability, relative to the
+    /// certificate
+    pub proof: PathBuf,
+}
+
+impl OptimalityCertificate {
+    /// Writes the certificate and the instance to `dir`. The proof is expected
+    /// to already be at [`PROOF_FILE`] in there.
+    pub fn write(&self, dir: &Path, cnf: &Cnf, n_vars: u32) -> anyhow::Result<()> {
+        let mut writer = BufWriter::new(
+            File::create(dir.join(&self.instance)).context("Failed to create the instance file")?,
+        );
+        write_dimacs(&mut writer, cnf, n_vars)?;
+        writer.flush()?;
+
+        let toml = toml::to_string_pretty(self).context("Failed to serialize the certificate")?;
+        fs::write(dir.join(CERTIFICATE_FILE), toml).context("Failed to write the certificate")
+    }
+
+    pub fn read(dir: &Path) -> anyhow::Result<Self> {
+        let toml = fs::read_to_string(dir.join(CERTIFICATE_FILE))
+            .context("Failed to read the certificate")?;
+        toml::from_str(&toml).context("Failed to parse the certificate")
+    }
+
+    /// A standard proof checker invocation for this certificate.
+    pub fn check_command(&self, dir: &Path) -> String {
+        format!(
+            "drat-trim {} {}",
+            dir.join(&self.instance).display(),
+            dir.join(&self.proof).display()
+        )
+    }
+}
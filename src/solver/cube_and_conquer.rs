@@ -0,0 +1,371 @@
+//! Cube-and-conquer: splitting the search into independent parts for parallel
+//! solving.
+//!
+//! The split variables (see [`EncodingVars::central_cell_vars`]) are fixed to
+//! every combination of values, giving `2^depth` cubes. A pool of worker
+//! threads takes cubes one by one, each worker with its own solver for the
+//! base instance that gets the cube's literals as assumptions, so clauses
+//! learned on one cube help with the next. The instance is satisfiable iff any
+//! cube is.
+//!
+//! The workers' solvers only last for one solve call, since some backends
+//! can't be used again after being interrupted, and every answer interrupts
+//! the workers still searching. So there are no conflict limits either: solving
+//! in slices (see [`budget`]) would start every cube over in each slice.
+//!
+//! [`budget`]: crate::solver::budget
+
+use std::{
+    fmt::{Display, Formatter},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+use anyhow::{Context, bail};
+use rustsat::{
+    instances::Cnf,
+    solvers::{InterruptSolver, SolverResult, SolverStats},
+    types::{Assignment, Lit, Var},
+};
+
+use crate::{
+    encoder::EncodingVars,
+    solver::{DynSolver, SearchStats, SolverKind},
+};
+
+/// Splitting deeper than this would make more cubes than is ever useful.
+pub const MAX_DEPTH: usize = 16;
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct CubeConfig {
+    /// Backend for solving the individual cubes
+    pub solver: SolverKind,
+    /// Number of split variables
+    pub depth: usize,
+    /// Worker threads, defaults to the available parallelism
+    pub threads: Option<usize>,
+}
+
+impl Display for CubeConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.solver, self.depth)
+    }
+}
+
+impl CubeConfig {
+    pub const DEFAULT_DEPTH: usize = 6;
+
+    fn threads(&self) -> usize {
+        self.threads
+            .or_else(|| thread::available_parallelism().ok().map(Into::into))
+            .unwrap_or(1)
+            .max(1)
+    }
+}
+
+#[derive(Default)]
+struct SharedState {
+    /// Set once the answer is known, or on interruption
+    stop: AtomicBool,
+    /// Set only on interruption from outside
+    interrupted: AtomicBool,
+    /// Interrupters of the workers' solvers
+    running: Mutex<Vec<Option<Box<dyn InterruptSolver + Send>>>>,
+}
+
+impl SharedState {
+    fn stop_all(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+        for interrupter in self.running.lock().unwrap().iter().flatten() {
+            interrupter.interrupt();
+        }
+    }
+}
+
+pub struct CubeSolver {
+    config: CubeConfig,
+    split_vars: Vec<Var>,
+    cnf: Cnf,
+    state: Arc<SharedState>,
+    solution: Option<Assignment>,
+    stats: SolverStats,
+    /// Summed over the workers' solvers of all calls, if the backend keeps
+    /// them
+    search_stats: Option<SearchStats>,
+}
+
+impl CubeSolver {
+    pub fn new(config: CubeConfig, vars: &EncodingVars) -> anyhow::Result<Self> {
+        if config.depth > MAX_DEPTH {
+            bail!("Cube depth {} is larger than the maximum of {MAX_DEPTH}", config.depth);
+        }
+        // Fail early rather than once per worker
+        let mut probe = config.solver.create()?;
+        probe.solve_assumps(&[]).with_context(|| {
+            format!(
+                "Cube-and-conquer needs a backend with assumptions, `{}` has none",
+                config.solver
+            )
+        })?;
+        let search_stats = probe.search_stats().map(|_| SearchStats::default());
+
+        let split_vars = vars.central_cell_vars(config.depth);
+        Ok(Self {
+            config,
+            split_vars,
+            cnf: Cnf::default(),
+            state: Default::default(),
+            solution: None,
+            stats: Default::default(),
+            search_stats,
+        })
+    }
+
+    fn cube_lits(&self, cube: usize) -> impl Iterator<Item = Lit> {
+        self.split_vars.iter().enumerate().map(move |(bit, var)| var.lit(cube & (1 << bit) == 0))
+    }
+
+    /// Creates the solver for a worker, and registers it for interrupts.
+    fn worker_solver(&self, worker: usize) -> anyhow::Result<Box<dyn DynSolver>> {
+        let mut solver = self.config.solver.create()?;
+        solver.add_cnf(self.cnf.clone())?;
+        self.state.running.lock().unwrap()[worker] = Some(solver.interrupter());
+        Ok(solver)
+    }
+
+    fn solve_cube(&self, solver: &mut dyn DynSolver, cube: usize) -> anyhow::Result<CubeResult> {
+        let assumps: Vec<Lit> = self.cube_lits(cube).collect();
+        // Checked after registering, so a concurrent stop_all can't be missed
+        if self.state.stop.load(Ordering::SeqCst) {
+            return Ok(CubeResult::Interrupted);
+        }
+
+        Ok(match solver.solve_assumps(&assumps)? {
+            SolverResult::Sat => CubeResult::Sat(solver.full_solution()?),
+            SolverResult::Unsat => CubeResult::Unsat,
+            SolverResult::Interrupted => CubeResult::Interrupted,
+        })
+    }
+
+    /// Solves cubes until there are none left or the answer is known, and
+    /// returns the stats of the worker's solver.
+    fn run_worker(
+        &self,
+        worker: usize,
+        next_cube: &AtomicUsize,
+        n_cubes: usize,
+        solution: &Mutex<Option<Assignment>>,
+    ) -> anyhow::Result<(SolverStats, Option<SearchStats>)> {
+        let mut solver = self.worker_solver(worker)?;
+        loop {
+            let cube = next_cube.fetch_add(1, Ordering::Relaxed);
+            if cube >= n_cubes || self.state.stop.load(Ordering::SeqCst) {
+                break;
+            }
+
+            match self.solve_cube(solver.as_mut(), cube).with_context(|| format!("Cube {cube}"))? {
+                CubeResult::Sat(assignment) => {
+                    solution.lock().unwrap().get_or_insert(assignment);
+                    self.state.stop_all();
+                }
+                CubeResult::Unsat | CubeResult::Interrupted => {}
+            }
+        }
+        Ok((solver.stats(), solver.search_stats()))
+    }
+}
+
+enum CubeResult {
+    Sat(Assignment),
+    Unsat,
+    Interrupted,
+}
+
+impl DynSolver for CubeSolver {
+    fn add_cnf(&mut self, cnf: Cnf) -> anyhow::Result<()> {
+        for clause in cnf {
+            self.stats.n_clauses += 1;
+            self.cnf.add_clause(clause);
+        }
+        Ok(())
+    }
+
+    fn solve(&mut self) -> anyhow::Result<SolverResult> {
+        self.solution = None;
+        // An interrupt may come in before solving even started
+        self.state.stop.store(self.state.interrupted.load(Ordering::SeqCst), Ordering::SeqCst);
+
+        let n_cubes = 1usize << self.split_vars.len();
+        let n_workers = self.config.threads().min(n_cubes);
+        *self.state.running.lock().unwrap() = (0..n_workers).map(|_| None).collect();
+
+        let next_cube = AtomicUsize::new(0);
+        let solution = Mutex::new(None);
+        let first_error = Mutex::new(None);
+        let stats = Mutex::new(SolverStats::default());
+        let search_stats = Mutex::new(SearchStats::default());
+        let this = &*self;
+
+        thread::scope(|scope| {
+            for worker in 0..n_workers {
+                let (next_cube, solution, first_error, stats, search_stats) =
+                    (&next_cube, &solution, &first_error, &stats, &search_stats);
+                scope.spawn(move || match this.run_worker(worker, next_cube, n_cubes, solution) {
+                    Ok((worker_stats, worker_search_stats)) => {
+                        let mut stats = stats.lock().unwrap();
+                        stats.n_terminated += worker_stats.n_terminated;
+                        stats.cpu_solve_time += worker_stats.cpu_solve_time;
+                        let mut search_stats = search_stats.lock().unwrap();
+                        *search_stats = *search_stats + worker_search_stats.unwrap_or_default();
+                    }
+                    Err(err) => {
+                        first_error.lock().unwrap().get_or_insert(err);
+                        this.state.stop_all();
+                    }
+                });
+            }
+        });
+        self.state.running.lock().unwrap().clear();
+
+        let cube_stats = stats.into_inner().unwrap();
+        self.stats.n_terminated += cube_stats.n_terminated;
+        self.stats.cpu_solve_time += cube_stats.cpu_solve_time;
+        if let Some(total) = &mut self.search_stats {
+            *total = *total + search_stats.into_inner().unwrap();
+        }
+
+        let interrupted = self.state.interrupted.swap(false, Ordering::SeqCst);
+        if let Some(assignment) = solution.into_inner().unwrap() {
+            self.stats.n_sat += 1;
+            self.solution = Some(assignment);
+            return Ok(SolverResult::Sat);
+        }
+        if let Some(err) = first_error.into_inner().unwrap() {
+            return Err(err);
+        }
+        if interrupted {
+            return Ok(SolverResult::Interrupted);
+        }
+        // Every cube was refuted
+        self.stats.n_unsat += 1;
+        Ok(SolverResult::Unsat)
+    }
+
+    fn full_solution(&self) -> anyhow::Result<Assignment> {
+        self.solution.clone().context("No solution available")
+    }
+
+    fn stats(&self) -> SolverStats {
+        self.stats.clone()
+    }
+
+    fn interrupter(&mut self) -> Box<dyn InterruptSolver + Send> {
+        Box::new(CubeInterrupter(self.state.clone()))
+    }
+
+    fn signature(&self) -> &'static str {
+        "cube-and-conquer"
+    }
+
+    fn search_stats(&self) -> Option<SearchStats> {
+        self.search_stats
+    }
+}
+
+struct CubeInterrupter(Arc<SharedState>);
+
+impl InterruptSolver for CubeInterrupter {
+    fn interrupt(&self) {
+        self.0.interrupted.store(true, Ordering::SeqCst);
+        self.0.stop_all();
+    }
+}
+
+#[cfg(all(test, feature = "glucose"))]
+mod tests {
+    use std::time::Duration;
+
+    use rustsat::{clause, instances::ManageVars};
+    use test_case::test_case;
+
+    use super::*;
+    use crate::{
+        encoder::{Encoding, PlatformLayout, PlatformLimits},
+        platform::PLATFORMS_DEFAULT,
+        platform_def,
+        solver::{
+            budget::{BudgetTracker, CallOutcome, SolveBudget, StopHandle},
+            progress::{PROGRESS_SLICE, ProgressSender},
+        },
+        test_utils::{filled_encoding, filled_world},
+    };
+
+    fn cube_solver(encoding: &Encoding, limits: &PlatformLimits, depth: usize) -> CubeSolver {
+        let config = CubeConfig { solver: SolverKind::Glucose, depth, threads: Some(4) };
+        let mut solver = CubeSolver::new(config, encoding.vars()).unwrap();
+        solver.add_cnf(encoding.with_limits(limits).into_cnf().0).unwrap();
+        solver
+    }
+
+    #[test_case(0)]
+    #[test_case(3)]
+    #[test_case(6)]
+    fn cubes_find_valid_layout(depth: usize) {
+        let world = filled_world(7, 6);
+        let encoding = Encoding::encode(&PLATFORMS_DEFAULT, world.grid());
+        let mut solver = cube_solver(&encoding, &PlatformLimits::default(), depth);
+
+        assert_eq!(solver.solve().unwrap(), SolverResult::Sat);
+        let layout =
+            PlatformLayout::from_assignment(&solver.full_solution().unwrap(), encoding.vars());
+        assert!(layout.validate(&world).is_valid());
+    }
+
+    #[test]
+    fn all_cubes_unsat() {
+        let encoding = filled_encoding(7, 6);
+        let mut limits = PlatformLimits::default();
+        limits.card_limits.insert(platform_def!(1, 1), 0);
+        let mut solver = cube_solver(&encoding, &limits, 4);
+
+        assert_eq!(solver.solve().unwrap(), SolverResult::Unsat);
+    }
+
+    /// `pigeons` in `holes` with at most one per hole, on new variables from
+    /// `first_var` on. Unsatisfiable if there are more pigeons than holes, and
+    /// hard to refute.
+    fn pigeonhole(first_var: u32, pigeons: u32, holes: u32) -> Cnf {
+        let var = |pigeon: u32, hole: u32| Var::new(first_var + pigeon * holes + hole);
+        let mut cnf = Cnf::default();
+        for pigeon in 0..pigeons {
+            cnf.add_clause((0..holes).map(|hole| var(pigeon, hole).pos_lit()).collect());
+        }
+        for hole in 0..holes {
+            for a in 0..pigeons {
+                for b in a + 1..pigeons {
+                    cnf.add_clause(clause![var(a, hole).neg_lit(), var(b, hole).neg_lit()]);
+                }
+            }
+        }
+        cnf
+    }
+
+    #[test]
+    fn cubes_longer_than_a_progress_slice_finish() {
+        let encoding = filled_encoding(4, 4);
+        let (cnf, var_manager) = encoding.with_limits(&PlatformLimits::default()).into_cnf();
+        let config = CubeConfig { solver: SolverKind::Glucose, depth: 1, threads: Some(1) };
+        let mut solver = CubeSolver::new(config, encoding.vars()).unwrap();
+        solver.add_cnf(cnf).unwrap();
+        solver.add_cnf(pigeonhole(var_manager.n_used(), 8, 7)).unwrap();
+
+        let (progress, _rx) = ProgressSender::channel(Duration::from_millis(10));
+        let mut tracker = BudgetTracker::new(SolveBudget::default()).with_progress(progress);
+        let outcome = tracker.solve(&mut solver, &StopHandle::default()).unwrap();
+        assert_eq!(outcome, CallOutcome::Unsat);
+        assert!(solver.search_stats().unwrap().conflicts > u64::from(PROGRESS_SLICE));
+    }
+}
@@ -0,0 +1,287 @@
+//! A backend that runs a solver executable as a subprocess.
+//!
+//! The instance is written to a temporary DIMACS file, whose path is passed to
+//! the solver as the last argument - followed by the proof path, if proof
+//! logging is enabled (the usual convention of CaDiCaL, Kissat etc.). The
+//! solver's stdout is expected to follow the SAT competition format, see
+//! [`parse_solver_output`][crate::encoder::export::parse_solver_output].
+
+use std::{
+    fmt::{Display, Formatter},
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Instant,
+};
+
+use anyhow::{Context, anyhow, bail};
+use rustsat::{
+    instances::Cnf,
+    solvers::{InterruptSolver, SolverResult, SolverStats},
+    types::Assignment,
+};
+
+use crate::{
+    encoder::export::{SolverOutput, parse_solver_output, write_dimacs},
+    solver::DynSolver,
+};
+
+/// Command line of an external solver, without the instance path.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ExternalSolver {
+    pub program: PathBuf,
+    pub args: Vec<String>,
+}
+
+impl ExternalSolver {
+    pub fn new(program: impl Into<PathBuf>) -> Self {
+        Self { program: program.into(), args: vec![] }
+    }
+
+    pub fn with_args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Parses a whitespace-separated command line, e.g. `kissat -q`.
+    pub fn parse(command: &str) -> Option<Self> {
+        let mut words = command.split_whitespace();
+        Some(Self::new(words.next()?).with_args(words))
+    }
+}
+
+impl Display for ExternalSolver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.program.display())?;
+        for arg in self.args.iter() {
+            write!(f, " {arg}")?;
+        }
+        Ok(())
+    }
+}
+
+/// [`DynSolver`] running an [`ExternalSolver`] once per [`DynSolver::solve`]
+/// call. Clauses are collected in memory until then.
+pub struct ExternalProcessSolver {
+    command: ExternalSolver,
+    cnf: Cnf,
+    n_vars: u32,
+    proof: Option<PathBuf>,
+    child: Arc<Mutex<Option<Child>>>,
+    interrupted: Arc<AtomicBool>,
+    solution: Option<Assignment>,
+    stats: SolverStats,
+}
+
+impl ExternalProcessSolver {
+    pub fn new(command: ExternalSolver) -> Self {
+        Self {
+            command,
+            cnf: Cnf::default(),
+            n_vars: 0,
+            proof: None,
+            child: Default::default(),
+            interrupted: Default::default(),
+            solution: None,
+            stats: Default::default(),
+        }
+    }
+
+    fn run(&mut self) -> anyhow::Result<SolverOutput> {
+        // A fresh file with a random name that only this user can read, rather
+        // than a predictable path others could put a symlink at
+        let mut file = tempfile::Builder::new()
+            .prefix("platform-cruncher-")
+            .suffix(".cnf")
+            .tempfile()
+            .context("Failed to create the instance file")?;
+        let mut writer = BufWriter::new(file.as_file_mut());
+        write_dimacs(&mut writer, &self.cnf, self.n_vars)?;
+        writer.flush()?;
+        drop(writer);
+        // Closed for the solver to open, and deleted when this returns
+        let instance_path = file.into_temp_path();
+
+        let mut child = Command::new(&self.command.program)
+            .args(&self.command.args)
+            .arg(&instance_path)
+            .args(&self.proof)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Failed to start `{}`", self.command))?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+
+        {
+            let mut guard = self.child.lock().unwrap();
+            // Interrupted before the process even started
+            if self.interrupted.load(Ordering::Acquire) {
+                _ = child.kill();
+            }
+            *guard = Some(child);
+        }
+
+        // Reads until the process exits (or gets killed)
+        let output = parse_solver_output(BufReader::new(stdout));
+        let status = match self.child.lock().unwrap().take() {
+            Some(mut child) => Some(child.wait()?),
+            None => None,
+        };
+
+        match output {
+            Ok(output) => Ok(output),
+            Err(_) if self.interrupted.load(Ordering::Acquire) => Ok(SolverOutput::Unknown),
+            Err(err) => Err(anyhow!(err).context(match status {
+                Some(status) => format!("`{}` exited with {status}", self.command),
+                None => format!("`{}` failed", self.command),
+            })),
+        }
+    }
+}
+
+impl DynSolver for ExternalProcessSolver {
+    fn add_cnf(&mut self, cnf: Cnf) -> anyhow::Result<()> {
+        for clause in cnf {
+            for lit in clause.iter() {
+                self.n_vars = self.n_vars.max(lit.var().idx32() + 1);
+            }
+            self.stats.n_clauses += 1;
+            self.cnf.add_clause(clause);
+        }
+        Ok(())
+    }
+
+    fn solve(&mut self) -> anyhow::Result<SolverResult> {
+        self.solution = None;
+
+        let start = Instant::now();
+        let output = self.run();
+        self.stats.cpu_solve_time += start.elapsed();
+
+        // Cleared as the call returns, so an interrupt only carries over into
+        // the next call if it came in between the two
+        if self.interrupted.swap(false, Ordering::AcqRel) {
+            self.stats.n_terminated += 1;
+            return Ok(SolverResult::Interrupted);
+        }
+        match output? {
+            SolverOutput::Sat(assignment) => {
+                self.stats.n_sat += 1;
+                self.solution = Some(assignment);
+                Ok(SolverResult::Sat)
+            }
+            SolverOutput::Unsat => {
+                self.stats.n_unsat += 1;
+                Ok(SolverResult::Unsat)
+            }
+            SolverOutput::Unknown => bail!("`{}` did not find an answer", self.command),
+        }
+    }
+
+    fn full_solution(&self) -> anyhow::Result<Assignment> {
+        self.solution.clone().context("No solution available")
+    }
+
+    fn stats(&self) -> SolverStats {
+        self.stats.clone()
+    }
+
+    fn interrupter(&mut self) -> Box<dyn InterruptSolver + Send> {
+        Box::new(ExternalInterrupter {
+            child: self.child.clone(),
+            interrupted: self.interrupted.clone(),
+        })
+    }
+
+    fn signature(&self) -> &'static str {
+        "external process"
+    }
+
+    fn trace_proof(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.proof = Some(path.to_owned());
+        Ok(())
+    }
+}
+
+/// Kills the running solver process.
+struct ExternalInterrupter {
+    child: Arc<Mutex<Option<Child>>>,
+    interrupted: Arc<AtomicBool>,
+}
+
+impl InterruptSolver for ExternalInterrupter {
+    fn interrupt(&self) {
+        let mut guard = self.child.lock().unwrap();
+        self.interrupted.store(true, Ordering::Release);
+        if let Some(child) = guard.as_mut() {
+            _ = child.kill();
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use rustsat::{
+        clause,
+        types::{Lit, TernaryVal},
+    };
+
+    use super::*;
+
+    fn fake_solver(mode: &str) -> ExternalProcessSolver {
+        let script = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fake_solver.sh");
+        let mut solver =
+            ExternalProcessSolver::new(ExternalSolver::new("sh").with_args([script, mode]));
+        let mut cnf = Cnf::default();
+        cnf.add_clause(clause![Lit::positive(0), Lit::positive(1)]);
+        cnf.add_clause(clause![Lit::negative(1)]);
+        solver.add_cnf(cnf).unwrap();
+        solver
+    }
+
+    #[test]
+    fn sat() {
+        let mut solver = fake_solver("sat");
+        assert_eq!(solver.solve().unwrap(), SolverResult::Sat);
+
+        let solution = solver.full_solution().unwrap();
+        assert_eq!(solution.lit_value(Lit::positive(0)), TernaryVal::True);
+        assert_eq!(solution.lit_value(Lit::positive(1)), TernaryVal::False);
+        assert_eq!(solver.stats().n_sat, 1);
+    }
+
+    #[test]
+    fn unsat_and_unknown() {
+        assert_eq!(fake_solver("unsat").solve().unwrap(), SolverResult::Unsat);
+        assert!(fake_solver("unknown").solve().is_err());
+    }
+
+    #[test]
+    fn interrupt_kills_process() {
+        let mut solver = fake_solver("hang");
+        let interrupter = solver.interrupter();
+
+        let start = Instant::now();
+        let handle = thread::spawn(move || solver.solve().unwrap());
+        thread::sleep(Duration::from_millis(200));
+        interrupter.interrupt();
+
+        assert_eq!(handle.join().unwrap(), SolverResult::Interrupted);
+        assert!(start.elapsed() < Duration::from_secs(30));
+    }
+
+    #[test]
+    fn interrupt_before_the_call_stops_only_that_call() {
+        let mut solver = fake_solver("sat");
+        solver.interrupter().interrupt();
+        assert_eq!(solver.solve().unwrap(), SolverResult::Interrupted);
+        assert_eq!(solver.solve().unwrap(), SolverResult::Sat);
+    }
+}
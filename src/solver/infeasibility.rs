@@ -0,0 +1,236 @@
+//! Explaining why a set of limits can't be met.
+//!
+//! Each limit, and the support requirement of each terrain region, is added as
+//! a group guarded by a selector literal, so the solver can be asked for the
+//! groups an UNSAT answer depended on (its unsatisfiable core). The core is
+//! then shrunk by dropping one group at a time, until every remaining group is
+//! needed, i.e. it's a minimal unsatisfiable subset.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{Display, Formatter},
+    mem,
+};
+
+use anyhow::bail;
+use itertools::Itertools;
+use rustsat::{
+    instances::{BasicVarManager, Cnf, ManageVars, SatInstance},
+    solvers::SolverResult,
+    types::Lit,
+};
+
+use crate::{
+    encoder::{Encoding, LimitConstraints, PlatformLimits},
+    math::Point,
+    platform::PlatformDef,
+    solver::{DynSolver, SolverKind},
+};
+
+/// Width and height of the terrain regions that are blamed as a whole.
+pub const REGION_SIZE: isize = 4;
+
+/// A part of the constraints that can be switched off as a whole.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConstraintGroup {
+    /// A cardinality limit from [`PlatformLimits::card_limits`]
+    CardLimit { def: PlatformDef, limit: usize },
+    /// [`PlatformLimits::weight_limit`]
+    WeightLimit(isize),
+    /// Support for all terrain tiles within a region
+    Terrain { tiles: Vec<Point> },
+}
+
+impl ConstraintGroup {
+    /// The tile closest to the center of a terrain region.
+    pub fn center(&self) -> Option<Point> {
+        let ConstraintGroup::Terrain { tiles } = self else {
+            return None;
+        };
+        let n = tiles.len() as isize;
+        let mean = Point::new(
+            tiles.iter().map(|p| p.x).sum::<isize>() / n,
+            tiles.iter().map(|p| p.y).sum::<isize>() / n,
+        );
+        tiles.iter().copied().min_by_key(|p| (p.x - mean.x).pow(2) + (p.y - mean.y).pow(2))
+    }
+}
+
+impl Display for ConstraintGroup {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConstraintGroup::CardLimit { def, limit } => {
+                write!(f, "the {} limit (at most {limit})", def.dimensions_str())
+            }
+            ConstraintGroup::WeightLimit(limit) => write!(f, "the weight limit (at most {limit})"),
+            ConstraintGroup::Terrain { .. } => {
+                let center = self.center().expect("terrain regions are never empty");
+                write!(f, "the tiles around ({};{})", center.x, center.y)
+            }
+        }
+    }
+}
+
+/// A minimal set of constraint groups that can't all be satisfied together.
+#[derive(Clone, Debug)]
+pub struct Infeasibility {
+    pub groups: Vec<ConstraintGroup>,
+}
+
+impl Display for Infeasibility {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.groups.as_slice() {
+            [] => write!(f, "the encoding is unsatisfiable even without any requirements"),
+            [group] => write!(f, "{group} cannot be satisfied"),
+            [init @ .., last] => {
+                write!(f, "{} plus {last} cannot all be satisfied", init.iter().join(", "))
+            }
+        }
+    }
+}
+
+/// Finds out which limits and terrain regions make `limits` infeasible.
+/// Returns `None` if the limits can be met after all.
+///
+/// `solver` has to support assumptions. Limits are preferred over terrain when
+/// there's a choice, since they're what can be changed.
+pub fn explain_infeasibility(
+    encoding: &Encoding,
+    limits: &PlatformLimits,
+    solver: SolverKind,
+) -> anyhow::Result<Option<Infeasibility>> {
+    let (base, required) = encoding.without_support_requirement();
+    let (mut cnf, mut var_manager) = base.into_cnf();
+    let mut groups = vec![];
+
+    for (&def, &limit) in
+        limits.card_limits.iter().sorted_by_key(|(def, _)| (def.dims().width, def.dims().height))
+    {
+        let single = PlatformLimits::new_unweighted([(def, limit)].into());
+        let constraints = encoding.limit_constraints(&single, &mut var_manager);
+        let selector = add_guarded(&mut cnf, &mut var_manager, &constraints);
+        groups.push((selector, ConstraintGroup::CardLimit { def, limit }));
+    }
+    if let Some(weight_limit) = limits.weight_limit {
+        let weighted = PlatformLimits::new_with_weights(
+            HashMap::new(),
+            limits.weights.clone(),
+            limits.weight_limit,
+        );
+        let constraints = encoding.limit_constraints(&weighted, &mut var_manager);
+        let selector = add_guarded(&mut cnf, &mut var_manager, &constraints);
+        groups.push((selector, ConstraintGroup::WeightLimit(weight_limit)));
+    }
+
+    let regions: BTreeMap<_, Vec<_>> = required
+        .into_iter()
+        .into_group_map_by(|(p, _)| (p.y.div_euclid(REGION_SIZE), p.x.div_euclid(REGION_SIZE)))
+        .into_iter()
+        .collect();
+    for (_, tiles) in regions {
+        let selector = var_manager.new_var().pos_lit();
+        for &(_, lit) in tiles.iter() {
+            cnf.add_lit_impl_lit(selector, lit);
+        }
+        let tiles = tiles.into_iter().map(|(p, _)| p).collect();
+        groups.push((selector, ConstraintGroup::Terrain { tiles }));
+    }
+
+    let mut solver = solver.create()?;
+    solver.add_cnf(cnf)?;
+    let by_core_lit: HashMap<Lit, usize> =
+        groups.iter().enumerate().map(|(i, &(selector, _))| (!selector, i)).collect();
+    let assumptions = |indices: &[usize]| indices.iter().map(|&i| groups[i].0).collect_vec();
+    let core_groups = |solver: &mut dyn DynSolver| -> anyhow::Result<Vec<usize>> {
+        Ok(solver.core()?.iter().filter_map(|lit| by_core_lit.get(lit).copied()).sorted().collect())
+    };
+
+    let all = (0..groups.len()).collect_vec();
+    match solver.solve_assumps(&assumptions(&all))? {
+        SolverResult::Sat => return Ok(None),
+        SolverResult::Unsat => {}
+        SolverResult::Interrupted => bail!("The solver was interrupted"),
+    }
+
+    // Deletion-based minimization. Groups are dropped from the back, i.e.
+    // terrain first
+    let mut candidates = core_groups(solver.as_mut())?;
+    let mut necessary = vec![];
+    while let Some(group) = candidates.pop() {
+        let rest = necessary.iter().chain(candidates.iter()).copied().collect_vec();
+        match solver.solve_assumps(&assumptions(&rest))? {
+            SolverResult::Sat => necessary.push(group),
+            SolverResult::Unsat => {
+                // The new core may drop further candidates
+                let core = core_groups(solver.as_mut())?;
+                candidates.retain(|i| core.contains(i));
+            }
+            SolverResult::Interrupted => bail!("The solver was interrupted"),
+        }
+    }
+
+    necessary.sort();
+    let mut groups = groups.into_iter().map(|(_, group)| Some(group)).collect_vec();
+    Ok(Some(Infeasibility {
+        groups: necessary.into_iter().map(|i| groups[i].take().unwrap()).collect(),
+    }))
+}
+
+/// Adds the constraints to `cnf`, each clause extended by the negation of a
+/// new selector, which is returned. The constraints only apply while it's true.
+fn add_guarded(
+    cnf: &mut Cnf,
+    var_manager: &mut BasicVarManager,
+    constraints: &LimitConstraints,
+) -> Lit {
+    let selector = var_manager.new_var().pos_lit();
+    let mut instance = SatInstance::new_with_manager(mem::take(var_manager));
+    constraints.add_to(&mut instance);
+    let (group_cnf, group_var_manager) = instance.into_cnf();
+    *var_manager = group_var_manager;
+
+    for mut clause in group_cnf {
+        clause.add(!selector);
+        cnf.add_clause(clause);
+    }
+    selector
+}
+
+#[cfg(all(test, feature = "glucose"))]
+mod tests {
+    use assertables::assert_contains;
+
+    use super::*;
+    use crate::{platform_def, test_utils::filled_encoding};
+
+    #[test]
+    fn feasible_limits_have_no_explanation() {
+        let limits = PlatformLimits::new_unweighted([(platform_def!(3, 3), 1)].into());
+        let explanation =
+            explain_infeasibility(&filled_encoding(8, 8), &limits, SolverKind::Glucose).unwrap();
+        assert!(explanation.is_none());
+    }
+
+    #[test]
+    fn blames_only_the_guilty_limit() {
+        let limits = PlatformLimits::new_unweighted(
+            [(platform_def!(1, 1), 0), (platform_def!(3, 3), 5)].into(),
+        );
+        let explanation =
+            explain_infeasibility(&filled_encoding(8, 8), &limits, SolverKind::Glucose)
+                .unwrap()
+                .unwrap();
+
+        // No platforms at all can't support any region
+        assert_eq!(explanation.groups.len(), 2);
+        assert_contains!(
+            explanation.groups,
+            &ConstraintGroup::CardLimit { def: platform_def!(1, 1), limit: 0 }
+        );
+        assert!(matches!(explanation.groups[1], ConstraintGroup::Terrain { .. }));
+        assert_contains!(
+            explanation.to_string(),
+            "the 1x1 limit (at most 0) plus the tiles around"
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! Run manifests, for reproducing a solve run.
+//!
+//! Variables and clauses are created in a fixed order, so the same terrain and
+//! platforms always give the same instance, identified by
+//! [`Encoding::fingerprint`]. Together with the limits, the solver, its seed
+//! and what the run solved for (see [`RunMode`]) that pins down the solutions
+//! a run finds, as long as it isn't stopped by a time limit.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{Display, Formatter},
+    fs,
+    path::Path,
+};
+
+use anyhow::{Context, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    encoder::{Encoding, PlatformLimits},
+    platform::PlatformDef,
+    solver::SolverConfig,
+};
+
+/// Version of this crate, as recorded in manifests.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Everything needed to repeat a solve run.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RunManifest {
+    /// Version of this crate
+    pub version: String,
+    /// [`Encoding::fingerprint`] of the base instance, as hex
+    pub encoding_hash: String,
+    /// Solver config, without the seed
+    pub solver: String,
+    /// Name and version reported by the solver
+    pub solver_signature: String,
+    pub seed: Option<u64>,
+    /// Cardinality limits by platform dimensions, e.g. `"3x3" = 2`
+    pub limits: BTreeMap<String, usize>,
+    /// Weights by platform dimensions
+    pub weights: BTreeMap<String, isize>,
+    pub weight_limit: Option<isize>,
+    /// Whether the encoding requires [`Encoding::with_fault_tolerance`], which
+    /// is part of its hash
+    #[serde(default)]
+    pub fault_tolerant: bool,
+    /// Manifests from before modes were recorded only describe minimize runs
+    #[serde(default)]
+    pub mode: RunMode,
+}
+
+/// What a run solved for, on top of the base instance.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum RunMode {
+    /// The fewest (or lightest) platforms that support all terrain
+    #[default]
+    Minimize,
+    /// Like [`RunMode::Minimize`], then up to `count` distinct optimal layouts
+    Alternatives { count: usize, min_distance: usize },
+    /// As much supported terrain as possible within the limits
+    Partial,
+    /// The cheapest mix of platforms and dug out terrain
+    Removal { cost: isize },
+}
+
+impl Display for RunMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunMode::Minimize => write!(f, "minimize"),
+            RunMode::Alternatives { count, min_distance } => {
+                write!(f, "minimize with up to {count} alternatives at distance {min_distance}")
+            }
+            RunMode::Partial => write!(f, "partial support"),
+            RunMode::Removal { cost } => write!(f, "removal cost {cost}"),
+        }
+    }
+}
+
+impl RunManifest {
+    /// Describes a run of `solver_config` on `encoding` with `limits`. Creates
+    /// a solver to ask for its signature.
+    pub fn new(
+        encoding: &Encoding,
+        limits: &PlatformLimits,
+        solver_config: &SolverConfig,
+    ) -> anyhow::Result<Self> {
+        let solver_signature = solver_config.create(encoding.vars())?.signature().to_owned();
+        Ok(Self {
+            version: VERSION.to_owned(),
+            encoding_hash: format!("{:016x}", encoding.fingerprint()),
+            solver: solver_config.clone().without_seed().to_string(),
+            solver_signature,
+            seed: solver_config.seed(),
+            limits: by_dims(&limits.card_limits),
+            weights: by_dims(&limits.weights),
+            weight_limit: limits.weight_limit,
+            fault_tolerant: encoding.is_fault_tolerant(),
+            mode: RunMode::Minimize,
+        })
+    }
+
+    pub fn with_mode(mut self, mode: RunMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let toml = toml::to_string_pretty(self).context("Failed to serialize the manifest")?;
+        fs::write(path, toml).context("Failed to write the manifest")
+    }
+
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let toml = fs::read_to_string(path).context("Failed to read the manifest")?;
+        toml::from_str(&toml).context("Failed to parse the manifest")
+    }
+
+    /// Fails if `encoding` isn't the one the manifest was made for.
+    pub fn check_encoding(&self, encoding: &Encoding) -> anyhow::Result<()> {
+        let hash = format!("{:016x}", encoding.fingerprint());
+        if hash != self.encoding_hash {
+            bail!(
+                "The encoding hash {hash} doesn't match the manifest's {}; the terrain, platforms \
+                 or encoder have changed",
+                self.encoding_hash
+            );
+        }
+        Ok(())
+    }
+
+    /// The solver config, with the seed.
+    pub fn solver_config(&self) -> anyhow::Result<SolverConfig> {
+        let solver: SolverConfig = self.solver.parse()?;
+        Ok(match self.seed {
+            Some(seed) => solver.with_seed(seed),
+            None => solver,
+        })
+    }
+
+    /// The limits, with platforms looked up by dimensions in `platform_defs`.
+    pub fn platform_limits(&self, platform_defs: &[PlatformDef]) -> anyhow::Result<PlatformLimits> {
+        let lookup = |dims: &str| {
+            platform_defs
+                .iter()
+                .copied()
+                .find(|def| def.dimensions_str() == dims)
+                .with_context(|| format!("No platform with dimensions `{dims}`"))
+        };
+        let card_limits = self
+            .limits
+            .iter()
+            .map(|(dims, &limit)| Ok((lookup(dims)?, limit)))
+            .collect::<anyhow::Result<_>>()?;
+        let weights = self
+            .weights
+            .iter()
+            .map(|(dims, &weight)| Ok((lookup(dims)?, weight)))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(PlatformLimits::new_with_weights(card_limits, weights, self.weight_limit))
+    }
+}
+
+fn by_dims<T: Copy>(map: &HashMap<PlatformDef, T>) -> BTreeMap<String, T> {
+    map.iter().map(|(def, &value)| (def.dimensions_str(), value)).collect()
+}
+
+#[cfg(all(test, feature = "glucose"))]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+    use crate::{
+        platform::PLATFORMS_DEFAULT,
+        platform_def,
+        solver::{
+            SolverKind,
+            budget::StopHandle,
+            minimize::{MinimizeOptions, minimize},
+        },
+        test_utils::filled_encoding,
+    };
+
+    #[test]
+    fn fingerprint_is_stable() {
+        assert_eq!(filled_encoding(7, 6).fingerprint(), filled_encoding(7, 6).fingerprint());
+        assert_ne!(filled_encoding(7, 6).fingerprint(), filled_encoding(6, 7).fingerprint());
+    }
+
+    #[test_case(RunMode::Minimize)]
+    #[test_case(RunMode::Alternatives { count: 3, min_distance: 2 })]
+    #[test_case(RunMode::Partial)]
+    #[test_case(RunMode::Removal { cost: 2 })]
+    fn manifest_round_trips(mode: RunMode) {
+        let encoding = filled_encoding(6, 6);
+        let limits = PlatformLimits::new_unweighted([(platform_def!(3, 3), 2)].into());
+        let solver_config = SolverConfig::from(SolverKind::Glucose).with_seed(7);
+        let manifest =
+            RunManifest::new(&encoding, &limits, &solver_config).unwrap().with_mode(mode);
+
+        let parsed: RunManifest =
+            toml::from_str(&toml::to_string_pretty(&manifest).unwrap()).unwrap();
+        assert_eq!(parsed, manifest);
+        parsed.check_encoding(&encoding).unwrap();
+        assert_eq!(parsed.solver_config().unwrap(), solver_config);
+        let parsed_limits = parsed.platform_limits(&PLATFORMS_DEFAULT).unwrap();
+        assert_eq!(parsed_limits.card_limits, limits.card_limits);
+        assert_eq!(parsed.mode, mode);
+    }
+
+    #[test]
+    fn old_manifests_are_minimize_runs() {
+        let encoding = filled_encoding(6, 6);
+        let manifest =
+            RunManifest::new(&encoding, &PlatformLimits::default(), &SolverKind::Glucose.into())
+                .unwrap();
+        let mut toml = toml::Table::try_from(&manifest).unwrap();
+        toml.remove("mode");
+
+        let parsed: RunManifest = toml.try_into().unwrap();
+        assert_eq!(parsed.mode, RunMode::Minimize);
+    }
+
+    #[test]
+    fn seeded_runs_repeat() {
+        let encoding = filled_encoding(8, 7);
+        let solver_config = SolverConfig::from(SolverKind::Glucose).with_seed(3);
+        let run = || {
+            minimize(
+                &encoding,
+                PlatformLimits::default(),
+                &solver_config,
+                MinimizeOptions::default(),
+                &StopHandle::default(),
+                |_| {},
+            )
+            .unwrap()
+            .best
+            .unwrap()
+        };
+
+        let (a, b) = (run(), run());
+        assert_eq!(encoding.vars().layout_lits(&a), encoding.vars().layout_lits(&b));
+    }
+}
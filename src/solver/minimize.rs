@@ -0,0 +1,308 @@
+//! Finding layouts with as few platforms as possible.
+
+use std::{path::PathBuf, time::Duration};
+
+use rustsat::{instances::ManageVars, solvers::SolverStats};
+
+use crate::{
+    encoder::{Encoding, PlatformLayout, PlatformLimits},
+    platform_def,
+    solver::{
+        SolverConfig,
+        alternatives::{Alternatives, AlternativesOptions, enumerate_optimal},
+        budget::{BudgetTracker, CallOutcome, SolveBudget, StopHandle, StopReason},
+        certificate::{INSTANCE_FILE, Objective, OptimalityCertificate, PROOF_FILE},
+        progress::ProgressSender,
+    },
+};
+
+/// The result of a [`minimize`] run.
+#[derive(Clone, Debug)]
+pub struct MinimizeOutcome {
+    /// The best layout found, if any
+    pub best: Option<PlatformLayout>,
+    pub stop_reason: StopReason,
+    /// Number of improving solutions found
+    pub n_solutions: usize,
+    pub elapsed: Duration,
+    /// Written to [`MinimizeOptions::proof_dir`] if optimality was proven
+    pub certificate: Option<OptimalityCertificate>,
+    /// Optimal layouts, if [`MinimizeOptions::alternatives`] was set and the
+    /// optimum was reached
+    pub alternatives: Option<Alternatives>,
+}
+
+/// Optional settings of a [`minimize`] run.
+#[derive(Clone, Debug, Default)]
+pub struct MinimizeOptions {
+    pub budget: SolveBudget,
+    /// Receives [`Progress`][crate::solver::progress::Progress] snapshots
+    /// throughout the run
+    pub progress: Option<ProgressSender>,
+    /// Directory to store an [`OptimalityCertificate`] in. Every call is
+    /// proof-logged, so the backend has to support
+    /// [`DynSolver::trace_proof`][crate::solver::DynSolver::trace_proof].
+    pub proof_dir: Option<PathBuf>,
+    /// Once the optimum is proven, look for further layouts that are just as
+    /// good, within the same budget
+    pub alternatives: Option<AlternativesOptions>,
+}
+
+/// An improving solution, passed to the `on_solution` callback of
+/// [`minimize`].
+pub struct Improvement<'a> {
+    pub layout: &'a PlatformLayout,
+    /// Total weight if the limits define weights, otherwise the platform count
+    pub objective: isize,
+    pub solver_stats: SolverStats,
+    pub elapsed: Duration,
+}
+
+/// Repeatedly solves with a tighter bound than the previous solution had,
+/// until the bound can't be met or the budget runs out.
+///
+/// With platform weights in the limits, the weight limit is tightened;
+/// otherwise it's the 1x1 cardinality limit, i.e. the total platform count.
+/// Rather than stepping down from a high initial estimate one by one, each
+/// step starts from the last solution, so the solver is free to skip ahead.
+///
+/// The final, UNSAT call is only made if the bound can still be tightened;
+/// with a [`MinimizeOptions::proof_dir`], its proof and instance end up in
+/// the returned certificate.
+pub fn minimize(
+    encoding: &Encoding,
+    mut limits: PlatformLimits,
+    solver_config: &SolverConfig,
+    options: MinimizeOptions,
+    stop: &StopHandle,
+    mut on_solution: impl FnMut(Improvement),
+) -> anyhow::Result<MinimizeOutcome> {
+    let MinimizeOptions { budget, progress, proof_dir, alternatives } = options;
+    let mut tracker = BudgetTracker::new(budget);
+    if let Some(progress) = progress {
+        tracker = tracker.with_progress(progress);
+    }
+    let mut best = None;
+    let mut best_objective = None;
+    let mut n_solutions = 0;
+    let mut certificate = None;
+
+    let stop_reason = loop {
+        let mut solver = solver_config.create(encoding.vars())?;
+        let (cnf, var_manager) = encoding.with_limits(&limits).into_cnf();
+        // The final instance has to be kept around for the certificate
+        let proof_instance = match &proof_dir {
+            Some(dir) => {
+                solver.trace_proof(&dir.join(PROOF_FILE))?;
+                Some((dir, cnf.clone(), var_manager.n_used()))
+            }
+            None => None,
+        };
+        solver.add_cnf(cnf)?;
+
+        match tracker.solve(solver.as_mut(), stop)? {
+            CallOutcome::Sat => {}
+            CallOutcome::Unsat if best.is_some() => {
+                if let (Some((dir, cnf, n_vars)), Some(optimum)) = (proof_instance, best_objective)
+                {
+                    // Flushes the proof
+                    let signature = solver.signature();
+                    drop(solver);
+                    let proven = OptimalityCertificate {
+                        objective: if limits.weights.is_empty() {
+                            Objective::PlatformCount
+                        } else {
+                            Objective::Weight
+                        },
+                        optimum,
+                        solver: signature.to_string(),
+                        instance: INSTANCE_FILE.into(),
+                        proof: PROOF_FILE.into(),
+                    };
+                    proven.write(dir, &cnf, n_vars)?;
+                    certificate = Some(proven);
+                }
+                break StopReason::Optimal;
+            }
+            CallOutcome::Unsat => break StopReason::Unsat,
+            CallOutcome::Stopped(reason) => break reason,
+        }
+
+        let layout = PlatformLayout::from_assignment(&solver.full_solution()?, encoding.vars());
+        let objective = objective(&layout, &limits);
+        tracker.record_improvement(objective);
+        n_solutions += 1;
+
+        on_solution(Improvement {
+            layout: &layout,
+            objective,
+            solver_stats: solver.stats(),
+            elapsed: tracker.elapsed(),
+        });
+        best = Some(layout);
+        best_objective = Some(objective);
+
+        if !tighten(&mut limits, objective) {
+            break StopReason::Optimal;
+        }
+    };
+
+    let alternatives = match (&best, best_objective, alternatives) {
+        (Some(best), Some(optimum), Some(options)) if stop_reason == StopReason::Optimal => {
+            limit_objective(&mut limits, optimum);
+            Some(enumerate_optimal(
+                encoding,
+                &limits,
+                solver_config,
+                best.clone(),
+                options,
+                &mut tracker,
+                stop,
+                |_| {},
+            )?)
+        }
+        _ => None,
+    };
+
+    Ok(MinimizeOutcome {
+        best,
+        stop_reason,
+        n_solutions,
+        elapsed: tracker.elapsed(),
+        certificate,
+        alternatives,
+    })
+}
+
+fn objective(layout: &PlatformLayout, limits: &PlatformLimits) -> isize {
+    if limits.weights.is_empty() {
+        layout.platform_count() as isize
+    } else {
+        layout.total_weight(&limits.weights)
+    }
+}
+
+/// Requires solutions better than `objective`. Returns false if nothing better
+/// is possible.
+fn tighten(limits: &mut PlatformLimits, objective: isize) -> bool {
+    if objective <= 0 {
+        return false;
+    }
+    limit_objective(limits, objective - 1);
+    true
+}
+
+/// Requires solutions with an objective of at most `bound`: the total weight
+/// if weights are set, otherwise the platform count.
+pub fn limit_objective(limits: &mut PlatformLimits, bound: isize) {
+    if limits.weights.is_empty() {
+        limits.card_limits.insert(platform_def!(1, 1), bound.max(0) as usize);
+    } else {
+        limits.weight_limit = Some(bound);
+    }
+}
+
+#[cfg(all(test, feature = "glucose"))]
+mod tests {
+    use super::*;
+    use crate::{
+        brute_force,
+        platform::PLATFORMS_DEFAULT,
+        solver::SolverKind,
+        test_utils::{filled_encoding, filled_world},
+    };
+
+    #[test]
+    fn minimize_matches_brute_force() {
+        let world = filled_world(8, 8);
+        let encoding = Encoding::encode(&PLATFORMS_DEFAULT, world.grid());
+
+        let mut objectives = vec![];
+        let outcome = minimize(
+            &encoding,
+            PlatformLimits::default(),
+            &SolverKind::Glucose.into(),
+            MinimizeOptions::default(),
+            &StopHandle::default(),
+            |improvement| objectives.push(improvement.objective),
+        )
+        .unwrap();
+
+        let expected = brute_force::solve_minimum(&world, &PLATFORMS_DEFAULT).unwrap().unwrap();
+        assert_eq!(outcome.stop_reason, StopReason::Optimal);
+        assert_eq!(outcome.best.unwrap().platform_count(), expected.platform_count());
+        assert!(objectives.is_sorted_by(|a, b| a > b));
+    }
+
+    #[test]
+    fn progress_is_published() {
+        let encoding = filled_encoding(8, 8);
+
+        let (progress, rx) = ProgressSender::channel(Duration::from_millis(10));
+        let outcome = minimize(
+            &encoding,
+            PlatformLimits::default(),
+            &SolverKind::Glucose.into(),
+            MinimizeOptions { progress: Some(progress), ..Default::default() },
+            &StopHandle::default(),
+            |_| {},
+        )
+        .unwrap();
+
+        let last = rx.try_iter().last().expect("expected progress updates");
+        assert_eq!(last.n_solutions, outcome.n_solutions);
+        assert!(last.n_calls > outcome.n_solutions);
+        assert!(last.conflicts.is_some());
+        assert!(last.search_stats.is_some_and(|search| search.propagations > 0));
+    }
+
+    #[test]
+    fn stop_keeps_best() {
+        let encoding = filled_encoding(8, 8);
+
+        let stop = StopHandle::default();
+        let outcome = minimize(
+            &encoding,
+            PlatformLimits::default(),
+            &SolverKind::Glucose.into(),
+            MinimizeOptions::default(),
+            &stop,
+            |_| stop.stop(),
+        )
+        .unwrap();
+
+        assert_eq!(outcome.stop_reason, StopReason::Interrupted);
+        assert_eq!(outcome.n_solutions, 1);
+        assert!(outcome.best.is_some());
+    }
+
+    #[cfg(feature = "cadical")]
+    #[test]
+    fn optimum_is_certified() {
+        use crate::solver::certificate::CERTIFICATE_FILE;
+
+        let encoding = filled_encoding(6, 6);
+        let dir = std::env::temp_dir()
+            .join(format!("platform-cruncher-certificate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let outcome = minimize(
+            &encoding,
+            PlatformLimits::default(),
+            &SolverKind::Cadical.into(),
+            MinimizeOptions { proof_dir: Some(dir.clone()), ..Default::default() },
+            &StopHandle::default(),
+            |_| {},
+        )
+        .unwrap();
+
+        let certificate = outcome.certificate.expect("expected a certificate");
+        assert_eq!(certificate.objective, Objective::PlatformCount);
+        assert_eq!(certificate.optimum, outcome.best.unwrap().platform_count() as isize);
+        assert_eq!(OptimalityCertificate::read(&dir).unwrap(), certificate);
+        assert!(dir.join(CERTIFICATE_FILE).is_file());
+        assert!(dir.join(INSTANCE_FILE).is_file());
+        assert!(dir.join(PROOF_FILE).is_file());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,189 @@
+//! Supporting as much terrain as possible with limited platforms.
+//!
+//! Rather than requiring every terrain tile to be supported, the requirement
+//! becomes soft (see [`Encoding::without_support_requirement`]), and the total
+//! weight of the supported tiles is maximized under the hard limits.
+
+use std::{collections::HashMap, time::Duration};
+
+use rustsat::{
+    solvers::SolverStats,
+    types::{Lit, TernaryVal, constraints::PbConstraint},
+};
+
+use crate::{
+    encoder::{Encoding, PlatformLayout, PlatformLimits},
+    math::Point,
+    solver::{
+        SolverConfig,
+        budget::{BudgetTracker, CallOutcome, SolveBudget, StopHandle, StopReason},
+        progress::ProgressSender,
+    },
+};
+
+/// The result of a [`maximize_support`] run.
+#[derive(Clone, Debug)]
+pub struct SupportOutcome {
+    /// The best layout found, if any
+    pub best: Option<PlatformLayout>,
+    /// Weight of the terrain the best layout supports
+    pub supported: isize,
+    /// Weight of all terrain
+    pub total: isize,
+    pub stop_reason: StopReason,
+    /// Number of improving solutions found
+    pub n_solutions: usize,
+    pub elapsed: Duration,
+}
+
+/// An improving solution, passed to the `on_solution` callback of
+/// [`maximize_support`].
+pub struct SupportImprovement<'a> {
+    pub layout: &'a PlatformLayout,
+    /// Weight of the supported terrain
+    pub supported: isize,
+    pub solver_stats: SolverStats,
+    pub elapsed: Duration,
+}
+
+/// Repeatedly solves for more supported terrain than the previous solution
+/// had, until that's impossible under `limits` or the budget runs out.
+///
+/// Tiles are weighted by `tile_weights`, or 1 if missing. Tiles with a weight
+/// of 0 or less are left out of the objective. [`StopReason::Optimal`] also
+/// covers all terrain being supported; [`StopReason::Unsat`] means the limits
+/// can't be met even without supporting anything.
+#[allow(clippy::too_many_arguments)]
+pub fn maximize_support(
+    encoding: &Encoding,
+    limits: &PlatformLimits,
+    tile_weights: &HashMap<Point, isize>,
+    solver_config: &SolverConfig,
+    budget: SolveBudget,
+    stop: &StopHandle,
+    progress: Option<ProgressSender>,
+    mut on_solution: impl FnMut(SupportImprovement),
+) -> anyhow::Result<SupportOutcome> {
+    let mut tracker = BudgetTracker::new(budget);
+    if let Some(progress) = progress {
+        tracker = tracker.with_progress(progress);
+    }
+
+    let (mut base, required) = encoding.without_support_requirement();
+    encoding.limit_constraints(limits, base.var_manager_mut()).add_to(&mut base);
+    let weighted: Vec<(Lit, isize)> = required
+        .into_iter()
+        .map(|(point, lit)| (lit, tile_weights.get(&point).copied().unwrap_or(1)))
+        .filter(|&(_, weight)| weight > 0)
+        .collect();
+    let total = weighted.iter().map(|&(_, weight)| weight).sum();
+
+    let mut best = None;
+    let mut supported = 0;
+    let mut n_solutions = 0;
+
+    let stop_reason = loop {
+        let mut instance = base.clone();
+        if best.is_some() {
+            instance.add_pb_constr(PbConstraint::new_lb(weighted.iter().copied(), supported + 1));
+        }
+        let mut solver = solver_config.create(encoding.vars())?;
+        solver.add_cnf(instance.into_cnf().0)?;
+
+        match tracker.solve(solver.as_mut(), stop)? {
+            CallOutcome::Sat => {}
+            CallOutcome::Unsat if best.is_some() => break StopReason::Optimal,
+            CallOutcome::Unsat => break StopReason::Unsat,
+            CallOutcome::Stopped(reason) => break reason,
+        }
+
+        let assignment = solver.full_solution()?;
+        let layout = PlatformLayout::from_assignment(&assignment, encoding.vars());
+        supported = weighted
+            .iter()
+            .filter(|&&(lit, _)| assignment.lit_value(lit) == TernaryVal::True)
+            .map(|&(_, weight)| weight)
+            .sum();
+        tracker.record_improvement(supported);
+        n_solutions += 1;
+
+        on_solution(SupportImprovement {
+            layout: &layout,
+            supported,
+            solver_stats: solver.stats(),
+            elapsed: tracker.elapsed(),
+        });
+        best = Some(layout);
+
+        if supported >= total {
+            break StopReason::Optimal;
+        }
+    };
+
+    Ok(SupportOutcome {
+        best,
+        supported,
+        total,
+        stop_reason,
+        n_solutions,
+        elapsed: tracker.elapsed(),
+    })
+}
+
+#[cfg(all(test, feature = "glucose"))]
+mod tests {
+    use super::*;
+    use crate::{
+        platform::PLATFORMS_DEFAULT,
+        platform_def,
+        solver::SolverKind,
+        test_utils::filled_world,
+        world::World,
+    };
+
+    fn run(world: &World, limits: &PlatformLimits) -> SupportOutcome {
+        let encoding = Encoding::encode(&PLATFORMS_DEFAULT, world.grid());
+        maximize_support(
+            &encoding,
+            limits,
+            &HashMap::new(),
+            &SolverKind::Glucose.into(),
+            SolveBudget::default(),
+            &StopHandle::default(),
+            None,
+            |_| {},
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn single_platform_supports_part_of_the_terrain() {
+        let world = filled_world(12, 12);
+        let limits = PlatformLimits::new_unweighted([(platform_def!(1, 1), 1)].into());
+        let outcome = run(&world, &limits);
+
+        assert_eq!(outcome.stop_reason, StopReason::Optimal);
+        assert!(outcome.supported < outcome.total);
+
+        let best = outcome.best.unwrap();
+        assert_eq!(best.platform_count(), 1);
+        let validation = best.validate(&world);
+        assert!(validation.without_unsupported_terrain().is_valid());
+        let n_terrain = world.grid().iter().filter(|&&terrain| terrain).count();
+        assert_eq!(
+            n_terrain - validation.unsupported_terrain.len(),
+            outcome.supported as usize,
+            "the objective should match the validated support"
+        );
+    }
+
+    #[test]
+    fn enough_platforms_support_everything() {
+        let world = filled_world(6, 6);
+        let outcome = run(&world, &PlatformLimits::default());
+
+        assert_eq!(outcome.stop_reason, StopReason::Optimal);
+        assert_eq!(outcome.supported, outcome.total);
+        assert!(outcome.best.unwrap().validate(&world).is_valid());
+    }
+}
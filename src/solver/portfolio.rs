@@ -0,0 +1,391 @@
+//! Racing several solvers on the same instance.
+//!
+//! [`PortfolioSolver`] is a [`DynSolver`] itself, so it can be used anywhere a
+//! single backend can. Every [`DynSolver::solve`] call runs all members in
+//! parallel, takes the first definite answer, and interrupts the rest.
+//!
+//! Optimisation loops (like the REPL's `solve`) start a new race with a
+//! tightened bound after each solution, so an improvement found by any member
+//! applies to all of them in the next round.
+//!
+//! A portfolio can still be solved more than once, e.g. in the slices of a
+//! conflict budget (see [`budget`]), and some backends (e.g. Glucose) keep an
+//! interrupt until they're dropped. Members are therefore only interrupted
+//! while their solve call runs, never after they've answered.
+//!
+//! [`budget`]: crate::solver::budget
+
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+        mpsc::RecvTimeoutError,
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Context, bail};
+use rustsat::{
+    instances::Cnf,
+    solvers::{InterruptSolver, SolverResult, SolverStats},
+    types::Assignment,
+};
+
+use crate::{
+    encoder::EncodingVars,
+    solver::{DynSolver, SearchStats, SolverConfig, UnknownSolver, seeded::shuffled_cnf},
+};
+
+/// How often members that were told to stop get interrupted again, in case
+/// the first interrupt came before their solve call started. Only members
+/// that haven't answered yet are interrupted again.
+const INTERRUPT_RETRY: Duration = Duration::from_millis(50);
+
+/// A solver in a portfolio. Members with a seed get the instance with clauses
+/// and literals shuffled by it, which is enough to send CDCL solvers down
+/// different paths.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct PortfolioMember {
+    pub solver: SolverConfig,
+    pub seed: Option<u64>,
+}
+
+impl Display for PortfolioMember {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.solver)?;
+        if let Some(seed) = self.seed {
+            write!(f, "@{seed}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PortfolioMember {
+    type Err = UnknownSolver;
+
+    /// Parses `<solver>` or `<solver>@<seed>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (solver, seed) = match s.rsplit_once('@') {
+            Some((solver, seed)) => {
+                (solver, Some(seed.trim().parse().map_err(|_| UnknownSolver(s.to_owned()))?))
+            }
+            None => (s, None),
+        };
+        Ok(PortfolioMember { solver: solver.parse()?, seed })
+    }
+}
+
+/// Parses a comma-separated list of [`PortfolioMember`]s.
+pub fn parse_members(s: &str) -> Result<Vec<PortfolioMember>, UnknownSolver> {
+    s.split(',').map(str::parse).collect()
+}
+
+struct RunningMember {
+    solver: Box<dyn DynSolver>,
+    seed: Option<u64>,
+}
+
+/// Interrupts a member, but only while its solve call runs.
+struct MemberInterrupter {
+    interrupter: Box<dyn InterruptSolver + Send>,
+    /// Set right before the member starts solving, and cleared as soon as it
+    /// returns
+    solving: Mutex<bool>,
+}
+
+impl MemberInterrupter {
+    fn interrupt_if_solving(&self) {
+        let solving = self.solving.lock().unwrap();
+        if *solving {
+            self.interrupter.interrupt();
+        }
+    }
+
+    fn set_solving(&self, solving: bool) {
+        *self.solving.lock().unwrap() = solving;
+    }
+}
+
+struct Interrupters {
+    members: Vec<MemberInterrupter>,
+    /// Whether the whole portfolio was interrupted during the current call
+    interrupted: AtomicBool,
+}
+
+pub struct PortfolioSolver {
+    members: Vec<RunningMember>,
+    interrupters: Arc<Interrupters>,
+    /// Index of the member that answered the last solve call
+    winner: Option<usize>,
+}
+
+impl PortfolioSolver {
+    pub fn new(members: &[PortfolioMember], vars: &EncodingVars) -> anyhow::Result<Self> {
+        if members.is_empty() {
+            bail!("A portfolio needs at least one solver");
+        }
+
+        let mut running = members
+            .iter()
+            .map(|member| {
+                let solver = member
+                    .solver
+                    .create(vars)
+                    .with_context(|| format!("Failed to create portfolio member `{member}`"))?;
+                Ok(RunningMember { solver, seed: member.seed })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let members = running
+            .iter_mut()
+            .map(|member| MemberInterrupter {
+                interrupter: member.solver.interrupter(),
+                solving: Mutex::new(false),
+            })
+            .collect();
+        let interrupters = Interrupters { members, interrupted: AtomicBool::new(false) };
+
+        Ok(Self { members: running, interrupters: Arc::new(interrupters), winner: None })
+    }
+
+    fn winner(&self) -> anyhow::Result<&dyn DynSolver> {
+        let winner = self.winner.context("The portfolio hasn't solved anything yet")?;
+        Ok(self.members[winner].solver.as_ref())
+    }
+}
+
+impl DynSolver for PortfolioSolver {
+    fn add_cnf(&mut self, cnf: Cnf) -> anyhow::Result<()> {
+        for member in self.members.iter_mut() {
+            let cnf = match member.seed {
+                Some(seed) => shuffled_cnf(&cnf, seed),
+                None => cnf.clone(),
+            };
+            member.solver.add_cnf(cnf)?;
+        }
+        Ok(())
+    }
+
+    fn solve(&mut self) -> anyhow::Result<SolverResult> {
+        self.winner = None;
+        let interrupters = self.interrupters.as_ref();
+
+        let result = thread::scope(|scope| {
+            let (tx, rx) = mpsc::channel();
+            for (i, (member, interrupter)) in
+                self.members.iter_mut().zip(interrupters.members.iter()).enumerate()
+            {
+                let tx = tx.clone();
+                interrupter.set_solving(true);
+                scope.spawn(move || {
+                    let result = member.solver.solve();
+                    interrupter.set_solving(false);
+                    // The receiver only goes away once every member is done
+                    _ = tx.send((i, result));
+                });
+            }
+            drop(tx);
+            // Interrupted before the members started
+            if interrupters.interrupted.load(Ordering::Acquire) {
+                interrupters.interrupt_solving();
+            }
+
+            let mut answer: Option<(usize, SolverResult)> = None;
+            let mut errors = vec![];
+            loop {
+                let (i, result) = match rx.recv_timeout(INTERRUPT_RETRY) {
+                    Ok(received) => received,
+                    Err(RecvTimeoutError::Timeout) => {
+                        if answer.is_some() || interrupters.interrupted.load(Ordering::Acquire) {
+                            interrupters.interrupt_solving();
+                        }
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+
+                match result {
+                    Ok(result @ (SolverResult::Sat | SolverResult::Unsat)) if answer.is_none() => {
+                        answer = Some((i, result));
+                        interrupters.interrupt_solving();
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        errors.push(err.context(format!("Portfolio member {i}")));
+                    }
+                }
+            }
+
+            if let Some((i, result)) = answer {
+                self.winner = Some(i);
+                return Ok(result);
+            }
+            if errors.len() == interrupters.members.len() {
+                // Every member failed
+                return Err(errors.swap_remove(0));
+            }
+            Ok(SolverResult::Interrupted)
+        });
+        // Cleared as the call returns, so an interrupt only carries over into
+        // the next call if it came in between the two
+        interrupters.interrupted.store(false, Ordering::Release);
+        result
+    }
+
+    fn full_solution(&self) -> anyhow::Result<Assignment> {
+        self.winner()?.full_solution()
+    }
+
+    /// The stats of the member that answered the last call, as the others
+    /// raced on the same instance. Empty before the first answer.
+    fn stats(&self) -> SolverStats {
+        self.winner().map(|solver| solver.stats()).unwrap_or_default()
+    }
+
+    fn interrupter(&mut self) -> Box<dyn InterruptSolver + Send> {
+        Box::new(PortfolioInterrupter(self.interrupters.clone()))
+    }
+
+    fn signature(&self) -> &'static str {
+        "portfolio"
+    }
+
+    fn limit_conflicts(&mut self, limit: Option<u32>) -> anyhow::Result<()> {
+        self.members.iter_mut().try_for_each(|member| member.solver.limit_conflicts(limit))
+    }
+
+    fn limit_propagations(&mut self, limit: Option<u32>) -> anyhow::Result<()> {
+        self.members.iter_mut().try_for_each(|member| member.solver.limit_propagations(limit))
+    }
+
+    /// Unlike [`Self::stats`], summed over all members, as they all spend the
+    /// time. `None` unless every member keeps them.
+    fn search_stats(&self) -> Option<SearchStats> {
+        self.members.iter().map(|member| member.solver.search_stats()).sum()
+    }
+}
+
+impl Interrupters {
+    fn interrupt_solving(&self) {
+        for member in self.members.iter() {
+            member.interrupt_if_solving();
+        }
+    }
+}
+
+struct PortfolioInterrupter(Arc<Interrupters>);
+
+impl InterruptSolver for PortfolioInterrupter {
+    fn interrupt(&self) {
+        // Repeated by the running call, for members that haven't started yet
+        self.0.interrupted.store(true, Ordering::Release);
+        self.0.interrupt_solving();
+    }
+}
+
+#[cfg(all(test, feature = "glucose"))]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::{
+        encoder::{Encoding, PlatformLayout, PlatformLimits},
+        platform::PLATFORMS_DEFAULT,
+        platform_def,
+        solver::SolverKind,
+        test_utils::{filled_encoding, filled_world},
+    };
+
+    fn glucose_members(seeds: impl IntoIterator<Item = Option<u64>>) -> Vec<PortfolioMember> {
+        seeds
+            .into_iter()
+            .map(|seed| PortfolioMember { solver: SolverKind::Glucose.into(), seed })
+            .collect()
+    }
+
+    #[test]
+    fn parse_portfolio() {
+        let members = parse_members("glucose,glucose@3, cadical").unwrap();
+        assert_eq!(
+            members,
+            vec![
+                PortfolioMember { solver: SolverKind::Glucose.into(), seed: None },
+                PortfolioMember { solver: SolverKind::Glucose.into(), seed: Some(3) },
+                PortfolioMember { solver: SolverKind::Cadical.into(), seed: None },
+            ]
+        );
+        assert!(parse_members("glucose@x").is_err());
+    }
+
+    #[test]
+    fn race_finds_valid_layout() {
+        let world = filled_world(7, 6);
+        let encoding = Encoding::encode(&PLATFORMS_DEFAULT, world.grid());
+        let limits = PlatformLimits::default();
+        let (cnf, _) = encoding.with_limits(&limits).into_cnf();
+
+        let mut portfolio =
+            PortfolioSolver::new(&glucose_members([None, Some(1), Some(2)]), encoding.vars())
+                .unwrap();
+        portfolio.add_cnf(cnf).unwrap();
+        assert_eq!(portfolio.solve().unwrap(), SolverResult::Sat);
+
+        let layout =
+            PlatformLayout::from_assignment(&portfolio.full_solution().unwrap(), encoding.vars());
+        assert!(layout.validate(&world).is_valid());
+    }
+
+    #[test]
+    fn race_agrees_on_unsat() {
+        let encoding = filled_encoding(7, 6);
+        let mut limits = PlatformLimits::default();
+        limits.card_limits.insert(platform_def!(1, 1), 0);
+        let (cnf, _) = encoding.with_limits(&limits).into_cnf();
+
+        let mut portfolio =
+            PortfolioSolver::new(&glucose_members([Some(1), Some(2)]), encoding.vars()).unwrap();
+        portfolio.add_cnf(cnf).unwrap();
+        assert_eq!(portfolio.solve().unwrap(), SolverResult::Unsat);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn winner_interrupts_others() {
+        use crate::solver::external::ExternalSolver;
+
+        let script = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fake_solver.sh");
+        let hanging = SolverConfig::External(ExternalSolver::new("sh").with_args([script, "hang"]));
+        let mut members = glucose_members([None]);
+        members.push(PortfolioMember { solver: hanging, seed: None });
+
+        let encoding = filled_encoding(7, 6);
+        let (cnf, _) = encoding.with_limits(&PlatformLimits::default()).into_cnf();
+
+        let start = Instant::now();
+        let mut portfolio = PortfolioSolver::new(&members, encoding.vars()).unwrap();
+        portfolio.add_cnf(cnf).unwrap();
+        assert_eq!(portfolio.solve().unwrap(), SolverResult::Sat);
+        assert!(start.elapsed() < Duration::from_secs(30));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn interrupt_before_the_call_stops_it() {
+        use crate::solver::external::ExternalSolver;
+
+        let script = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fake_solver.sh");
+        let hanging = SolverConfig::External(ExternalSolver::new("sh").with_args([script, "hang"]));
+        let members = [PortfolioMember { solver: hanging, seed: None }];
+
+        let encoding = filled_encoding(7, 6);
+        let (cnf, _) = encoding.with_limits(&PlatformLimits::default()).into_cnf();
+
+        let mut portfolio = PortfolioSolver::new(&members, encoding.vars()).unwrap();
+        portfolio.add_cnf(cnf).unwrap();
+        portfolio.interrupter().interrupt();
+        assert_eq!(portfolio.solve().unwrap(), SolverResult::Interrupted);
+    }
+}
@@ -0,0 +1,75 @@
+//! Live progress of a solver run.
+//!
+//! The rustsat solver APIs only report [`SolverStats`] and the search counters
+//! (see [`SearchStats`]) between solve calls. So while solving, backends that
+//! support conflict limits are run in slices of [`PROGRESS_SLICE`] conflicts,
+//! and the counters are updated between slices. A ticker thread publishes the
+//! latest [`Progress`] at a fixed interval, so the elapsed time keeps moving
+//! even if a slice takes long.
+
+use std::{
+    fmt::{Display, Formatter},
+    sync::mpsc,
+    time::Duration,
+};
+
+use rustsat::solvers::SolverStats;
+
+use crate::solver::SearchStats;
+
+/// Conflicts per slice when solving in slices only for progress reporting.
+pub const PROGRESS_SLICE: u32 = 1_000;
+
+/// A snapshot of a solver run.
+#[derive(Clone, Debug, Default)]
+pub struct Progress {
+    pub elapsed: Duration,
+    /// Solver calls started so far
+    pub n_calls: usize,
+    /// Conflicts so far, as charged to the budget (see
+    /// [`CONFLICT_SLICE`][crate::solver::budget::CONFLICT_SLICE]). `None` if
+    /// the backend can't be run in slices.
+    pub conflicts: Option<u64>,
+    /// Improving solutions found so far
+    pub n_solutions: usize,
+    pub best_objective: Option<isize>,
+    /// Stats of the current solver call, as of the last slice
+    pub solver_stats: SolverStats,
+    /// The current solver's own search counters, as of the last slice. `None`
+    /// if the backend doesn't keep them.
+    pub search_stats: Option<SearchStats>,
+}
+
+impl Display for Progress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.1?} | call {}", self.elapsed, self.n_calls)?;
+        if let Some(search) = self.search_stats {
+            write!(
+                f,
+                " | {} conflicts, {} decisions, {} propagations",
+                search.conflicts, search.decisions, search.propagations
+            )?;
+        } else if let Some(conflicts) = self.conflicts {
+            write!(f, " | {conflicts} conflicts")?;
+        }
+        match self.best_objective {
+            Some(best) => write!(f, " | best {best} ({} solutions)", self.n_solutions),
+            None => write!(f, " | no solution yet"),
+        }
+    }
+}
+
+/// Where and how often to publish [`Progress`].
+#[derive(Clone, Debug)]
+pub struct ProgressSender {
+    pub tx: mpsc::Sender<Progress>,
+    pub interval: Duration,
+}
+
+impl ProgressSender {
+    /// Creates a sender and the matching receiver.
+    pub fn channel(interval: Duration) -> (Self, mpsc::Receiver<Progress>) {
+        let (tx, rx) = mpsc::channel();
+        (Self { tx, interval }, rx)
+    }
+}
@@ -0,0 +1,228 @@
+//! Trading platforms for digging out terrain.
+//!
+//! In-game, an overhang can also just be removed rather than supported. Here,
+//! every terrain tile is either supported or removed at a fixed cost, and the
+//! sum of platform and removal costs is minimized.
+//!
+//! A tile counts as removed when its own support var is false (see
+//! [`Encoding::without_support_requirement`]). Its deeper support layers are
+//! then forced to be false too, so it doesn't pass support on to its
+//! neighbors.
+
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::bail;
+use rustsat::{
+    solvers::SolverStats,
+    types::{TernaryVal, constraints::PbConstraint},
+};
+
+use crate::{
+    encoder::{Encoding, PlatformLayout, PlatformLimits},
+    platform_def,
+    solver::{
+        SolverConfig,
+        budget::{BudgetTracker, CallOutcome, SolveBudget, StopHandle, StopReason},
+        progress::ProgressSender,
+    },
+};
+
+/// The result of a [`minimize_with_removal`] run.
+#[derive(Clone, Debug)]
+pub struct RemovalOutcome {
+    /// The best layout found, if any, with the tiles to remove
+    pub best: Option<PlatformLayout>,
+    /// Total cost of the best layout
+    pub cost: Option<isize>,
+    pub stop_reason: StopReason,
+    /// Number of improving solutions found
+    pub n_solutions: usize,
+    pub elapsed: Duration,
+}
+
+/// An improving solution, passed to the `on_solution` callback of
+/// [`minimize_with_removal`].
+pub struct RemovalImprovement<'a> {
+    pub layout: &'a PlatformLayout,
+    /// Platform cost plus removal cost
+    pub cost: isize,
+    pub solver_stats: SolverStats,
+    pub elapsed: Duration,
+}
+
+/// Repeatedly solves for a cheaper layout than the previous one, until that's
+/// impossible or the budget runs out.
+///
+/// Platforms cost their weight if `limits` has weights, otherwise 1 each.
+/// Every removed terrain tile costs `removal_cost`, which can't be negative as
+/// that would reward digging out terrain. All limits still apply.
+#[allow(clippy::too_many_arguments)]
+pub fn minimize_with_removal(
+    encoding: &Encoding,
+    limits: &PlatformLimits,
+    removal_cost: isize,
+    solver_config: &SolverConfig,
+    budget: SolveBudget,
+    stop: &StopHandle,
+    progress: Option<ProgressSender>,
+    mut on_solution: impl FnMut(RemovalImprovement),
+) -> anyhow::Result<RemovalOutcome> {
+    if removal_cost < 0 {
+        bail!("The removal cost can't be negative, got {removal_cost}");
+    }
+    let mut tracker = BudgetTracker::new(budget);
+    if let Some(progress) = progress {
+        tracker = tracker.with_progress(progress);
+    }
+
+    let (mut base, required) = encoding.without_support_requirement();
+    encoding.limit_constraints(limits, base.var_manager_mut()).add_to(&mut base);
+
+    // Only the implications of this are added, as there's no limit
+    let platform_costs = if limits.weights.is_empty() {
+        // The 1x1 vars count all platforms
+        [(platform_def!(1, 1), 1)].into()
+    } else {
+        limits.weights.clone()
+    };
+    let cost_constraints = encoding.limit_constraints(
+        &PlatformLimits::new_with_weights(HashMap::new(), platform_costs, None),
+        base.var_manager_mut(),
+    );
+    cost_constraints.add_to(&mut base);
+
+    let mut costs = cost_constraints.weighted;
+    for &(point, supported) in required.iter() {
+        let layers = encoding
+            .vars()
+            .at(point)
+            .and_then(|tile_vars| tile_vars.terrain())
+            .expect("required tiles have terrain vars");
+        for layer in layers.iter().skip(1) {
+            base.add_lit_impl_lit(layer.pos_lit(), supported);
+        }
+        costs.push((!supported, removal_cost));
+    }
+
+    let mut best = None;
+    let mut best_cost = None;
+    let mut n_solutions = 0;
+
+    let stop_reason = loop {
+        let mut instance = base.clone();
+        if let Some(cost) = best_cost {
+            instance.add_pb_constr(PbConstraint::new_ub(costs.iter().copied(), cost - 1));
+        }
+        let mut solver = solver_config.create(encoding.vars())?;
+        solver.add_cnf(instance.into_cnf().0)?;
+
+        match tracker.solve(solver.as_mut(), stop)? {
+            CallOutcome::Sat => {}
+            CallOutcome::Unsat if best.is_some() => break StopReason::Optimal,
+            CallOutcome::Unsat => break StopReason::Unsat,
+            CallOutcome::Stopped(reason) => break reason,
+        }
+
+        let assignment = solver.full_solution()?;
+        let removed = required
+            .iter()
+            .filter(|&&(_, supported)| assignment.lit_value(supported) != TernaryVal::True)
+            .map(|&(point, _)| point);
+        let layout = PlatformLayout::from_assignment(&assignment, encoding.vars())
+            .with_removed_tiles(removed);
+        let cost = costs
+            .iter()
+            .filter(|&&(lit, _)| assignment.lit_value(lit) == TernaryVal::True)
+            .map(|&(_, cost)| cost)
+            .sum();
+        tracker.record_improvement(cost);
+        n_solutions += 1;
+
+        on_solution(RemovalImprovement {
+            layout: &layout,
+            cost,
+            solver_stats: solver.stats(),
+            elapsed: tracker.elapsed(),
+        });
+        best = Some(layout);
+        best_cost = Some(cost);
+
+        if cost <= 0 {
+            break StopReason::Optimal;
+        }
+    };
+
+    Ok(RemovalOutcome {
+        best,
+        cost: best_cost,
+        stop_reason,
+        n_solutions,
+        elapsed: tracker.elapsed(),
+    })
+}
+
+#[cfg(all(test, feature = "glucose"))]
+mod tests {
+    use super::*;
+    use crate::{
+        brute_force,
+        math::{Dimensions, Grid, Point},
+        platform::PLATFORMS_DEFAULT,
+        solver::SolverKind,
+        test_utils::filled_world,
+        world::{World, WorldGrid},
+    };
+
+    fn run(world: &World, limits: &PlatformLimits, removal_cost: isize) -> RemovalOutcome {
+        let encoding = Encoding::encode(&PLATFORMS_DEFAULT, world.grid());
+        minimize_with_removal(
+            &encoding,
+            limits,
+            removal_cost,
+            &SolverKind::Glucose.into(),
+            SolveBudget::default(),
+            &StopHandle::default(),
+            None,
+            |_| {},
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn expensive_removal_supports_everything() {
+        let world = filled_world(8, 8);
+        let outcome = run(&world, &PlatformLimits::default(), 1000);
+
+        let best = outcome.best.unwrap();
+        let expected = brute_force::solve_minimum(&world, &PLATFORMS_DEFAULT).unwrap().unwrap();
+        assert_eq!(outcome.stop_reason, StopReason::Optimal);
+        assert!(best.removed_tiles().is_empty());
+        assert_eq!(best.platform_count(), expected.platform_count());
+        assert!(best.validate(&world).is_valid());
+    }
+
+    #[test]
+    fn cheap_removal_digs_out_isolated_tiles() {
+        // A block, and a single tile far away from it
+        let mut grid = Grid::new_fill(Dimensions::new(16, 4), false);
+        for p in Dimensions::new(6, 4).iter_within() {
+            grid.set(p, true).unwrap();
+        }
+        grid.set(Point::new(15, 3), true).unwrap();
+        let world = World::new(WorldGrid(grid));
+
+        // Removing the single tile is cheaper than an extra platform, but
+        // removing the block isn't
+        let limits = PlatformLimits::new_with_weights(
+            HashMap::new(),
+            [(platform_def!(1, 1), 3)].into(),
+            None,
+        );
+        let outcome = run(&world, &limits, 1);
+        let best = outcome.best.unwrap();
+        assert_eq!(outcome.stop_reason, StopReason::Optimal);
+        assert_eq!(best.removed_tiles().iter().collect::<Vec<_>>(), [&Point::new(15, 3)]);
+        assert_eq!(outcome.cost, Some(3 * best.platform_count() as isize + 1));
+        assert!(best.validate(&world).is_valid());
+    }
+}
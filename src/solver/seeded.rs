@@ -0,0 +1,116 @@
+//! Seeded solver runs.
+//!
+//! CDCL solvers are deterministic for a given instance, but small changes to
+//! it (like the order of clauses) can send them down very different paths. A
+//! seed picks one of those paths reproducibly: [`SeededSolver`] shuffles
+//! clause and literal order with it before handing the instance on.
+
+use std::path::Path;
+
+use rustsat::{
+    instances::Cnf,
+    solvers::{InterruptSolver, SolverResult, SolverStats},
+    types::{Assignment, Clause, Lit},
+};
+
+use crate::solver::{DynSolver, SearchStats};
+
+/// Wraps a solver, shuffling every added CNF by a fixed seed.
+pub struct SeededSolver {
+    inner: Box<dyn DynSolver>,
+    seed: u64,
+}
+
+impl SeededSolver {
+    pub fn new(inner: Box<dyn DynSolver>, seed: u64) -> Self {
+        Self { inner, seed }
+    }
+}
+
+impl DynSolver for SeededSolver {
+    fn add_cnf(&mut self, cnf: Cnf) -> anyhow::Result<()> {
+        self.inner.add_cnf(shuffled_cnf(&cnf, self.seed))
+    }
+
+    fn solve(&mut self) -> anyhow::Result<SolverResult> {
+        self.inner.solve()
+    }
+
+    fn full_solution(&self) -> anyhow::Result<Assignment> {
+        self.inner.full_solution()
+    }
+
+    fn stats(&self) -> SolverStats {
+        self.inner.stats()
+    }
+
+    fn interrupter(&mut self) -> Box<dyn InterruptSolver + Send> {
+        self.inner.interrupter()
+    }
+
+    fn signature(&self) -> &'static str {
+        self.inner.signature()
+    }
+
+    fn limit_conflicts(&mut self, limit: Option<u32>) -> anyhow::Result<()> {
+        self.inner.limit_conflicts(limit)
+    }
+
+    fn limit_propagations(&mut self, limit: Option<u32>) -> anyhow::Result<()> {
+        self.inner.limit_propagations(limit)
+    }
+
+    fn trace_proof(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.inner.trace_proof(path)
+    }
+
+    fn solve_assumps(&mut self, assumps: &[Lit]) -> anyhow::Result<SolverResult> {
+        self.inner.solve_assumps(assumps)
+    }
+
+    fn core(&mut self) -> anyhow::Result<Vec<Lit>> {
+        self.inner.core()
+    }
+
+    fn search_stats(&self) -> Option<SearchStats> {
+        self.inner.search_stats()
+    }
+}
+
+/// Copies the CNF with clause and literal order shuffled deterministically.
+pub fn shuffled_cnf(cnf: &Cnf, seed: u64) -> Cnf {
+    let mut rng = SplitMix64(seed);
+    let mut clauses = cnf.iter().cloned().collect::<Vec<Clause>>();
+    for clause in clauses.iter_mut() {
+        let mut lits = clause.iter().copied().collect::<Vec<_>>();
+        rng.shuffle(&mut lits);
+        *clause = lits.into_iter().collect();
+    }
+    rng.shuffle(&mut clauses);
+
+    let mut shuffled = Cnf::default();
+    for clause in clauses {
+        shuffled.add_clause(clause);
+    }
+    shuffled
+}
+
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Fisher-Yates
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = (self.next() % (i as u64 + 1)) as usize;
+            items.swap(i, j);
+        }
+    }
+}
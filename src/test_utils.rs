@@ -0,0 +1,18 @@
+//! Fixtures shared by the unit tests.
+
+use crate::{
+    encoder::Encoding,
+    math::{Dimensions, Grid},
+    platform::PLATFORMS_DEFAULT,
+    world::{World, WorldGrid},
+};
+
+/// A world with terrain on every tile.
+pub(crate) fn filled_world(width: usize, height: usize) -> World {
+    World::new(WorldGrid(Grid::new_fill(Dimensions::new(width, height), true)))
+}
+
+/// The encoding of [`filled_world`] with the default platforms.
+pub(crate) fn filled_encoding(width: usize, height: usize) -> Encoding {
+    Encoding::encode(&PLATFORMS_DEFAULT, filled_world(width, height).grid())
+}
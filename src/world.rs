@@ -8,7 +8,10 @@ use serde::{
     ser::SerializeSeq,
 };
 
-use crate::math::{Dimensions, Grid};
+use crate::{
+    math::{Dimensions, Grid, Transform},
+    utils::Fnv1a,
+};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct World {
@@ -93,12 +96,35 @@ impl<'de> Deserialize<'de> for WorldGrid {
     }
 }
 
+impl WorldGrid {
+    /// See [`Grid::transformed`]. New tiles have no terrain.
+    pub fn transformed(&self, transform: Transform) -> WorldGrid {
+        WorldGrid(self.0.transformed(transform, false))
+    }
+}
+
 impl World {
     pub fn new(grid: WorldGrid) -> Self {
         World { grid }
     }
 
+    pub fn transformed(&self, transform: Transform) -> World {
+        World::new(self.grid.transformed(transform))
+    }
+
     pub fn grid(&self) -> &WorldGrid {
         &self.grid
     }
+
+    /// A hash of the terrain that stays the same across runs and builds, to
+    /// tell whether saved data was made for this world.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = Fnv1a::new();
+        hasher.write(&(self.grid.dims().width as u64).to_le_bytes());
+        hasher.write(&(self.grid.dims().height as u64).to_le_bytes());
+        for &terrain in self.grid.iter() {
+            hasher.write(&[terrain as u8]);
+        }
+        hasher.finish()
+    }
 }
@@ -0,0 +1,30 @@
+#!/bin/sh
+# Stand-in for an external SAT solver, used by the external backend tests.
+# Usage: fake_solver.sh <sat|unsat|unknown|hang> <instance.cnf>
+
+mode="$1"
+instance="$2"
+
+if ! head -n 1 "$instance" | grep -q '^p cnf '; then
+    echo "c not a DIMACS file: $instance"
+    exit 1
+fi
+
+case "$mode" in
+    sat)
+        echo "c fake solver"
+        echo "s SATISFIABLE"
+        echo "v 1 -2 0"
+        exit 10
+        ;;
+    unsat)
+        echo "s UNSATISFIABLE"
+        exit 20
+        ;;
+    hang)
+        exec sleep 60
+        ;;
+esac
+
+echo "s UNKNOWN"
+exit 0